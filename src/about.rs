@@ -0,0 +1,149 @@
+use crate::fetcher::scheme_handler::escape_html;
+use url::Url;
+
+mod blank;
+mod config;
+mod cookies;
+mod protocols;
+mod tabs;
+mod version;
+
+// About pages are built-in pages like `about:blank` or `about:tabs`. Each page is a small module that
+// generates HTML from the live state of the browser. Pages that need state from the window (like the
+// open tabs) get it through the `AboutContext`, which is collected on the GTK thread before the page
+// is rendered.
+
+/// A tab, as shown on `about:tabs`
+#[derive(Debug, Clone)]
+pub struct AboutTab {
+    pub title: String,
+    pub url: String,
+    pub pinned: bool,
+    pub private: bool,
+    pub active: bool,
+}
+
+/// State of the browser window that is needed to render about pages
+#[derive(Debug, Clone, Default)]
+pub struct AboutContext {
+    pub tabs: Vec<AboutTab>,
+}
+
+/// A single about page
+pub trait AboutPage: Sync {
+    /// Title of the page
+    fn title(&self) -> &str;
+
+    /// Generates the body of the page
+    fn render(&self, context: &AboutContext) -> String;
+}
+
+/// All about pages, keyed by their name (`about:<name>`)
+const PAGES: [(&str, &dyn AboutPage); 6] = [
+    ("blank", &blank::BlankPage),
+    ("config", &config::ConfigPage),
+    ("cookies", &cookies::CookiesPage),
+    ("protocols", &protocols::ProtocolsPage),
+    ("tabs", &tabs::TabsPage),
+    ("version", &version::VersionPage),
+];
+
+/// Returns the names of all about pages
+pub fn about_pages() -> Vec<&'static str> {
+    PAGES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Renders the about page for the given URL. Unknown pages result in a page that lists all pages.
+pub fn render_about(url: &Url, context: &AboutContext) -> String {
+    let name = url.path().to_ascii_lowercase();
+
+    match PAGES.iter().find(|(page_name, _)| *page_name == name) {
+        Some((_, page)) => html_page(page.title(), &page.render(context)),
+        None => {
+            let links: String = about_pages()
+                .iter()
+                .map(|name| format!("<li><a href=\"about:{0}\">about:{0}</a></li>\n", name))
+                .collect();
+            html_page(
+                "Unknown about: page",
+                &format!(
+                    "<p>The page about:{} does not exist. Available pages:</p><ul>{}</ul>",
+                    escape_html(&name),
+                    links
+                ),
+            )
+        }
+    }
+}
+
+/// Wraps the body in a complete HTML document
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        r#"
+        <html>
+            <head>
+                <title>{0}</title>
+            </head>
+            <body>
+                <h1>{0}</h1>
+                {1}
+            </body>
+        </html>
+        "#,
+        escape_html(title),
+        body
+    )
+}
+
+/// Renders a table with the given header and rows. All cells are escaped.
+fn html_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for cell in header {
+        html.push_str(&format!("<th>{}</th>", escape_html(cell)));
+    }
+    html.push_str("</tr>\n");
+
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", escape_html(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</table>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_router() {
+        let context = AboutContext {
+            tabs: vec![AboutTab {
+                title: "<Gosub>".into(),
+                url: "https://gosub.io/".into(),
+                pinned: true,
+                private: false,
+                active: true,
+            }],
+        };
+
+        let html = render_about(&Url::parse("about:blank").unwrap(), &context);
+        assert!(html.contains("<title>Blank page</title>"));
+
+        let html = render_about(&Url::parse("about:tabs").unwrap(), &context);
+        assert!(html.contains("&lt;Gosub&gt;"));
+        assert!(html.contains("https://gosub.io/"));
+
+        let html = render_about(&Url::parse("about:protocols").unwrap(), &context);
+        assert!(html.contains("<td>calc:</td>"));
+        assert!(html.contains("<td>about:</td>"));
+
+        let html = render_about(&Url::parse("about:nothing").unwrap(), &context);
+        assert!(html.contains("about:nothing does not exist"));
+        assert!(html.contains("href=\"about:version\""));
+    }
+}
@@ -0,0 +1,14 @@
+use crate::about::{AboutContext, AboutPage};
+
+/// `about:blank`: an empty page
+pub struct BlankPage;
+
+impl AboutPage for BlankPage {
+    fn title(&self) -> &str {
+        "Blank page"
+    }
+
+    fn render(&self, _context: &AboutContext) -> String {
+        "<p>This is a blank page</p>".to_string()
+    }
+}
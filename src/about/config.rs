@@ -0,0 +1,53 @@
+use crate::about::{html_table, AboutContext, AboutPage};
+use crate::bookmarks::bookmarks_db_path;
+use crate::containers::containers_db_path;
+use crate::cookies::{cookie_db_path, cookie_policy, permissions_db_path};
+use crate::downloads::downloads_db_path;
+use crate::fetcher::search_engine::{search_engines, search_engines_db_path};
+use crate::history::history_db_path;
+use crate::profile::profile;
+use crate::settings::{settings, settings_path};
+use std::path::Path;
+
+/// `about:config`: the current configuration of the browser
+pub struct ConfigPage;
+
+impl AboutPage for ConfigPage {
+    fn title(&self) -> &str {
+        "Configuration"
+    }
+
+    fn render(&self, _context: &AboutContext) -> String {
+        let engines = search_engines();
+        let default_engine = engines
+            .default_engine()
+            .map(|engine| engine.name.clone())
+            .unwrap_or_else(|| "none".to_string());
+
+        let mut rows: Vec<Vec<String>> = settings().entries().into_iter().map(|(name, value)| vec![name, value]).collect();
+        rows.extend([
+            vec![
+                "cookies.site_exceptions".to_string(),
+                cookie_policy().permissions().all().len().to_string(),
+            ],
+            vec!["search.default_engine".to_string(), default_engine],
+            vec!["search.engines".to_string(), engines.engines().len().to_string()],
+            vec!["profile.name".to_string(), profile().name.clone()],
+            vec!["storage.cookies".to_string(), display(&cookie_db_path())],
+            vec!["storage.permissions".to_string(), display(&permissions_db_path())],
+            vec!["storage.containers".to_string(), display(&containers_db_path())],
+            vec!["storage.search_engines".to_string(), display(&search_engines_db_path())],
+            vec!["storage.bookmarks".to_string(), display(&bookmarks_db_path())],
+            vec!["storage.history".to_string(), display(&history_db_path())],
+            vec!["storage.downloads".to_string(), display(&downloads_db_path())],
+            vec!["storage.settings".to_string(), display(&settings_path())],
+            vec!["storage.cache".to_string(), display(&profile().cache_dir)],
+        ]);
+
+        html_table(&["Name", "Value"], &rows)
+    }
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
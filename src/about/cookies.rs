@@ -0,0 +1,56 @@
+use crate::about::{html_table, AboutContext, AboutPage};
+use crate::fetcher::network_profile::{network_profiles, NetworkKind};
+
+/// `about:cookies`: all cookies that are stored in the default network profile
+pub struct CookiesPage;
+
+impl AboutPage for CookiesPage {
+    fn title(&self) -> &str {
+        "Cookies"
+    }
+
+    fn render(&self, _context: &AboutContext) -> String {
+        let profile = network_profiles().lock().unwrap().get(NetworkKind::Default);
+        let Some(jar) = profile.jar() else {
+            return "<p>Cookies are not available</p>".to_string();
+        };
+
+        let mut cookies = jar.all();
+        cookies.sort_by(|a, b| (&a.domain, a.cookie.name()).cmp(&(&b.domain, b.cookie.name())));
+
+        let rows: Vec<Vec<String>> = cookies
+            .iter()
+            .map(|stored| {
+                let cookie = &stored.cookie;
+                let expires = match cookie.expires_datetime() {
+                    Some(expires) => expires.to_string(),
+                    None => "session".to_string(),
+                };
+                let mut flags = Vec::new();
+                if cookie.secure().unwrap_or(false) {
+                    flags.push("secure");
+                }
+                if cookie.http_only().unwrap_or(false) {
+                    flags.push("httponly");
+                }
+                if stored.host_only {
+                    flags.push("host-only");
+                }
+
+                vec![
+                    stored.domain.clone(),
+                    cookie.name().to_string(),
+                    cookie.path().unwrap_or("/").to_string(),
+                    expires,
+                    flags.join(", "),
+                ]
+            })
+            .collect();
+
+        format!(
+            "<p>{} cookies in the default profile</p>\n{}",
+            cookies.len(),
+            html_table(&["Domain", "Name", "Path", "Expires", ""], &rows)
+        )
+    }
+}
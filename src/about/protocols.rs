@@ -0,0 +1,25 @@
+use crate::about::{html_table, AboutContext, AboutPage};
+use crate::fetcher::scheme_handler::scheme_handlers;
+use crate::fetcher::Fetcher;
+
+/// `about:protocols`: all protocols that can be fetched, and all schemes that are handled by the
+/// browser itself
+pub struct ProtocolsPage;
+
+impl AboutPage for ProtocolsPage {
+    fn title(&self) -> &str {
+        "Protocols"
+    }
+
+    fn render(&self, _context: &AboutContext) -> String {
+        let mut rows = vec![vec!["about:".to_string(), "Built-in browser pages".to_string()]];
+        for protocol in Fetcher::protocols_implemented() {
+            rows.push(vec![format!("{}:", protocol), "Network protocol".to_string()]);
+        }
+        for (scheme, description) in scheme_handlers().schemes() {
+            rows.push(vec![format!("{}:", scheme), description]);
+        }
+
+        html_table(&["Scheme", "Description"], &rows)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::about::{html_table, AboutContext, AboutPage};
+
+/// `about:tabs`: all tabs that are open in the window
+pub struct TabsPage;
+
+impl AboutPage for TabsPage {
+    fn title(&self) -> &str {
+        "Tabs"
+    }
+
+    fn render(&self, context: &AboutContext) -> String {
+        let rows: Vec<Vec<String>> = context
+            .tabs
+            .iter()
+            .map(|tab| {
+                let mut flags = Vec::new();
+                if tab.active {
+                    flags.push("active");
+                }
+                if tab.pinned {
+                    flags.push("pinned");
+                }
+                if tab.private {
+                    flags.push("private");
+                }
+                vec![tab.title.clone(), tab.url.clone(), flags.join(", ")]
+            })
+            .collect();
+
+        format!(
+            "<p>{} open tabs</p>\n{}",
+            context.tabs.len(),
+            html_table(&["Title", "URL", ""], &rows)
+        )
+    }
+}
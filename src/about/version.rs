@@ -0,0 +1,77 @@
+use crate::about::{html_table, AboutContext, AboutPage};
+use crate::engine::GosubEngineConfig;
+use crate::fetcher::Fetcher;
+use crate::settings::settings;
+use gosub_engine::prelude::*;
+use std::any::type_name;
+
+/// `about:version`: versions of the browser, the engine modules and the libraries it is built with
+pub struct VersionPage;
+
+impl AboutPage for VersionPage {
+    fn title(&self) -> &str {
+        "Version"
+    }
+
+    fn render(&self, _context: &AboutContext) -> String {
+        let mut rows = vec![
+            vec!["Gosub GTK browser".to_string(), env!("CARGO_PKG_VERSION").to_string()],
+            vec![
+                "GTK".to_string(),
+                format!("{}.{}.{}", gtk4::major_version(), gtk4::minor_version(), gtk4::micro_version()),
+            ],
+            vec!["HTTP agent".to_string(), http_agent().to_string()],
+            vec!["Protocols".to_string(), Fetcher::protocols_implemented().join(", ")],
+        ];
+        #[cfg(feature = "proto-http")]
+        rows.push(vec!["User agent".to_string(), settings().network.user_agent.clone()]);
+
+        let modules = vec![
+            vec![
+                "CSS system".to_string(),
+                type_name::<<GosubEngineConfig as HasCssSystem>::CssSystem>().to_string(),
+            ],
+            vec![
+                "Document".to_string(),
+                type_name::<<GosubEngineConfig as HasDocument>::Document>().to_string(),
+            ],
+            vec![
+                "HTML parser".to_string(),
+                type_name::<<GosubEngineConfig as HasHtmlParser>::HtmlParser>().to_string(),
+            ],
+            vec![
+                "Layouter".to_string(),
+                type_name::<<GosubEngineConfig as HasLayouter>::Layouter>().to_string(),
+            ],
+            vec![
+                "Render tree".to_string(),
+                type_name::<<GosubEngineConfig as HasRenderTree>::RenderTree>().to_string(),
+            ],
+            vec![
+                "Tree drawer".to_string(),
+                type_name::<<GosubEngineConfig as HasTreeDrawer>::TreeDrawer>().to_string(),
+            ],
+            vec![
+                "Render backend".to_string(),
+                type_name::<<GosubEngineConfig as HasRenderBackend>::RenderBackend>().to_string(),
+            ],
+        ];
+
+        format!(
+            "{}\n<h2>Engine modules</h2>\n{}",
+            html_table(&["Component", "Version"], &rows),
+            html_table(&["Module", "Implementation"], &modules)
+        )
+    }
+}
+
+/// Returns the name of the HTTP agent the browser is compiled with
+fn http_agent() -> &'static str {
+    if cfg!(feature = "http-agent-reqwest") {
+        "reqwest"
+    } else if cfg!(feature = "http-agent-ureq") {
+        "ureq"
+    } else {
+        "none"
+    }
+}
@@ -1,19 +1,48 @@
+use crate::bookmarks::{bookmark_store, BookmarkCompletionProvider, BookmarkId};
+use crate::command_line::{resolve_address, CommandLine};
+use crate::completion::register_completion_provider;
 use crate::dialog::about::About;
+use crate::dialog::bookmarks::BookmarksDialog;
+use crate::dialog::containers::ContainersDialog;
+use crate::dialog::downloads::DownloadsDialog;
+use crate::dialog::history::HistoryDialog;
+use crate::dialog::profiles::ProfilePickerDialog;
+use crate::dialog::search_engines::SearchEnginesDialog;
+use crate::dialog::settings::SettingsDialog;
 use crate::dialog::shortcuts::ShortcutsDialog;
+use crate::dialog::site_permissions::SitePermissionsDialog;
+use crate::fetcher::network_profile::{network_profiles, NetworkKind};
+use crate::history::HistoryCompletionProvider;
+use crate::profile::{is_profile_selected, profile, ProfileLock};
+use crate::session::{session_path, Session, SessionStore, WindowSession};
+use crate::settings::{save_settings, settings, update_settings, StartupMode};
 use crate::window::BrowserWindow;
-use crate::APP_ID;
-use gtk4::glib::clone;
+use gtk4::gio::{Menu, MenuItem, SimpleAction};
+use gtk4::glib::{clone, VariantTy};
 use gtk4::subclass::prelude::GtkApplicationImpl;
-use gtk4::{gio, glib, prelude::*, subclass::prelude::*, Settings};
+use gtk4::{gio, glib, prelude::*, subclass::prelude::*, AlertDialog, Settings, Window};
 use gtk_macros::action;
-use log::info;
+use log::{info, warn};
+use std::cell::{OnceCell, RefCell};
+use std::sync::Arc;
 
 mod imp {
     use super::*;
     use crate::window::BrowserWindow;
 
     #[derive(Default)]
-    pub struct Application {}
+    pub struct Application {
+        /// The downloads window, when it is open. There is only one for all browser windows.
+        pub downloads_window: glib::WeakRef<Window>,
+        /// Lock of the profile, held while the browser uses it
+        pub profile_lock: RefCell<Option<ProfileLock>>,
+        /// URLs from the command line that are opened as soon as the profile has been opened
+        pub pending_urls: RefCell<Option<(Vec<String>, NetworkKind)>>,
+        /// The "Recently Closed" submenu of the main menu
+        pub recently_closed_menu: OnceCell<Menu>,
+        /// The section of the "Bookmarks" menu that lists the bookmarks
+        pub bookmarks_menu: OnceCell<Menu>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for Application {
@@ -36,8 +65,49 @@ mod imp {
                 return;
             }
 
-            let window = BrowserWindow::new(&obj);
-            window.present();
+            if !is_profile_selected() {
+                ProfilePickerDialog::create_dialog(&obj).present();
+                return;
+            }
+
+            obj.open_profile();
+        }
+
+        fn command_line(&self, command_line: &gio::ApplicationCommandLine) -> glib::ExitCode {
+            info!(target: "gtk", "GtkApplication<Application>::command_line");
+
+            // The arguments have already been checked by the process that was started
+            let args: Vec<String> = command_line
+                .arguments()
+                .iter()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect();
+            let mut options = match CommandLine::parse(&args) {
+                Ok(options) => options,
+                Err(e) => {
+                    warn!(target: "gtk", "invalid command line: {}", e);
+                    return glib::ExitCode::FAILURE;
+                }
+            };
+
+            // Files are relative to the directory the browser was started in, which may differ
+            // from the directory of the running instance
+            let cwd = command_line.cwd();
+            options.urls = options.urls.iter().map(|url| resolve_address(url, cwd.as_deref())).collect();
+
+            self.obj().open_command_line(options);
+            glib::ExitCode::SUCCESS
+        }
+
+        fn open(&self, files: &[gio::File], _hint: &str) {
+            info!(target: "gtk", "GtkApplication<Application>::open");
+
+            let urls = files.iter().map(|file| file.uri().to_string()).collect();
+            self.obj().open_command_line(CommandLine {
+                urls,
+                ..CommandLine::default()
+            });
         }
 
         fn startup(&self) {
@@ -47,6 +117,17 @@ mod imp {
             let obj = self.obj();
             obj.setup_actions();
             obj.setup_accelerators();
+            obj.setup_menubar();
+
+            register_completion_provider(Arc::new(BookmarkCompletionProvider));
+            register_completion_provider(Arc::new(HistoryCompletionProvider));
+        }
+
+        fn shutdown(&self) {
+            info!(target: "gtk", "GtkApplication<Application>::shutdown");
+            save_settings();
+            self.profile_lock.take();
+            self.parent_shutdown();
         }
     }
 
@@ -60,16 +141,28 @@ glib::wrapper! {
 }
 
 impl Application {
-    pub fn new() -> Self {
+    pub fn new(application_id: &str) -> Self {
         glib::Object::builder()
-            .property("application-id", APP_ID)
+            .property("application-id", application_id)
+            .property(
+                "flags",
+                gio::ApplicationFlags::HANDLES_COMMAND_LINE | gio::ApplicationFlags::HANDLES_OPEN,
+            )
             .property("resource-base-path", Some("/io/gosub/browser-gtk"))
             .build()
     }
 
-    pub fn window(&self) -> BrowserWindow {
-        self.active_window()
-            .map_or_else(|| BrowserWindow::new(self), |w| w.downcast().unwrap())
+    /// Returns all browser windows, the most recently used window first
+    pub fn browser_windows(&self) -> Vec<BrowserWindow> {
+        self.windows()
+            .into_iter()
+            .filter_map(|window| window.downcast::<BrowserWindow>().ok())
+            .collect()
+    }
+
+    /// Returns the most recently used browser window
+    pub fn browser_window(&self) -> Option<BrowserWindow> {
+        self.browser_windows().into_iter().next()
     }
 
     fn setup_actions(&self) {
@@ -80,22 +173,35 @@ impl Application {
                 #[weak(rename_to=app)]
                 self,
                 move |_, _| {
+                    // Quitting does not close the windows, so the session is saved here
+                    app.save_session();
                     app.quit();
                 }
             )
         );
 
+        action!(
+            self,
+            "new-window",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "New window action triggered");
+                    BrowserWindow::new(&app).present();
+                }
+            )
+        );
+
         action!(
             self,
             "toggle-dark-mode",
             clone!(
-                #[weak(rename_to=_app)]
+                #[weak(rename_to=app)]
                 self,
                 move |_, _| {
                     info!(target: "gtk", "Toggle dark mode action triggered");
-                    let settings = Settings::default().expect("Failed to get default GtkSettings");
-                    let mode: bool = settings.property("gtk-application-prefer-dark-theme");
-                    settings.set_property("gtk-application-prefer-dark-theme", !mode);
+                    app.set_dark_mode(!settings().appearance.dark_mode);
                 }
             )
         );
@@ -127,20 +233,345 @@ impl Application {
                 }
             )
         );
+
+        action!(
+            self,
+            "site-permissions",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show site permissions action triggered");
+                    let dialog = SitePermissionsDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "containers",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show containers action triggered");
+                    let dialog = ContainersDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "bookmarks",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show bookmarks action triggered");
+                    let dialog = BookmarksDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "history",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show history action triggered");
+                    let dialog = HistoryDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "downloads",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show downloads action triggered");
+                    app.show_downloads();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "settings",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show settings action triggered");
+                    let dialog = SettingsDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
+
+        action!(
+            self,
+            "search-engines",
+            clone!(
+                #[weak(rename_to=app)]
+                self,
+                move |_, _| {
+                    info!(target: "gtk", "Show search engines action triggered");
+                    let dialog = SearchEnginesDialog::create_dialog(&app);
+                    dialog.present();
+                }
+            )
+        );
     }
 
     fn setup_accelerators(&self) {
         // Global application accelerators
         self.set_accels_for_action("app.quit", &["<Primary>Q"]);
-        self.set_accels_for_action("app.toggle-dark-mode", &["<Primary>D"]);
+        self.set_accels_for_action("app.new-window", &["<Primary>N"]);
+        self.set_accels_for_action("app.toggle-dark-mode", &["<Primary><Shift>D"]);
         self.set_accels_for_action("app.show-about", &["F1"]);
         self.set_accels_for_action("app.show-shortcuts", &["F2"]);
+        self.set_accels_for_action("app.history", &["<Primary>H"]);
+        self.set_accels_for_action("app.downloads", &["<Primary><Shift>Y"]);
+        self.set_accels_for_action("app.settings", &["<Primary>comma"]);
+    }
+
+    /// Sets up the main menu, which is shared by all browser windows. The bookmarks are filled in
+    /// when the profile has been opened.
+    fn setup_menubar(&self) {
+        let builder = gtk4::Builder::from_resource("/io/gosub/browser-gtk/ui/main_menu.ui");
+        let menubar = builder.object::<gio::MenuModel>("app-menu").expect("Could not find app-menu");
+        self.set_menubar(Some(&menubar));
+
+        if let Some(recently_closed) = builder.object::<Menu>("recently-closed-menu") {
+            _ = self.imp().recently_closed_menu.set(recently_closed);
+        }
+        if let Some(bookmarks) = builder.object::<Menu>("bookmarks-menu") {
+            _ = self.imp().bookmarks_menu.set(bookmarks);
+        }
+    }
+
+    /// Sets up the actions that need the settings of the profile
+    fn setup_profile_actions(&self) {
+        // What to show on startup. The state is the key of the startup mode.
+        let startup_mode_action = SimpleAction::new_stateful(
+            "startup-mode",
+            Some(VariantTy::STRING),
+            &settings().general.startup_mode.key().to_variant(),
+        );
+        startup_mode_action.connect_activate(move |action, param| {
+            let Some(mode) = param.and_then(|p| p.get::<String>()).and_then(|key| StartupMode::from_key(&key)) else {
+                return;
+            };
+            action.set_state(&mode.key().to_variant());
+            update_settings(|settings| settings.general.startup_mode = mode);
+        });
+        self.add_action(&startup_mode_action);
+    }
+
+    /// The "Recently Closed" submenu of the main menu
+    pub fn recently_closed_menu(&self) -> Option<Menu> {
+        self.imp().recently_closed_menu.get().cloned()
+    }
+
+    /// Locks the selected profile and opens a browser window for it. When another instance of the
+    /// browser uses the profile, an error is shown instead.
+    pub fn open_profile(&self) {
+        if self.imp().profile_lock.borrow().is_none() {
+            match profile().lock() {
+                Ok(lock) => {
+                    self.imp().profile_lock.replace(Some(lock));
+                }
+                Err(e) => {
+                    self.show_profile_error(&e.to_string());
+                    return;
+                }
+            }
+
+            // Logging is set up here when the profile has been picked after startup
+            crate::init_logging();
+            self.apply_dark_mode(settings().appearance.dark_mode);
+            self.setup_profile_actions();
+            self.update_bookmarks_menu();
+        }
+
+        // The windows of the previous session are restored, and the most recently used window gets
+        // the URLs of the command line
+        let mut sessions = match settings().general.startup_mode {
+            StartupMode::RestoreSession => SessionStore::new(&session_path())
+                .load()
+                .map(|session| session.windows)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let first = (!sessions.is_empty()).then(|| sessions.remove(0));
+        for session in sessions.into_iter().rev() {
+            BrowserWindow::with_urls(self, Some(session), Vec::new(), NetworkKind::Default).present();
+        }
+
+        let (urls, network) = self.imp().pending_urls.take().unwrap_or((Vec::new(), NetworkKind::Default));
+        let window = BrowserWindow::with_urls(self, first, urls, network);
+        window.present();
+    }
+
+    /// Starts the browser again with the given profile, and quits this instance. Every profile runs
+    /// under its own application id, which cannot be changed once the application has been registered.
+    /// The new process gets the same arguments, and hands them over to the running instance of the
+    /// profile when there is one.
+    pub fn restart_with_profile(&self, name: &str) -> anyhow::Result<()> {
+        info!(target: "gtk", "restarting with profile {}", name);
+        std::process::Command::new(std::env::current_exe()?)
+            .arg("--profile")
+            .arg(name)
+            .args(std::env::args_os().skip(1))
+            .spawn()?;
+
+        self.quit();
+        Ok(())
+    }
+
+    /// Writes the tabs of all browser windows to the session file
+    pub fn save_session(&self) {
+        self.write_session(&self.browser_windows());
+    }
+
+    /// Called when a browser window is about to be closed. Its tabs are dropped from the session,
+    /// unless it is the last window: the browser quits then, and the window is restored on the next
+    /// start.
+    pub fn browser_window_closing(&self, closing: &BrowserWindow) {
+        let others: Vec<BrowserWindow> = self.browser_windows().into_iter().filter(|window| window != closing).collect();
+        if others.is_empty() {
+            self.write_session(&[closing.clone()]);
+            return;
+        }
+        self.write_session(&others);
+
+        // All state of private tabs is thrown away when the last private tab is gone
+        let private_tabs_left = others
+            .iter()
+            .any(|window| window.imp().tab_manager.lock().unwrap().has_private_tabs());
+        if !private_tabs_left {
+            network_profiles().lock().unwrap().discard_private();
+        }
+    }
+
+    fn write_session(&self, windows: &[BrowserWindow]) {
+        let sessions: Vec<Option<WindowSession>> = windows.iter().map(|window| window.imp().window_session()).collect();
+        // Nothing is restored yet, so the session of the previous run should not be overwritten
+        if sessions.iter().all(Option::is_none) {
+            return;
+        }
+
+        // Windows that only have private tabs are not stored
+        let windows = sessions.into_iter().flatten().filter(|window| !window.tabs.is_empty()).collect();
+        if let Err(e) = SessionStore::new(&session_path()).save(&Session::new(windows)) {
+            log::error!("Failed to save session: {}", e);
+        }
+    }
+
+    /// Opens the URLs of a command line in the most recently used browser window, or in a new window
+    /// when asked for. URLs that arrive before the profile has been opened are opened with it.
+    pub fn open_command_line(&self, command_line: CommandLine) {
+        let network = if command_line.private {
+            NetworkKind::Private
+        } else {
+            NetworkKind::Default
+        };
+        let mut urls = command_line.urls;
+        // Without URLs, private mode opens an empty private tab
+        if urls.is_empty() && command_line.private {
+            urls.push("about:blank".to_string());
+        }
+
+        if self.imp().profile_lock.borrow().is_none() {
+            self.imp().pending_urls.replace(Some((urls, network)));
+            self.activate();
+            return;
+        }
+
+        let window = match self.browser_window() {
+            Some(window) if !command_line.new_window => {
+                window.open_urls(urls, network);
+                window
+            }
+            _ => BrowserWindow::with_urls(self, None, urls, network),
+        };
+        window.present();
+    }
+
+    /// Shows why the profile cannot be opened, and quits when the message is closed
+    fn show_profile_error(&self, message: &str) {
+        // There may not be any window that keeps the application running
+        let guard = self.hold();
+
+        let dialog = AlertDialog::builder()
+            .message("The profile cannot be opened")
+            .detail(message)
+            .modal(true)
+            .build();
+        dialog.choose(None::<&Window>, gio::Cancellable::NONE, {
+            let app = self.clone();
+            move |_| {
+                drop(guard);
+                app.quit();
+            }
+        });
+    }
+
+    /// Switches between the dark and the light theme, and remembers the choice
+    pub fn set_dark_mode(&self, dark: bool) {
+        self.apply_dark_mode(dark);
+        update_settings(|settings| settings.appearance.dark_mode = dark);
     }
 
-    pub fn run(&self) {
+    fn apply_dark_mode(&self, dark: bool) {
+        let gtk_settings = Settings::default().expect("Failed to get default GtkSettings");
+        gtk_settings.set_property("gtk-application-prefer-dark-theme", dark);
+    }
+
+    /// Shows the downloads window, or brings it to the front when it is already open
+    pub fn show_downloads(&self) {
+        if let Some(window) = self.imp().downloads_window.upgrade() {
+            window.present();
+            return;
+        }
+
+        let window = DownloadsDialog::create_dialog(self);
+        self.imp().downloads_window.set(Some(&window));
+        window.present();
+    }
+
+    /// Updates the bookmarks menu and the bookmark button of all browser windows, after the bookmarks
+    /// have been changed
+    pub fn bookmarks_changed(&self) {
+        self.update_bookmarks_menu();
+        for window in self.browser_windows() {
+            window.imp().update_bookmarks();
+        }
+    }
+
+    /// Fills the "Bookmarks" menu with the bookmarks. Folders are shown as submenus.
+    fn update_bookmarks_menu(&self) {
+        let Some(menu) = self.imp().bookmarks_menu.get() else {
+            return;
+        };
+
+        menu.remove_all();
+        append_bookmarks(menu, None);
+    }
+
+    pub fn run(&self, args: &[String]) {
         info!("Application started");
         sourceview5::init();
-        ApplicationExtManual::run(self);
+        ApplicationExtManual::run_with_args(self, args);
     }
 }
 
@@ -149,3 +580,27 @@ impl Default for Application {
         gio::Application::default().unwrap().downcast::<Application>().unwrap()
     }
 }
+
+/// Appends the bookmarks in the given folder to the menu
+fn append_bookmarks(menu: &Menu, parent: Option<BookmarkId>) {
+    for bookmark in bookmark_store().children(parent) {
+        if bookmark.is_folder() {
+            let submenu = Menu::new();
+            append_bookmarks(&submenu, Some(bookmark.id));
+            menu.append_submenu(Some(bookmark.title.as_str()), &submenu);
+            continue;
+        }
+
+        let Some(url) = &bookmark.url else {
+            continue;
+        };
+        let label = if bookmark.title.is_empty() {
+            url.as_str()
+        } else {
+            bookmark.title.as_str()
+        };
+        let item = MenuItem::new(Some(label), None);
+        item.set_action_and_target_value(Some("win.open-url"), Some(&url.as_str().to_variant()));
+        menu.append_item(&item);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::bookmarks::sqlite_store::BookmarkStore;
+use crate::completion::{CompletionCandidate, CompletionKind, CompletionProvider};
+use crate::profile::profile;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use url::Url;
+
+pub mod chromium;
+pub mod firefox;
+pub mod netscape;
+pub mod sqlite_store;
+
+/// Name of the database that holds the bookmarks
+const BOOKMARKS_DB_FILE: &str = "bookmarks.db";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BookmarkId(i64);
+
+impl fmt::Display for BookmarkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for BookmarkId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(BookmarkId)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkKind {
+    /// A bookmarked page
+    Bookmark,
+    /// A folder that contains other bookmarks and folders
+    Folder,
+}
+
+impl fmt::Display for BookmarkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookmarkKind::Bookmark => write!(f, "bookmark"),
+            BookmarkKind::Folder => write!(f, "folder"),
+        }
+    }
+}
+
+impl FromStr for BookmarkKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bookmark" => Ok(BookmarkKind::Bookmark),
+            "folder" => Ok(BookmarkKind::Folder),
+            _ => Err(anyhow::anyhow!("unknown bookmark kind: {}", s)),
+        }
+    }
+}
+
+/// A bookmark or a bookmark folder. Bookmarks without a parent are shown at the top level of the
+/// bookmarks menu.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub id: BookmarkId,
+    pub parent: Option<BookmarkId>,
+    pub kind: BookmarkKind,
+    pub title: String,
+    /// URL of the bookmarked page. Folders have no URL.
+    pub url: Option<Url>,
+    /// Optional keyword: typing the keyword in the address bar opens the bookmark
+    pub keyword: Option<String>,
+    pub tags: Vec<String>,
+    /// URL of the favicon of the bookmarked page
+    pub favicon: Option<Url>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+impl Bookmark {
+    pub fn is_folder(&self) -> bool {
+        self.kind == BookmarkKind::Folder
+    }
+}
+
+/// A bookmark or folder together with everything in it, as exchanged with other browsers
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BookmarkNode {
+    pub title: String,
+    /// URL of the bookmarked page. Folders have no URL.
+    pub url: Option<Url>,
+    pub keyword: Option<String>,
+    pub tags: Vec<String>,
+    pub favicon: Option<Url>,
+    pub added: Option<DateTime<Utc>>,
+    /// Contents of a folder
+    pub children: Vec<BookmarkNode>,
+}
+
+impl BookmarkNode {
+    pub fn is_folder(&self) -> bool {
+        self.url.is_none()
+    }
+
+    /// Returns the number of bookmarks in this node, including the bookmarks in subfolders
+    pub fn bookmark_count(&self) -> usize {
+        if !self.is_folder() {
+            return 1;
+        }
+        self.children.iter().map(|child| child.bookmark_count()).sum()
+    }
+}
+
+/// File formats that bookmarks can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// The `places.sqlite` database of Firefox
+    Firefox,
+    /// The `Bookmarks` JSON file of Chromium based browsers
+    Chromium,
+    /// A Netscape bookmarks HTML file, as exported by most browsers
+    NetscapeHtml,
+}
+
+impl ImportFormat {
+    pub const ALL: [ImportFormat; 3] = [ImportFormat::Firefox, ImportFormat::Chromium, ImportFormat::NetscapeHtml];
+
+    /// Name of the format, as shown to the user
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportFormat::Firefox => "Firefox (places.sqlite)",
+            ImportFormat::Chromium => "Chrome / Chromium (Bookmarks)",
+            ImportFormat::NetscapeHtml => "HTML file",
+        }
+    }
+
+    /// Title of the folder the imported bookmarks are added to
+    fn folder_title(&self) -> &'static str {
+        match self {
+            ImportFormat::Firefox => "Imported from Firefox",
+            ImportFormat::Chromium => "Imported from Chromium",
+            ImportFormat::NetscapeHtml => "Imported bookmarks",
+        }
+    }
+}
+
+/// Imports the bookmarks from the given file into a new folder at the top level. Returns the number
+/// of bookmarks that were imported.
+pub fn import_bookmarks(store: &BookmarkStore, format: ImportFormat, path: &Path) -> anyhow::Result<usize> {
+    let nodes = match format {
+        ImportFormat::Firefox => firefox::parse(path)?,
+        ImportFormat::Chromium => chromium::parse(&fs::read_to_string(path)?)?,
+        ImportFormat::NetscapeHtml => netscape::parse(&fs::read_to_string(path)?),
+    };
+    if nodes.is_empty() {
+        return Err(anyhow!("no bookmarks found in {}", path.display()));
+    }
+
+    let folder = store
+        .add_folder(None, format.folder_title())
+        .ok_or_else(|| anyhow!("cannot create the folder for the imported bookmarks"))?;
+    let count = store.add_tree(Some(folder.id), &nodes);
+
+    info!("imported {} bookmarks from {}", count, path.display());
+    Ok(count)
+}
+
+/// Exports all bookmarks to a Netscape bookmarks HTML file. Returns the number of bookmarks that
+/// were exported.
+pub fn export_bookmarks(store: &BookmarkStore, path: &Path) -> anyhow::Result<usize> {
+    let nodes = store.tree(None);
+    let count = nodes.iter().map(|node| node.bookmark_count()).sum();
+
+    fs::write(path, netscape::format(&nodes))?;
+
+    info!("exported {} bookmarks to {}", count, path.display());
+    Ok(count)
+}
+
+/// Parses a comma separated list of tags, as typed by the user
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Path of the database that holds the bookmarks of the current profile
+pub fn bookmarks_db_path() -> PathBuf {
+    profile().data_path(BOOKMARKS_DB_FILE)
+}
+
+/// Returns the bookmark store of the browser
+pub fn bookmark_store() -> Arc<BookmarkStore> {
+    static STORE: OnceLock<Arc<BookmarkStore>> = OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            let store = BookmarkStore::new(bookmarks_db_path()).unwrap_or_else(|e| {
+                warn!("failed to open bookmark store, using in-memory store: {:?}", e);
+                BookmarkStore::new(":memory:").expect("in-memory bookmark store")
+            });
+            Arc::new(store)
+        })
+        .clone()
+}
+
+/// Suggests bookmarks in the address bar
+pub struct BookmarkCompletionProvider;
+
+impl CompletionProvider for BookmarkCompletionProvider {
+    fn candidates(&self, query: &str, limit: usize) -> Vec<CompletionCandidate> {
+        bookmark_store()
+            .search(query, limit)
+            .into_iter()
+            .filter_map(|bookmark| {
+                Some(CompletionCandidate {
+                    url: bookmark.url?.to_string(),
+                    title: bookmark.title,
+                    kind: CompletionKind::Bookmark,
+                    visit_count: 0,
+                    last_visit: None,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("rust, gtk,,  rust ,browser "), vec!["rust", "gtk", "browser"]);
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_import_export() {
+        let dir = std::env::temp_dir();
+        let import_path = dir.join(format!("gosub_bookmarks_import_{}.html", std::process::id()));
+        let export_path = dir.join(format!("gosub_bookmarks_export_{}.html", std::process::id()));
+        fs::write(&import_path, include_str!("../tests/fixtures/bookmarks/bookmarks.html")).unwrap();
+
+        let store = BookmarkStore::new(":memory:").unwrap();
+        assert_eq!(import_bookmarks(&store, ImportFormat::NetscapeHtml, &import_path).unwrap(), 4);
+
+        let top_level = store.children(None);
+        assert_eq!(top_level.len(), 1);
+        assert_eq!(top_level[0].title, "Imported bookmarks");
+
+        // Added dates, keywords and tags are kept
+        let gosub = store.find_by_keyword("gs").unwrap();
+        assert_eq!(gosub.title, "Gosub");
+        assert_eq!(gosub.created_at.timestamp(), 1700000100);
+        assert_eq!(gosub.tags, vec!["browser", "rust"]);
+
+        assert_eq!(export_bookmarks(&store, &export_path).unwrap(), 4);
+        let exported = netscape::parse(&fs::read_to_string(&export_path).unwrap());
+        assert_eq!(exported.len(), 1);
+        let titles: Vec<&str> = exported[0].children.iter().map(|node| node.title.as_str()).collect();
+        assert_eq!(titles, vec!["Bookmarks Toolbar", "Gosub", "Empty", "Rust & friends"]);
+        assert_eq!(exported[0].children[1].keyword.as_deref(), Some("gs"));
+        assert_eq!(exported[0].children[1].added.unwrap().timestamp(), 1700000100);
+
+        // A file without bookmarks is an error, and does not create a folder
+        fs::write(&import_path, "<DL><p></DL>").unwrap();
+        assert!(import_bookmarks(&store, ImportFormat::NetscapeHtml, &import_path).is_err());
+        assert_eq!(store.children(None).len(), 1);
+
+        fs::remove_file(&import_path).unwrap();
+        fs::remove_file(&export_path).unwrap();
+    }
+
+    #[test]
+    fn test_bookmark_kind() {
+        for kind in [BookmarkKind::Bookmark, BookmarkKind::Folder] {
+            assert_eq!(kind.to_string().parse::<BookmarkKind>().unwrap(), kind);
+        }
+        assert!("separator".parse::<BookmarkKind>().is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::bookmarks::BookmarkNode;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+// Chromium based browsers (Chrome, Edge, Brave, Vivaldi) store their bookmarks in a JSON file called
+// `Bookmarks` in the profile directory. It has three root folders: the bookmarks bar, other
+// bookmarks and mobile bookmarks. Dates are microseconds since 1601-01-01 (the Windows epoch),
+// stored as strings.
+
+/// Seconds between 1601-01-01 and 1970-01-01
+const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+#[derive(Deserialize)]
+struct BookmarksFile {
+    roots: Roots,
+}
+
+#[derive(Deserialize)]
+struct Roots {
+    bookmark_bar: Option<Node>,
+    other: Option<Node>,
+    synced: Option<Node>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<String>,
+    date_added: Option<String>,
+    #[serde(default)]
+    children: Vec<Node>,
+}
+
+/// Parses a Chromium `Bookmarks` file. Every root folder that contains bookmarks is returned as a
+/// folder. Bookmarks that do not have a valid URL are skipped.
+pub fn parse(content: &str) -> anyhow::Result<Vec<BookmarkNode>> {
+    let file: BookmarksFile = serde_json::from_str(content)?;
+
+    let roots = [file.roots.bookmark_bar, file.roots.other, file.roots.synced];
+    Ok(roots
+        .into_iter()
+        .flatten()
+        .filter_map(convert)
+        .filter(|root| !root.children.is_empty())
+        .collect())
+}
+
+fn convert(node: Node) -> Option<BookmarkNode> {
+    let added = node.date_added.as_deref().and_then(parse_date);
+
+    match node.kind.as_str() {
+        "folder" => Some(BookmarkNode {
+            title: node.name,
+            added,
+            children: node.children.into_iter().filter_map(convert).collect(),
+            ..Default::default()
+        }),
+        "url" => Some(BookmarkNode {
+            title: node.name,
+            url: Some(Url::parse(node.url.as_deref()?).ok()?),
+            added,
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// Converts a Chromium timestamp (microseconds since 1601-01-01) into a date
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let micros: i64 = value.parse().ok()?;
+    if micros == 0 {
+        return None;
+    }
+    DateTime::from_timestamp(micros / 1_000_000 - WINDOWS_EPOCH_OFFSET, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/bookmarks/chromium_bookmarks.json");
+
+    #[test]
+    fn test_parse() {
+        let roots = parse(FIXTURE).unwrap();
+        let titles: Vec<&str> = roots.iter().map(|root| root.title.as_str()).collect();
+        // Empty roots are skipped
+        assert_eq!(titles, vec!["Bookmarks bar", "Other bookmarks"]);
+
+        let bar = &roots[0];
+        assert_eq!(bar.children.len(), 2);
+        assert_eq!(bar.children[0].title, "Gosub");
+        assert_eq!(bar.children[0].url.as_ref().unwrap().as_str(), "https://gosub.io/");
+        assert_eq!(bar.children[0].added.unwrap().timestamp(), 1700000000);
+
+        let folder = &bar.children[1];
+        assert!(folder.is_folder());
+        assert_eq!(folder.title, "Rust");
+        assert_eq!(folder.children.len(), 1);
+        assert_eq!(folder.children[0].title, "Docs.rs");
+
+        // The bookmark with an invalid URL is skipped
+        assert_eq!(roots[1].children.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("not json").is_err());
+        assert!(parse(r#"{"roots": {}}"#).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::bookmarks::BookmarkNode;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::Path;
+use url::Url;
+
+// Firefox stores bookmarks and history in `places.sqlite` in the profile directory. Bookmarks live
+// in `moz_bookmarks` (type 1 is a bookmark, type 2 a folder, type 3 a separator) and point to their
+// URL in `moz_places`. Tags are folders below the tags root that contain a bookmark for every
+// tagged URL, and keywords are stored per URL in `moz_keywords`. Dates are microseconds since
+// 1970-01-01.
+
+/// Bookmark type of a bookmark in `moz_bookmarks`
+const TYPE_BOOKMARK: i64 = 1;
+/// Bookmark type of a folder in `moz_bookmarks`
+const TYPE_FOLDER: i64 = 2;
+
+/// Root folders that hold the bookmarks of the user, by their fixed GUID
+const ROOTS: [(&str, &str); 4] = [
+    ("toolbar_____", "Bookmarks Toolbar"),
+    ("menu________", "Bookmarks Menu"),
+    ("unfiled_____", "Other Bookmarks"),
+    ("mobile______", "Mobile Bookmarks"),
+];
+
+/// GUID of the root folder that holds the tags
+const TAGS_ROOT: &str = "tags________";
+
+/// Reads the bookmarks from a Firefox `places.sqlite` database. Every root folder that contains
+/// bookmarks is returned as a folder. Firefox locks the database while it is running, so the
+/// database should be copied (or Firefox closed) before importing.
+pub fn parse(path: &Path) -> anyhow::Result<Vec<BookmarkNode>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut roots = Vec::new();
+    for (guid, title) in ROOTS {
+        let root: Option<(i64, Option<i64>)> = conn
+            .query_row("SELECT id, dateAdded FROM moz_bookmarks WHERE guid = ?1", params![guid], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+        let Some((id, added)) = root else {
+            continue;
+        };
+
+        let children = read_folder(&conn, id)?;
+        if children.is_empty() {
+            continue;
+        }
+        roots.push(BookmarkNode {
+            title: title.to_string(),
+            added: added.and_then(parse_date),
+            children,
+            ..Default::default()
+        });
+    }
+
+    Ok(roots)
+}
+
+/// Reads the contents of the given folder, including its subfolders
+fn read_folder(conn: &Connection, folder_id: i64) -> anyhow::Result<Vec<BookmarkNode>> {
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.type, b.title, b.dateAdded, b.fk, p.url FROM moz_bookmarks b
+         LEFT JOIN moz_places p ON p.id = b.fk
+         WHERE b.parent = ?1 ORDER BY b.position",
+    )?;
+    let rows = stmt
+        .query_map(params![folder_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut nodes = Vec::new();
+    for (id, kind, title, added, place_id, url) in rows {
+        let title = title.unwrap_or_default();
+        let added = added.and_then(parse_date);
+
+        match kind {
+            TYPE_FOLDER => nodes.push(BookmarkNode {
+                title,
+                added,
+                children: read_folder(conn, id)?,
+                ..Default::default()
+            }),
+            TYPE_BOOKMARK => {
+                // Smart bookmarks ("place:" queries) only make sense within Firefox
+                let Some(url) = url.and_then(|url| Url::parse(&url).ok()).filter(|url| url.scheme() != "place") else {
+                    continue;
+                };
+                let Some(place_id) = place_id else {
+                    continue;
+                };
+
+                nodes.push(BookmarkNode {
+                    title,
+                    url: Some(url),
+                    keyword: read_keyword(conn, place_id)?,
+                    tags: read_tags(conn, place_id)?,
+                    added,
+                    ..Default::default()
+                });
+            }
+            // Separators are not supported
+            _ => {}
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn read_keyword(conn: &Connection, place_id: i64) -> anyhow::Result<Option<String>> {
+    Ok(conn
+        .query_row("SELECT keyword FROM moz_keywords WHERE place_id = ?1", params![place_id], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+/// Returns the tags of a URL: the titles of the tag folders that contain a bookmark for it
+fn read_tags(conn: &Connection, place_id: i64) -> anyhow::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT tag.title FROM moz_bookmarks b
+         JOIN moz_bookmarks tag ON tag.id = b.parent
+         JOIN moz_bookmarks root ON root.id = tag.parent
+         WHERE root.guid = ?1 AND b.fk = ?2
+         ORDER BY tag.title",
+    )?;
+    let tags = stmt
+        .query_map(params![TAGS_ROOT, place_id], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tags)
+}
+
+/// Converts a Firefox timestamp (microseconds since 1970-01-01) into a date
+fn parse_date(micros: i64) -> Option<DateTime<Utc>> {
+    if micros <= 0 {
+        return None;
+    }
+    DateTime::from_timestamp(micros / 1_000_000, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/bookmarks/places.sql");
+
+    #[test]
+    fn test_parse() {
+        let path = std::env::temp_dir().join(format!("gosub_places_test_{}.sqlite", std::process::id()));
+        _ = fs::remove_file(&path);
+        Connection::open(&path).unwrap().execute_batch(FIXTURE).unwrap();
+
+        let roots = parse(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Empty roots and the tags root are skipped
+        let titles: Vec<&str> = roots.iter().map(|root| root.title.as_str()).collect();
+        assert_eq!(titles, vec!["Bookmarks Toolbar", "Bookmarks Menu"]);
+
+        let toolbar = &roots[0];
+        let titles: Vec<&str> = toolbar.children.iter().map(|node| node.title.as_str()).collect();
+        assert_eq!(titles, vec!["Gosub", "Rust"]);
+
+        let gosub = &toolbar.children[0];
+        assert_eq!(gosub.url.as_ref().unwrap().as_str(), "https://gosub.io/");
+        assert_eq!(gosub.added.unwrap().timestamp(), 1700000000);
+        assert_eq!(gosub.keyword.as_deref(), Some("gs"));
+        assert_eq!(gosub.tags, vec!["browser", "rust"]);
+
+        let rust = &toolbar.children[1];
+        assert!(rust.is_folder());
+        assert_eq!(rust.children.len(), 1);
+        assert_eq!(rust.children[0].title, "Docs.rs");
+        assert!(rust.children[0].tags.is_empty());
+
+        // Separators and smart bookmarks are skipped
+        let menu = &roots[1];
+        assert_eq!(menu.children.len(), 1);
+        assert_eq!(menu.children[0].title, "Example");
+    }
+}
@@ -0,0 +1,262 @@
+use crate::bookmarks::{parse_tags, BookmarkNode};
+use chrono::DateTime;
+use std::fmt::Write;
+use url::Url;
+
+// The Netscape bookmark file is the HTML format every browser can import and export. Folders are
+// `<H3>` headers followed by a `<DL>` list with their contents, bookmarks are `<A>` links:
+//
+//   <DL><p>
+//       <DT><H3 ADD_DATE="1700000000">Folder</H3>
+//       <DL><p>
+//           <DT><A HREF="https://gosub.io/" ADD_DATE="1700000000" TAGS="rust,browser">Gosub</A>
+//       </DL><p>
+//   </DL><p>
+//
+// The files are not valid HTML (most end tags are missing), so they are read with a small tag
+// scanner instead of an HTML parser. Dates are unix timestamps in seconds.
+
+/// Header of every exported bookmarks file
+const NETSCAPE_HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+";
+
+/// Parses a Netscape bookmarks file into the bookmarks and folders at its top level. Links that do
+/// not have a valid URL are skipped.
+pub fn parse(content: &str) -> Vec<BookmarkNode> {
+    // Folders that are being read, the outermost first. The bottom folder holds the top level.
+    let mut folders = vec![BookmarkNode::default()];
+    // A folder header that has been read, but whose list has not started yet
+    let mut pending_folder: Option<BookmarkNode> = None;
+    let mut top_level_started = false;
+
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let (name, attributes) = split_tag(&rest[..end]);
+        rest = &rest[end + 1..];
+
+        // The text of a header or link runs until the next tag
+        let text = decode_entities(rest[..rest.find('<').unwrap_or(rest.len())].trim());
+
+        match name.as_str() {
+            "DL" if !top_level_started => top_level_started = true,
+            "DL" => folders.push(pending_folder.take().unwrap_or_default()),
+            "/DL" => {
+                // A folder without a list is an empty folder
+                if let Some(folder) = pending_folder.take() {
+                    folders.last_mut().unwrap().children.push(folder);
+                }
+                if folders.len() > 1 {
+                    let folder = folders.pop().unwrap();
+                    folders.last_mut().unwrap().children.push(folder);
+                }
+            }
+            "H3" => {
+                if let Some(folder) = pending_folder.take() {
+                    folders.last_mut().unwrap().children.push(folder);
+                }
+                pending_folder = Some(BookmarkNode {
+                    title: text,
+                    added: date_attribute(&attributes, "ADD_DATE"),
+                    ..Default::default()
+                });
+            }
+            "A" => {
+                if let Some(folder) = pending_folder.take() {
+                    folders.last_mut().unwrap().children.push(folder);
+                }
+                let Some(url) = attribute(&attributes, "HREF").and_then(|href| Url::parse(&href).ok()) else {
+                    continue;
+                };
+
+                folders.last_mut().unwrap().children.push(BookmarkNode {
+                    title: text,
+                    url: Some(url),
+                    keyword: attribute(&attributes, "SHORTCUTURL").filter(|keyword| !keyword.is_empty()),
+                    tags: attribute(&attributes, "TAGS").map(|tags| parse_tags(&tags)).unwrap_or_default(),
+                    favicon: attribute(&attributes, "ICON_URI").and_then(|icon| Url::parse(&icon).ok()),
+                    added: date_attribute(&attributes, "ADD_DATE"),
+                    children: Vec::new(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Lists that are not closed at the end of the file
+    if let Some(folder) = pending_folder.take() {
+        folders.last_mut().unwrap().children.push(folder);
+    }
+    while folders.len() > 1 {
+        let folder = folders.pop().unwrap();
+        folders.last_mut().unwrap().children.push(folder);
+    }
+
+    folders.pop().unwrap().children
+}
+
+/// Splits the contents of a tag into its (uppercase) name and its attributes. Attribute names are
+/// uppercase as well.
+fn split_tag(tag: &str) -> (String, Vec<(String, String)>) {
+    let tag = tag.trim().trim_end_matches('/');
+    let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let attribute_name = rest[..name_end].to_ascii_uppercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(value) = rest.strip_prefix('=') else {
+            attributes.push((attribute_name, String::new()));
+            continue;
+        };
+        let value = value.trim_start();
+
+        let (raw, after) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        attributes.push((attribute_name, decode_entities(raw)));
+        rest = after;
+    }
+
+    (name.to_ascii_uppercase(), attributes)
+}
+
+fn attribute(attributes: &[(String, String)], name: &str) -> Option<String> {
+    attributes.iter().find(|(n, _)| n == name).map(|(_, value)| value.clone())
+}
+
+fn date_attribute(attributes: &[(String, String)], name: &str) -> Option<DateTime<chrono::Utc>> {
+    let timestamp = attribute(attributes, name)?.parse::<i64>().ok()?;
+    DateTime::from_timestamp(timestamp, 0)
+}
+
+/// Decodes the entities that browsers use in bookmark files
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+fn encode_entities(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats the given bookmarks and folders as a Netscape bookmarks file
+pub fn format(nodes: &[BookmarkNode]) -> String {
+    let mut out = String::from(NETSCAPE_HEADER);
+    out.push_str("<DL><p>\n");
+    format_nodes(&mut out, nodes, 1);
+    out.push_str("</DL><p>\n");
+    out
+}
+
+fn format_nodes(out: &mut String, nodes: &[BookmarkNode], depth: usize) {
+    let indent = "    ".repeat(depth);
+
+    for node in nodes {
+        let add_date = node
+            .added
+            .map(|added| format!(" ADD_DATE=\"{}\"", added.timestamp()))
+            .unwrap_or_default();
+
+        let Some(url) = &node.url else {
+            _ = writeln!(out, "{}<DT><H3{}>{}</H3>", indent, add_date, encode_entities(&node.title));
+            _ = writeln!(out, "{}<DL><p>", indent);
+            format_nodes(out, &node.children, depth + 1);
+            _ = writeln!(out, "{}</DL><p>", indent);
+            continue;
+        };
+
+        let mut attributes = format!("HREF=\"{}\"{}", encode_entities(url.as_str()), add_date);
+        if let Some(favicon) = &node.favicon {
+            _ = write!(attributes, " ICON_URI=\"{}\"", encode_entities(favicon.as_str()));
+        }
+        if let Some(keyword) = &node.keyword {
+            _ = write!(attributes, " SHORTCUTURL=\"{}\"", encode_entities(keyword));
+        }
+        if !node.tags.is_empty() {
+            _ = write!(attributes, " TAGS=\"{}\"", encode_entities(&node.tags.join(",")));
+        }
+        _ = writeln!(out, "{}<DT><A {}>{}</A>", indent, attributes, encode_entities(&node.title));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/bookmarks/bookmarks.html");
+
+    #[test]
+    fn test_parse() {
+        let nodes = parse(FIXTURE);
+        let titles: Vec<&str> = nodes.iter().map(|node| node.title.as_str()).collect();
+        assert_eq!(titles, vec!["Bookmarks Toolbar", "Gosub", "Empty", "Rust & friends"]);
+
+        let toolbar = &nodes[0];
+        assert!(toolbar.is_folder());
+        assert_eq!(toolbar.added.unwrap().timestamp(), 1700000000);
+        assert_eq!(toolbar.children.len(), 2);
+        assert_eq!(toolbar.children[1].title, "Nested");
+        assert_eq!(toolbar.children[1].children[0].url.as_ref().unwrap().as_str(), "https://docs.rs/");
+
+        let gosub = &nodes[1];
+        assert_eq!(gosub.url.as_ref().unwrap().as_str(), "https://gosub.io/");
+        assert_eq!(gosub.keyword.as_deref(), Some("gs"));
+        assert_eq!(gosub.tags, vec!["browser", "rust"]);
+        assert_eq!(gosub.added.unwrap().timestamp(), 1700000100);
+        assert_eq!(gosub.favicon.as_ref().unwrap().as_str(), "https://gosub.io/favicon.ico");
+
+        assert!(nodes[2].is_folder());
+        assert!(nodes[2].children.is_empty());
+
+        // Entities are decoded, and links without a valid URL are skipped
+        assert_eq!(nodes[3].children.len(), 1);
+        assert_eq!(nodes[3].children[0].title, "\"The\" <Book>");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let nodes = parse(FIXTURE);
+        let output = format(&nodes);
+
+        assert!(output.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+        assert_eq!(parse(&output), nodes);
+    }
+}
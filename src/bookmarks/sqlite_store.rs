@@ -0,0 +1,445 @@
+use crate::bookmarks::{Bookmark, BookmarkId, BookmarkKind, BookmarkNode};
+use chrono::{DateTime, Utc};
+use log::warn;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use std::path::Path;
+use std::sync::Mutex;
+use url::Url;
+
+/// Columns that are selected for a bookmark, in the order `row_to_bookmark` expects them
+const COLUMNS: &str = "id, parent_id, kind, title, url, keyword, favicon, created_at, modified_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM bookmark_tags WHERE bookmark_id = bookmarks.id)";
+
+/// Stores the bookmarks in a SQLite database. Bookmarks are kept in order within their parent folder.
+pub struct BookmarkStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl BookmarkStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        // Removing a folder removes everything in it
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS bookmarks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                parent_id INTEGER REFERENCES bookmarks (id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                title TEXT NOT NULL,
+                url TEXT,
+                keyword TEXT,
+                favicon TEXT,
+                position INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                modified_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS bookmark_tags (
+                bookmark_id INTEGER NOT NULL REFERENCES bookmarks (id) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (bookmark_id, tag)
+            )",
+            [],
+        )?;
+
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_parent ON bookmarks (parent_id, position)", [])?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_url ON bookmarks (url)", [])?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Adds a bookmark at the end of the given folder and returns it
+    pub fn add_bookmark(&self, parent: Option<BookmarkId>, title: &str, url: &Url, favicon: Option<&Url>) -> Option<Bookmark> {
+        self.add(parent, BookmarkKind::Bookmark, title, Some(url), favicon, Utc::now())
+    }
+
+    /// Adds a folder at the end of the given folder and returns it
+    pub fn add_folder(&self, parent: Option<BookmarkId>, title: &str) -> Option<Bookmark> {
+        self.add(parent, BookmarkKind::Folder, title, None, None, Utc::now())
+    }
+
+    /// Adds the given bookmarks and folders, with everything in them, at the end of the given folder.
+    /// Returns the number of bookmarks that were added.
+    pub fn add_tree(&self, parent: Option<BookmarkId>, nodes: &[BookmarkNode]) -> usize {
+        let mut count = 0;
+
+        for node in nodes {
+            let kind = if node.is_folder() {
+                BookmarkKind::Folder
+            } else {
+                BookmarkKind::Bookmark
+            };
+            let created_at = node.added.unwrap_or_else(Utc::now);
+            let Some(mut bookmark) = self.add(parent, kind, &node.title, node.url.as_ref(), node.favicon.as_ref(), created_at) else {
+                continue;
+            };
+
+            if node.is_folder() {
+                count += self.add_tree(Some(bookmark.id), &node.children);
+                continue;
+            }
+
+            if node.keyword.is_some() || !node.tags.is_empty() {
+                bookmark.keyword = node.keyword.clone();
+                bookmark.tags = node.tags.clone();
+                self.update(&bookmark);
+            }
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Returns the bookmarks and folders in the given folder, with everything in them
+    pub fn tree(&self, parent: Option<BookmarkId>) -> Vec<BookmarkNode> {
+        self.children(parent)
+            .into_iter()
+            .map(|bookmark| BookmarkNode {
+                children: if bookmark.is_folder() {
+                    self.tree(Some(bookmark.id))
+                } else {
+                    Vec::new()
+                },
+                title: bookmark.title,
+                url: bookmark.url,
+                keyword: bookmark.keyword,
+                tags: bookmark.tags,
+                favicon: bookmark.favicon,
+                added: Some(bookmark.created_at),
+            })
+            .collect()
+    }
+
+    fn add(
+        &self,
+        parent: Option<BookmarkId>,
+        kind: BookmarkKind,
+        title: &str,
+        url: Option<&Url>,
+        favicon: Option<&Url>,
+        created_at: DateTime<Utc>,
+    ) -> Option<Bookmark> {
+        let conn = self.conn.lock().unwrap();
+        let e = conn.execute(
+            "INSERT INTO bookmarks (parent_id, kind, title, url, favicon, position, created_at, modified_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(position) + 1, 0) FROM bookmarks WHERE parent_id IS ?1), ?6, ?7)",
+            params![
+                parent.map(|id| id.0),
+                kind.to_string(),
+                title,
+                url.map(|url| url.to_string()),
+                favicon.map(|url| url.to_string()),
+                created_at.timestamp(),
+                Utc::now().timestamp()
+            ],
+        );
+        if let Err(e) = e {
+            warn!("failed to store bookmark: {:?}", e);
+            return None;
+        }
+        let id = BookmarkId(conn.last_insert_rowid());
+        drop(conn);
+
+        self.get(id)
+    }
+
+    /// Stores the title, URL, keyword, tags and favicon of the given bookmark. Any other bookmark with
+    /// the same keyword loses its keyword.
+    pub fn update(&self, bookmark: &Bookmark) {
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| -> Result<(), rusqlite::Error> {
+            let tx = conn.transaction()?;
+
+            if let Some(keyword) = &bookmark.keyword {
+                tx.execute(
+                    "UPDATE bookmarks SET keyword = NULL WHERE keyword = ?1 AND id != ?2",
+                    params![keyword, bookmark.id.0],
+                )?;
+            }
+            tx.execute(
+                "UPDATE bookmarks SET title = ?1, url = ?2, keyword = ?3, favicon = ?4, modified_at = ?5 WHERE id = ?6",
+                params![
+                    bookmark.title,
+                    bookmark.url.as_ref().map(|url| url.to_string()),
+                    bookmark.keyword,
+                    bookmark.favicon.as_ref().map(|url| url.to_string()),
+                    Utc::now().timestamp(),
+                    bookmark.id.0
+                ],
+            )?;
+
+            tx.execute("DELETE FROM bookmark_tags WHERE bookmark_id = ?1", params![bookmark.id.0])?;
+            for tag in &bookmark.tags {
+                // Tags are stored comma separated when they are read back
+                tx.execute(
+                    "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag) VALUES (?1, ?2)",
+                    params![bookmark.id.0, tag.replace(',', " ")],
+                )?;
+            }
+
+            tx.commit()
+        })();
+        if let Err(e) = result {
+            warn!("failed to update bookmark: {:?}", e);
+        }
+    }
+
+    /// Removes the given bookmark. Removing a folder removes everything in it.
+    pub fn remove(&self, id: BookmarkId) {
+        let e = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM bookmarks WHERE id = ?1", params![id.0]);
+        if let Err(e) = e {
+            warn!("failed to remove bookmark: {:?}", e);
+        }
+    }
+
+    /// Moves the bookmark into the given folder, at the given position within that folder. A folder
+    /// cannot be moved into itself or into one of its subfolders. Returns false when the bookmark
+    /// has not been moved.
+    pub fn move_to(&self, id: BookmarkId, parent: Option<BookmarkId>, position: usize) -> bool {
+        let mut ancestor = parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                return false;
+            }
+            ancestor = self.get(ancestor_id).and_then(|folder| folder.parent);
+        }
+        if parent.is_some_and(|parent| !self.get(parent).is_some_and(|folder| folder.is_folder())) {
+            return false;
+        }
+
+        let mut siblings: Vec<BookmarkId> = self
+            .children(parent)
+            .into_iter()
+            .map(|bookmark| bookmark.id)
+            .filter(|sibling| *sibling != id)
+            .collect();
+        siblings.insert(position.min(siblings.len()), id);
+
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| -> Result<(), rusqlite::Error> {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "UPDATE bookmarks SET parent_id = ?1, modified_at = ?2 WHERE id = ?3",
+                params![parent.map(|id| id.0), Utc::now().timestamp(), id.0],
+            )?;
+            for (position, sibling) in siblings.iter().enumerate() {
+                tx.execute(
+                    "UPDATE bookmarks SET position = ?1 WHERE id = ?2",
+                    params![position as i64, sibling.0],
+                )?;
+            }
+            tx.commit()
+        })();
+
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("failed to move bookmark: {:?}", e);
+                false
+            }
+        }
+    }
+
+    pub fn get(&self, id: BookmarkId) -> Option<Bookmark> {
+        self.select("id = ?1", [id.0]).pop()
+    }
+
+    /// Returns the bookmarks and folders in the given folder, in order. `None` returns the top level.
+    pub fn children(&self, parent: Option<BookmarkId>) -> Vec<Bookmark> {
+        self.select("parent_id IS ?1 ORDER BY position, id", [parent.map(|id| id.0)])
+    }
+
+    /// Returns all bookmarks of the given page
+    pub fn find_by_url(&self, url: &Url) -> Vec<Bookmark> {
+        self.select("kind = 'bookmark' AND url = ?1 ORDER BY id", [url.to_string()])
+    }
+
+    pub fn find_by_keyword(&self, keyword: &str) -> Option<Bookmark> {
+        self.select("kind = 'bookmark' AND keyword = ?1", [keyword]).pop()
+    }
+
+    /// Returns the most recently changed bookmarks where every word of the query is found in the
+    /// title, URL, keyword or tags
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Bookmark> {
+        let words: Vec<String> = query.split_whitespace().map(like_pattern).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let conditions: Vec<String> = (1..=words.len())
+            .map(|i| {
+                format!(
+                    "(title LIKE ?{i} ESCAPE '\\' OR url LIKE ?{i} ESCAPE '\\' OR keyword LIKE ?{i} ESCAPE '\\'
+                      OR id IN (SELECT bookmark_id FROM bookmark_tags WHERE tag LIKE ?{i} ESCAPE '\\'))"
+                )
+            })
+            .collect();
+        let condition = format!(
+            "kind = 'bookmark' AND {} ORDER BY modified_at DESC, id DESC LIMIT {}",
+            conditions.join(" AND "),
+            limit
+        );
+
+        self.select(&condition, params_from_iter(words))
+    }
+
+    /// Returns the bookmarks that match the given SQL condition
+    fn select(&self, condition: &str, params: impl rusqlite::Params) -> Vec<Bookmark> {
+        let locked_conn = self.conn.lock().unwrap();
+
+        let stmt = locked_conn.prepare(&format!("SELECT {} FROM bookmarks WHERE {}", COLUMNS, condition));
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let Ok(rows) = stmt.query_map(params, row_to_bookmark) else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok()).collect()
+    }
+}
+
+fn row_to_bookmark(row: &Row) -> Result<Bookmark, rusqlite::Error> {
+    let kind: String = row.get(2)?;
+    let url: Option<String> = row.get(4)?;
+    let favicon: Option<String> = row.get(6)?;
+    let tags: Option<String> = row.get(9)?;
+
+    Ok(Bookmark {
+        id: BookmarkId(row.get(0)?),
+        parent: row.get::<_, Option<i64>>(1)?.map(BookmarkId),
+        kind: kind.parse().unwrap_or(BookmarkKind::Bookmark),
+        title: row.get(3)?,
+        url: url.and_then(|url| Url::parse(&url).ok()),
+        keyword: row.get(5)?,
+        tags: tags
+            .map(|tags| tags.split(',').map(|tag| tag.to_string()).collect())
+            .unwrap_or_default(),
+        favicon: favicon.and_then(|url| Url::parse(&url).ok()),
+        created_at: DateTime::from_timestamp(row.get(7)?, 0).unwrap_or_default(),
+        modified_at: DateTime::from_timestamp(row.get(8)?, 0).unwrap_or_default(),
+    })
+}
+
+/// Returns a LIKE pattern that matches the given text anywhere
+fn like_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn titles(bookmarks: &[Bookmark]) -> Vec<&str> {
+        bookmarks.iter().map(|bookmark| bookmark.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_folders() {
+        let store = BookmarkStore::new(":memory:").unwrap();
+        let gosub = store
+            .add_bookmark(None, "Gosub", &url("https://gosub.io/"), Some(&url("https://gosub.io/favicon.ico")))
+            .unwrap();
+        let folder = store.add_folder(None, "Rust").unwrap();
+        let docs = store.add_bookmark(Some(folder.id), "Docs", &url("https://docs.rs/"), None).unwrap();
+        store
+            .add_bookmark(Some(folder.id), "Crates", &url("https://crates.io/"), None)
+            .unwrap();
+
+        assert!(folder.is_folder());
+        assert_eq!(folder.url, None);
+        assert_eq!(gosub.favicon, Some(url("https://gosub.io/favicon.ico")));
+        assert_eq!(titles(&store.children(None)), vec!["Gosub", "Rust"]);
+        assert_eq!(titles(&store.children(Some(folder.id))), vec!["Docs", "Crates"]);
+        assert_eq!(store.find_by_url(&url("https://docs.rs/")), vec![docs]);
+
+        // Removing a folder removes its contents
+        store.remove(folder.id);
+        assert_eq!(titles(&store.children(None)), vec!["Gosub"]);
+        assert!(store.find_by_url(&url("https://docs.rs/")).is_empty());
+    }
+
+    #[test]
+    fn test_move() {
+        let store = BookmarkStore::new(":memory:").unwrap();
+        let a = store.add_bookmark(None, "A", &url("https://a.example/"), None).unwrap();
+        let b = store.add_bookmark(None, "B", &url("https://b.example/"), None).unwrap();
+        let folder = store.add_folder(None, "Folder").unwrap();
+        let subfolder = store.add_folder(Some(folder.id), "Subfolder").unwrap();
+
+        assert!(store.move_to(b.id, None, 0));
+        assert_eq!(titles(&store.children(None)), vec!["B", "A", "Folder"]);
+
+        assert!(store.move_to(a.id, Some(folder.id), 0));
+        assert_eq!(titles(&store.children(None)), vec!["B", "Folder"]);
+        assert_eq!(titles(&store.children(Some(folder.id))), vec!["A", "Subfolder"]);
+        assert_eq!(store.get(a.id).unwrap().parent, Some(folder.id));
+
+        // Positions past the end append the bookmark
+        assert!(store.move_to(a.id, Some(folder.id), 10));
+        assert_eq!(titles(&store.children(Some(folder.id))), vec!["Subfolder", "A"]);
+
+        // A folder cannot be moved into itself or its subfolders, and bookmarks are not folders
+        assert!(!store.move_to(folder.id, Some(folder.id), 0));
+        assert!(!store.move_to(folder.id, Some(subfolder.id), 0));
+        assert!(!store.move_to(b.id, Some(a.id), 0));
+        assert_eq!(store.get(folder.id).unwrap().parent, None);
+    }
+
+    #[test]
+    fn test_update_and_keywords() {
+        let store = BookmarkStore::new(":memory:").unwrap();
+        let mut docs = store.add_bookmark(None, "Docs", &url("https://docs.rs/"), None).unwrap();
+        let mut crates = store.add_bookmark(None, "Crates", &url("https://crates.io/"), None).unwrap();
+
+        docs.keyword = Some("rs".into());
+        docs.tags = vec!["rust".into(), "docs".into()];
+        store.update(&docs);
+
+        let stored = store.get(docs.id).unwrap();
+        assert_eq!(stored.keyword, Some("rs".into()));
+        assert_eq!(stored.tags.len(), 2);
+        assert!(stored.tags.contains(&"rust".to_string()));
+        assert_eq!(store.find_by_keyword("rs").unwrap().id, docs.id);
+
+        // A keyword belongs to a single bookmark
+        crates.keyword = Some("rs".into());
+        store.update(&crates);
+        assert_eq!(store.find_by_keyword("rs").unwrap().id, crates.id);
+        assert_eq!(store.get(docs.id).unwrap().keyword, None);
+    }
+
+    #[test]
+    fn test_search() {
+        let store = BookmarkStore::new(":memory:").unwrap();
+        let mut docs = store.add_bookmark(None, "Docs.rs", &url("https://docs.rs/"), None).unwrap();
+        docs.tags = vec!["documentation".into()];
+        store.update(&docs);
+        store.add_bookmark(None, "Crates", &url("https://crates.io/"), None).unwrap();
+        store.add_folder(None, "Docs folder").unwrap();
+        store.add_bookmark(None, "100% rust", &url("https://rust.example/"), None).unwrap();
+
+        assert_eq!(titles(&store.search("docs", 10)), vec!["Docs.rs"]);
+        assert_eq!(titles(&store.search("document", 10)), vec!["Docs.rs"]);
+        assert_eq!(titles(&store.search("crates io", 10)), vec!["Crates"]);
+        assert_eq!(titles(&store.search("0%", 10)), vec!["100% rust"]);
+        assert!(store.search("crates docs", 10).is_empty());
+        assert!(store.search("  ", 10).is_empty());
+        assert_eq!(store.search("s", 2).len(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::settings::LogLevel;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Usage text that is shown for `--help` and after an invalid command line
+pub const USAGE: &str = "\
+Usage: gosub-gtk [OPTIONS] [URL...]
+
+Opens the given URLs or files in new tabs. When the browser is already running with the same
+profile, the URLs are opened in the running browser.
+
+Options:
+  --new-window                      open the URLs in a new window
+  --private                         open the URLs in private tabs
+  --profile <name>                  use the profile with the given name
+  --profile-dir <path>              use the profile stored in the given directory
+  --log-level <level>               log level of all targets: off, error, warn, info, debug or trace
+  --headless-screenshot <png> <url> render the page without a window, save it and exit
+  --import-cookies <file>           import a Netscape cookies.txt file and exit
+  --export-cookies <file>           export all cookies to a Netscape cookies.txt file and exit
+  --cookie-domain <domain>          only import/export cookies for this domain
+  --include-session-cookies         also export session cookies
+  --help                            show this help and exit";
+
+/// Options that are given on the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandLine {
+    /// URLs, addresses or files to open
+    pub urls: Vec<String>,
+    pub new_window: bool,
+    pub private: bool,
+    pub profile: Option<String>,
+    pub profile_dir: Option<PathBuf>,
+    /// Overrides the log levels in the settings
+    pub log_level: Option<LogLevel>,
+    /// File the screenshot is saved to, and the URL of the page
+    pub headless_screenshot: Option<(PathBuf, String)>,
+    pub import_cookies: Option<PathBuf>,
+    pub export_cookies: Option<PathBuf>,
+    pub cookie_domain: Option<String>,
+    pub include_session_cookies: bool,
+    pub help: bool,
+}
+
+impl CommandLine {
+    /// Parses the arguments, without the name of the program. Everything after `--` is a URL, even
+    /// when it starts with dashes.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut command_line = CommandLine::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| iter.next().cloned().ok_or_else(|| format!("{} needs a value", name));
+
+            match arg.as_str() {
+                "--new-window" => command_line.new_window = true,
+                "--private" => command_line.private = true,
+                "--profile" => command_line.profile = Some(value(arg)?),
+                "--profile-dir" => command_line.profile_dir = Some(PathBuf::from(value(arg)?)),
+                "--log-level" => {
+                    let level = value(arg)?;
+                    let level = LogLevel::from_key(&level).ok_or_else(|| format!("Unknown log level \"{}\"", level))?;
+                    command_line.log_level = Some(level);
+                }
+                "--headless-screenshot" => {
+                    let path = PathBuf::from(value(arg)?);
+                    command_line.headless_screenshot = Some((path, value(arg)?));
+                }
+                "--import-cookies" => command_line.import_cookies = Some(PathBuf::from(value(arg)?)),
+                "--export-cookies" => command_line.export_cookies = Some(PathBuf::from(value(arg)?)),
+                "--cookie-domain" => command_line.cookie_domain = Some(value(arg)?),
+                "--include-session-cookies" => command_line.include_session_cookies = true,
+                "--help" | "-h" => command_line.help = true,
+                "--" => {
+                    command_line.urls.extend(iter.by_ref().cloned());
+                    break;
+                }
+                option if option.starts_with('-') && option.len() > 1 => return Err(format!("Unknown option {}", option)),
+                url => command_line.urls.push(url.to_string()),
+            }
+        }
+
+        Ok(command_line)
+    }
+}
+
+/// Turns a URL argument into an address that can be opened in a tab. Arguments that name an existing
+/// file (relative to the working directory of the invocation) become `file:` URLs, everything else
+/// is used as if it was typed in the address bar.
+pub fn resolve_address(arg: &str, cwd: Option<&Path>) -> String {
+    if arg.contains("://") {
+        return arg.to_string();
+    }
+
+    let path = match cwd {
+        Some(cwd) => cwd.join(arg),
+        None => PathBuf::from(arg),
+    };
+    if path.exists() {
+        if let Some(url) = path.canonicalize().ok().and_then(|path| Url::from_file_path(path).ok()) {
+            return url.to_string();
+        }
+    }
+
+    arg.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn parse(args: &[&str]) -> Result<CommandLine, String> {
+        CommandLine::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]).unwrap(), CommandLine::default());
+
+        let command_line = parse(&["https://a", "--new-window", "--private", "https://b", "--log-level", "debug"]).unwrap();
+        assert_eq!(command_line.urls, vec!["https://a", "https://b"]);
+        assert!(command_line.new_window);
+        assert!(command_line.private);
+        assert_eq!(command_line.log_level, Some(LogLevel::Debug));
+
+        let command_line = parse(&["--profile", "work", "--headless-screenshot", "out.png", "gosub.io"]).unwrap();
+        assert_eq!(command_line.profile.as_deref(), Some("work"));
+        assert_eq!(
+            command_line.headless_screenshot,
+            Some((PathBuf::from("out.png"), "gosub.io".to_string()))
+        );
+        assert!(command_line.urls.is_empty());
+
+        let command_line = parse(&["--export-cookies", "c.txt", "--include-session-cookies", "--", "--not-an-option"]).unwrap();
+        assert_eq!(command_line.export_cookies, Some(PathBuf::from("c.txt")));
+        assert!(command_line.include_session_cookies);
+        assert_eq!(command_line.urls, vec!["--not-an-option"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--profile"]).is_err());
+        assert!(parse(&["--log-level", "loud"]).is_err());
+        assert!(parse(&["--headless-screenshot", "out.png"]).is_err());
+        assert_eq!(parse(&["-"]).unwrap().urls, vec!["-"]);
+    }
+
+    #[test]
+    fn test_resolve_address() {
+        let dir = std::env::temp_dir().join(format!("gosub_command_line_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("page.html"), "<p>hi</p>").unwrap();
+
+        let resolved = resolve_address("page.html", Some(&dir));
+        assert!(resolved.starts_with("file:///"), "{}", resolved);
+        assert!(resolved.ends_with("/page.html"));
+
+        assert_eq!(
+            resolve_address("https://gosub.io/page.html", Some(&dir)),
+            "https://gosub.io/page.html"
+        );
+        assert_eq!(resolve_address("gosub.io", Some(&dir)), "gosub.io");
+        assert_eq!(resolve_address("about:blank", None), "about:blank");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
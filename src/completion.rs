@@ -0,0 +1,279 @@
+use crate::tab::TabId;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Maximum number of suggestions shown below the address bar
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// Where a completion candidate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A page from the browsing history
+    History,
+    /// A bookmarked page
+    Bookmark,
+    /// A tab that is currently open. Selecting it switches to the tab instead of loading the URL.
+    OpenTab(TabId),
+    /// A search suggestion from the default search engine
+    SearchSuggestion,
+}
+
+/// A single completion candidate, as returned by a completion provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionCandidate {
+    pub url: String,
+    pub title: String,
+    pub kind: CompletionKind,
+    /// Number of times the URL has been visited
+    pub visit_count: u32,
+    /// Last time the URL has been visited
+    pub last_visit: Option<DateTime<Utc>>,
+}
+
+/// A ranked suggestion
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub candidate: CompletionCandidate,
+    pub score: f64,
+}
+
+/// A completion provider returns candidates that match (part of) the given query. Providers do not
+/// need to rank their results, but should return at most `limit` candidates.
+pub trait CompletionProvider: Send + Sync {
+    fn candidates(&self, query: &str, limit: usize) -> Vec<CompletionCandidate>;
+}
+
+/// Returns the completion providers that are registered by the different subsystems (history,
+/// bookmarks, etc.)
+fn providers() -> &'static RwLock<Vec<Arc<dyn CompletionProvider>>> {
+    static PROVIDERS: OnceLock<RwLock<Vec<Arc<dyn CompletionProvider>>>> = OnceLock::new();
+    PROVIDERS.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Registers a provider that is asked for candidates whenever the address bar changes
+pub fn register_completion_provider(provider: Arc<dyn CompletionProvider>) {
+    providers().write().unwrap().push(provider);
+}
+
+/// Returns the candidates of all registered providers
+pub fn provider_candidates(query: &str, limit: usize) -> Vec<CompletionCandidate> {
+    let providers = providers().read().unwrap().clone();
+    providers.iter().flat_map(|provider| provider.candidates(query, limit)).collect()
+}
+
+/// Returns the frecency (frequency + recency) score of a URL. Recent visits weigh more than old
+/// visits, so a page that is visited often last week beats a page that was visited often last year.
+pub fn frecency(visit_count: u32, last_visit: Option<DateTime<Utc>>, now: DateTime<Utc>) -> f64 {
+    let Some(last_visit) = last_visit else {
+        return 0.0;
+    };
+
+    visit_count as f64 * recency_weight(last_visit, now)
+}
+
+/// Returns how much a visit at the given time counts towards the frecency of a page
+pub fn recency_weight(visited_at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    match (now - visited_at).num_days() {
+        ..=4 => 100.0,
+        5..=14 => 70.0,
+        15..=31 => 50.0,
+        32..=90 => 30.0,
+        _ => 10.0,
+    }
+}
+
+/// Strips the scheme and a leading "www." from the URL, which is how users usually type a URL
+pub fn strip_url(url: &str) -> &str {
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    url.strip_prefix("www.").unwrap_or(url)
+}
+
+/// Returns how well the candidate matches the query, or None when it does not match at all. Every
+/// word of the query must be found in either the URL or the title.
+fn match_score(query: &str, candidate: &CompletionCandidate) -> Option<f64> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+
+    let url = strip_url(&candidate.url).to_lowercase();
+    let title = candidate.title.to_lowercase();
+
+    // Typing the start of the host is the strongest signal
+    if url.starts_with(&query) {
+        let host_len = url.find('/').unwrap_or(url.len());
+        return Some(if query.len() <= host_len { 4.0 } else { 3.0 });
+    }
+
+    let mut score = 0.0;
+    for word in query.split_whitespace() {
+        let word_score = if title.split_whitespace().any(|w| w.starts_with(word)) {
+            1.5
+        } else if url.contains(word) || title.contains(word) {
+            1.0
+        } else {
+            return None;
+        };
+        score += word_score;
+    }
+
+    Some(score / query.split_whitespace().count() as f64)
+}
+
+/// Ranks the candidates for the given query. Candidates for the same URL are merged, where open tabs
+/// take precedence over bookmarks, and bookmarks over history entries.
+pub fn rank(query: &str, candidates: Vec<CompletionCandidate>, now: DateTime<Utc>, limit: usize) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+
+    for candidate in candidates {
+        let score = if candidate.kind == CompletionKind::SearchSuggestion {
+            // Search suggestions always come after the pages the user knows about
+            0.5
+        } else {
+            let Some(match_score) = match_score(query, &candidate) else {
+                continue;
+            };
+            let kind_bonus = match candidate.kind {
+                CompletionKind::OpenTab(_) => 2.0,
+                CompletionKind::Bookmark => 1.5,
+                _ => 1.0,
+            };
+            match_score * kind_bonus * (1.0 + frecency(candidate.visit_count, candidate.last_visit, now).ln_1p())
+        };
+
+        if candidate.kind != CompletionKind::SearchSuggestion {
+            if let Some(existing) = suggestions.iter_mut().find(|s| s.candidate.url == candidate.url) {
+                existing.score = existing.score.max(score);
+                if priority(candidate.kind) > priority(existing.candidate.kind) {
+                    existing.candidate = candidate;
+                }
+                continue;
+            }
+        }
+
+        suggestions.push(Suggestion { candidate, score });
+    }
+
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// Priority of a candidate kind when the same URL is returned by multiple providers
+fn priority(kind: CompletionKind) -> u8 {
+    match kind {
+        CompletionKind::OpenTab(_) => 3,
+        CompletionKind::Bookmark => 2,
+        CompletionKind::History => 1,
+        CompletionKind::SearchSuggestion => 0,
+    }
+}
+
+/// Returns the text to complete inline in the address bar: the host of the best suggestion when the
+/// query is the start of that host. The returned string starts with the query as typed.
+pub fn inline_completion(query: &str, suggestions: &[Suggestion]) -> Option<String> {
+    if query.is_empty() || query.contains(char::is_whitespace) || query.contains('/') {
+        return None;
+    }
+
+    let lower_query = query.to_lowercase();
+    suggestions
+        .iter()
+        .filter(|s| s.candidate.kind != CompletionKind::SearchSuggestion)
+        .find_map(|s| {
+            let stripped = strip_url(&s.candidate.url);
+            let host = &stripped[..stripped.find('/').unwrap_or(stripped.len())];
+            let lower_host = host.to_lowercase();
+            if lower_host.starts_with(&lower_query) && lower_host.len() > lower_query.len() {
+                host.get(query.len()..).map(|rest| format!("{}{}", query, rest))
+            } else {
+                None
+            }
+        })
+}
+
+/// Parses an OpenSearch suggestions response: `["query", ["suggestion 1", "suggestion 2", ...]]`
+pub fn parse_search_suggestions(json: &str) -> Vec<String> {
+    let Ok(serde_json::Value::Array(values)) = serde_json::from_str::<serde_json::Value>(json) else {
+        return Vec::new();
+    };
+
+    match values.get(1) {
+        Some(serde_json::Value::Array(suggestions)) => suggestions.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn candidate(url: &str, title: &str, kind: CompletionKind, visits: u32, days_ago: i64) -> CompletionCandidate {
+        CompletionCandidate {
+            url: url.to_string(),
+            title: title.to_string(),
+            kind,
+            visit_count: visits,
+            last_visit: Some(Utc::now() - Duration::days(days_ago)),
+        }
+    }
+
+    #[test]
+    fn test_frecency() {
+        let now = Utc::now();
+        assert_eq!(frecency(0, Some(now), now), 0.0);
+        assert_eq!(frecency(10, None, now), 0.0);
+        assert!(frecency(5, Some(now - Duration::days(1)), now) > frecency(5, Some(now - Duration::days(100)), now));
+        assert!(frecency(20, Some(now - Duration::days(40)), now) > frecency(2, Some(now), now));
+    }
+
+    #[test]
+    fn test_rank() {
+        let candidates = vec![
+            candidate(
+                "https://www.rust-lang.org/",
+                "Rust Programming Language",
+                CompletionKind::History,
+                3,
+                1,
+            ),
+            candidate("https://docs.rs/", "Docs.rs", CompletionKind::History, 50, 1),
+            candidate("https://crates.io/crates/rusqlite", "rusqlite", CompletionKind::History, 10, 200),
+            candidate("https://example.com/", "Example", CompletionKind::History, 100, 1),
+        ];
+
+        let suggestions = rank("rus", candidates.clone(), Utc::now(), MAX_SUGGESTIONS);
+        let urls: Vec<&str> = suggestions.iter().map(|s| s.candidate.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://www.rust-lang.org/", "https://crates.io/crates/rusqlite"]);
+
+        // An open tab wins from a history entry for the same URL
+        let tab_id = TabId::new();
+        let mut with_tab = candidates.clone();
+        with_tab.push(candidate("https://docs.rs/", "Docs.rs", CompletionKind::OpenTab(tab_id), 0, 0));
+        let suggestions = rank("docs", with_tab, Utc::now(), MAX_SUGGESTIONS);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].candidate.kind, CompletionKind::OpenTab(tab_id));
+
+        // All words must match
+        assert!(rank("rust nothing", candidates, Utc::now(), MAX_SUGGESTIONS).is_empty());
+    }
+
+    #[test]
+    fn test_inline_completion() {
+        let candidates = vec![candidate("https://www.GitHub.com/gosub-io", "Gosub", CompletionKind::History, 3, 1)];
+        let suggestions = rank("git", candidates, Utc::now(), MAX_SUGGESTIONS);
+
+        assert_eq!(inline_completion("git", &suggestions), Some("gitHub.com".to_string()));
+        assert_eq!(inline_completion("GitHub.com", &suggestions), None);
+        assert_eq!(inline_completion("git hub", &suggestions), None);
+        assert_eq!(inline_completion("", &suggestions), None);
+    }
+
+    #[test]
+    fn test_parse_search_suggestions() {
+        let json = r#"["rust", ["rust lang", "rust game", 3]]"#;
+        assert_eq!(parse_search_suggestions(json), vec!["rust lang", "rust game"]);
+        assert!(parse_search_suggestions("{}").is_empty());
+    }
+}
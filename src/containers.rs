@@ -0,0 +1,267 @@
+use crate::profile::profile;
+use log::warn;
+use rusqlite::{params, Connection};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Name of the database that holds the container definitions
+const CONTAINERS_DB_FILE: &str = "containers.db";
+
+/// Containers that are created when no containers exist yet
+const DEFAULT_CONTAINERS: [(&str, ContainerColor); 4] = [
+    ("Personal", ContainerColor::Blue),
+    ("Work", ContainerColor::Orange),
+    ("Banking", ContainerColor::Green),
+    ("Shopping", ContainerColor::Pink),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContainerId(i64);
+
+impl fmt::Display for ContainerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ContainerId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(ContainerId)
+    }
+}
+
+/// Colors a container can have. The color is shown on the tab label of tabs in the container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerColor {
+    Blue,
+    Turquoise,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Pink,
+    Purple,
+}
+
+impl ContainerColor {
+    pub const ALL: [ContainerColor; 8] = [
+        ContainerColor::Blue,
+        ContainerColor::Turquoise,
+        ContainerColor::Green,
+        ContainerColor::Yellow,
+        ContainerColor::Orange,
+        ContainerColor::Red,
+        ContainerColor::Pink,
+        ContainerColor::Purple,
+    ];
+
+    /// Returns the color as a CSS hex value
+    pub fn hex(&self) -> &'static str {
+        match self {
+            ContainerColor::Blue => "#37adff",
+            ContainerColor::Turquoise => "#00c79a",
+            ContainerColor::Green => "#51cd00",
+            ContainerColor::Yellow => "#ffcb00",
+            ContainerColor::Orange => "#ff9f00",
+            ContainerColor::Red => "#ff613d",
+            ContainerColor::Pink => "#ff4bda",
+            ContainerColor::Purple => "#af51f5",
+        }
+    }
+}
+
+impl fmt::Display for ContainerColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContainerColor::Blue => "blue",
+            ContainerColor::Turquoise => "turquoise",
+            ContainerColor::Green => "green",
+            ContainerColor::Yellow => "yellow",
+            ContainerColor::Orange => "orange",
+            ContainerColor::Red => "red",
+            ContainerColor::Pink => "pink",
+            ContainerColor::Purple => "purple",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ContainerColor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ContainerColor::ALL
+            .iter()
+            .find(|color| color.to_string() == s)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("unknown container color: {}", s))
+    }
+}
+
+/// A container is a long-lived identity with its own cookies and cache. Tabs in different
+/// containers do not share any network state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    pub id: ContainerId,
+    pub name: String,
+    pub color: ContainerColor,
+}
+
+impl Container {
+    /// Path of the cookie database of this container
+    pub fn cookie_db_path(&self) -> PathBuf {
+        profile().data_path(&format!("cookies_container_{}.db", self.id))
+    }
+
+    /// Name of the cache partition of this container
+    pub fn cache_partition(&self) -> String {
+        format!("container-{}", self.id)
+    }
+}
+
+/// Stores the containers in a SQLite database
+pub struct ContainerStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl ContainerStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS containers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                color TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM containers", [], |row| row.get(0))?;
+        if count == 0 {
+            for (name, color) in DEFAULT_CONTAINERS {
+                conn.execute(
+                    "INSERT INTO containers (name, color) VALUES (?1, ?2)",
+                    params![name, color.to_string()],
+                )?;
+            }
+        }
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Adds a new container and returns it
+    pub fn add(&self, name: &str, color: ContainerColor) -> Option<Container> {
+        let conn = self.conn.lock().unwrap();
+        let e = conn.execute(
+            "INSERT INTO containers (name, color) VALUES (?1, ?2)",
+            params![name, color.to_string()],
+        );
+        if let Err(e) = e {
+            warn!("failed to store container: {:?}", e);
+            return None;
+        }
+
+        Some(Container {
+            id: ContainerId(conn.last_insert_rowid()),
+            name: name.to_string(),
+            color,
+        })
+    }
+
+    /// Removes the given container. Its cookie database is left on disk.
+    pub fn remove(&self, id: ContainerId) {
+        let e = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM containers WHERE id = ?1", params![id.0]);
+        if let Err(e) = e {
+            warn!("failed to remove container: {:?}", e);
+        }
+    }
+
+    pub fn get(&self, id: ContainerId) -> Option<Container> {
+        self.all().into_iter().find(|c| c.id == id)
+    }
+
+    /// Returns all containers, in order of creation
+    pub fn all(&self) -> Vec<Container> {
+        let locked_conn = self.conn.lock().unwrap();
+
+        let stmt = locked_conn.prepare("SELECT id, name, color FROM containers ORDER BY id");
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            let color: String = row.get(2)?;
+            Ok((id, name, color))
+        });
+
+        let Ok(rows) = rows else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok())
+            .map(|(id, name, color)| Container {
+                id: ContainerId(id),
+                name,
+                color: color.parse().unwrap_or(ContainerColor::Blue),
+            })
+            .collect()
+    }
+}
+
+/// Path of the database that holds the container definitions of the current profile
+pub fn containers_db_path() -> PathBuf {
+    profile().data_path(CONTAINERS_DB_FILE)
+}
+
+/// Returns the container store of the browser
+pub fn container_store() -> Arc<ContainerStore> {
+    static STORE: OnceLock<Arc<ContainerStore>> = OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            let store = ContainerStore::new(containers_db_path()).unwrap_or_else(|e| {
+                warn!("failed to open container store, using in-memory store: {:?}", e);
+                ContainerStore::new(":memory:").expect("in-memory container store")
+            });
+            Arc::new(store)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_store() {
+        let store = ContainerStore::new(":memory:").unwrap();
+        assert_eq!(store.all().len(), DEFAULT_CONTAINERS.len());
+
+        let container = store.add("Testing", ContainerColor::Purple).unwrap();
+        assert_eq!(store.get(container.id), Some(container.clone()));
+        assert_eq!(store.all().last().unwrap().name, "Testing");
+
+        store.remove(container.id);
+        assert_eq!(store.get(container.id), None);
+    }
+
+    #[test]
+    fn test_container_color() {
+        for color in ContainerColor::ALL {
+            assert_eq!(color.to_string().parse::<ContainerColor>().unwrap(), color);
+        }
+        assert!("magenta".parse::<ContainerColor>().is_err());
+    }
+}
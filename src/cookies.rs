@@ -1,2 +1,45 @@
+use crate::cookies::permission_store::SqlitePermissionStore;
+use crate::cookies::policy::CookiePolicy;
+use crate::profile::profile;
+use crate::settings::settings;
+use log::warn;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
 pub mod jar;
+pub mod manager;
+pub mod memory_store;
+pub mod netscape;
+pub mod permission_store;
+pub mod policy;
 pub mod sqlite_store;
+
+/// Name of the database that holds the persistent cookies
+const COOKIE_DB_FILE: &str = "cookies.db";
+
+/// Name of the database that holds the per-site cookie permissions
+const PERMISSIONS_DB_FILE: &str = "permissions.db";
+
+/// Path of the database that holds the persistent cookies of the current profile
+pub fn cookie_db_path() -> PathBuf {
+    profile().data_path(COOKIE_DB_FILE)
+}
+
+/// Path of the database that holds the per-site cookie permissions of the current profile
+pub fn permissions_db_path() -> PathBuf {
+    profile().data_path(PERMISSIONS_DB_FILE)
+}
+
+/// Returns the cookie policy that is shared by all cookie jars in the browser
+pub fn cookie_policy() -> Arc<CookiePolicy> {
+    static POLICY: OnceLock<Arc<CookiePolicy>> = OnceLock::new();
+    POLICY
+        .get_or_init(|| {
+            let store = SqlitePermissionStore::new(permissions_db_path()).unwrap_or_else(|e| {
+                warn!(target: "cookies", "failed to open permission store, using in-memory store: {:?}", e);
+                SqlitePermissionStore::new(":memory:").expect("in-memory permission store")
+            });
+            Arc::new(CookiePolicy::new(settings().privacy.cookie_mode, store))
+        })
+        .clone()
+}
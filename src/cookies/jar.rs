@@ -1,50 +1,96 @@
+use crate::cookies::policy::CookiePolicy;
 use cookie::Cookie;
+use log::info;
 use reqwest::cookie::CookieStore;
 use reqwest::header::HeaderValue;
 use std::sync::{Arc, Mutex};
 use url::Url;
 
 pub trait StorageBackend: Send + Sync {
-    fn store(&self, url: &Url, value: &Cookie);
+    /// Stores the cookie that was set by `url`. Returns false when the cookie is not kept, like
+    /// session cookies in a persistent store.
+    fn store(&self, url: &Url, value: &Cookie) -> bool;
     fn get(&self, url: &Url) -> Option<Vec<Cookie>>;
+    /// Returns all non-expired cookies in the store
+    fn all(&self) -> Vec<StoredCookie>;
+}
+
+/// A cookie in a storage backend, together with the host it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCookie {
+    /// The domain of the Domain attribute, or the host that set the cookie for host-only cookies
+    pub domain: String,
+    /// The cookie is sent to `domain` only, and not to its subdomains. Cookies that are set without
+    /// a Domain attribute are host-only.
+    pub host_only: bool,
+    pub cookie: Cookie<'static>,
 }
 
 pub struct CookieJar {
     store: Arc<Mutex<dyn StorageBackend>>,
+    policy: Arc<CookiePolicy>,
 }
 
 impl CookieJar {
-    pub fn new(store: Arc<Mutex<dyn StorageBackend>>) -> Self {
-        Self { store }
+    pub fn new(store: Arc<Mutex<dyn StorageBackend>>, policy: Arc<CookiePolicy>) -> Self {
+        Self { store, policy }
     }
-}
 
-impl CookieStore for CookieJar {
-    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
+    /// Stores the cookies from the given Set-Cookie headers, when allowed by the cookie policy for
+    /// a request to `url` that was started from a tab with `top_level` as its top-level URL.
+    pub fn set_cookies_for(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url, top_level: Option<&Url>) {
+        if !self.policy.allows(url, top_level) {
+            info!(target: "cookies", "cookies for {} blocked by policy", url);
+            return;
+        }
+
         for header_value in cookie_headers {
-            if let Ok(c) = Cookie::parse(header_value.to_str().unwrap()) {
+            let Ok(header_str) = header_value.to_str() else {
+                continue;
+            };
+            if let Ok(c) = Cookie::parse(header_str) {
                 self.store.lock().unwrap().store(url, &c);
             }
         }
     }
 
-    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
-        let locked_store = self.store.lock().unwrap();
-        let cookies = locked_store.get(url);
-
-        cookies.as_ref()?;
+    /// Returns the Cookie header value for a request to `url`, when allowed by the cookie policy
+    /// for a tab with `top_level` as its top-level URL.
+    pub fn cookies_for(&self, url: &Url, top_level: Option<&Url>) -> Option<HeaderValue> {
+        if !self.policy.allows(url, top_level) {
+            return None;
+        }
 
-        let cookies = cookies.unwrap();
-        let mut cookie_str = String::new();
-        for cookie in cookies {
-            cookie_str.push_str(&cookie.to_string());
-            cookie_str.push_str("; ");
+        let locked_store = self.store.lock().unwrap();
+        let cookies = locked_store.get(url)?;
+        if cookies.is_empty() {
+            return None;
         }
 
-        cookie_str.pop();
-        cookie_str.pop();
+        let cookie_str = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name(), c.value()))
+            .collect::<Vec<_>>()
+            .join("; ");
 
         drop(locked_store);
-        Some(HeaderValue::from_str(&cookie_str).unwrap())
+        HeaderValue::from_str(&cookie_str).ok()
+    }
+
+    /// Returns all non-expired cookies in the jar
+    pub fn all(&self) -> Vec<StoredCookie> {
+        self.store.lock().unwrap().all()
+    }
+}
+
+/// When the jar is used directly as a reqwest cookie provider, there is no top-level context
+/// available, so all requests are treated as first-party requests.
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
+        self.set_cookies_for(cookie_headers, url, None);
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
+        self.cookies_for(url, None)
     }
 }
@@ -0,0 +1,101 @@
+use crate::cookies::jar::{StorageBackend, StoredCookie};
+use crate::cookies::netscape;
+use crate::cookies::netscape::NetscapeOptions;
+use log::info;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Result of a cookie import
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Number of cookies that have been stored
+    pub imported: usize,
+    /// Number of cookies that have not been stored
+    pub skipped: usize,
+}
+
+/// The cookie manager gives access to the cookies in a storage backend outside of the HTTP request
+/// flow, for instance to import or export cookies from and to other tools.
+pub struct CookieManager {
+    store: Arc<Mutex<dyn StorageBackend>>,
+}
+
+impl CookieManager {
+    pub fn new(store: Arc<Mutex<dyn StorageBackend>>) -> Self {
+        Self { store }
+    }
+
+    /// Imports the cookies from a Netscape cookies.txt file into the store. Cookies the store does
+    /// not keep, like session cookies in a persistent store, are skipped.
+    pub fn import_netscape(&self, path: &Path, options: &NetscapeOptions) -> anyhow::Result<ImportSummary> {
+        let content = fs::read_to_string(path)?;
+        let cookies = netscape::parse(&content, options);
+
+        let store = self.store.lock().unwrap();
+        let mut summary = ImportSummary::default();
+        for StoredCookie { domain, cookie, .. } in cookies.iter() {
+            // Host-only cookies have no Domain attribute, so the store takes the host from the URL
+            let scheme = if cookie.secure().unwrap_or(false) { "https" } else { "http" };
+            let url = Url::parse(&format!("{}://{}{}", scheme, domain, cookie.path().unwrap_or("/")));
+
+            if url.is_ok_and(|url| store.store(&url, cookie)) {
+                summary.imported += 1;
+            } else {
+                info!(target: "cookies", "skipped cookie {} for {}", cookie.name(), domain);
+                summary.skipped += 1;
+            }
+        }
+
+        info!(target: "cookies", "imported {} cookies from {}, skipped {}", summary.imported, path.display(), summary.skipped);
+        Ok(summary)
+    }
+
+    /// Exports the cookies from the store into a Netscape cookies.txt file. Returns the number of
+    /// cookies that were exported.
+    pub fn export_netscape(&self, path: &Path, options: &NetscapeOptions) -> anyhow::Result<usize> {
+        let cookies = self.store.lock().unwrap().all();
+        let count = cookies.iter().filter(|c| options.matches(c)).count();
+
+        fs::write(path, netscape::format(cookies.iter(), options))?;
+
+        info!(target: "cookies", "exported {} cookies to {}", count, path.display());
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::sqlite_store::SqliteStorage;
+
+    #[test]
+    fn test_import_skips_session_cookies() {
+        let path = std::env::temp_dir().join(format!("gosub_cookies_{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            ".example.com\tTRUE\t/\tFALSE\t4000000000\tkept\tyes\n\
+             .example.com\tTRUE\t/\tFALSE\t0\tsession\tno\n\
+             127.0.0.1\tFALSE\t/\tFALSE\t4000000000\tlocal\tyes\n",
+        )
+        .unwrap();
+
+        let manager = CookieManager::new(Arc::new(Mutex::new(SqliteStorage::new(":memory:").unwrap())));
+        let options = NetscapeOptions {
+            domain: None,
+            include_session: true,
+        };
+        let summary = manager.import_netscape(&path, &options).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(summary, ImportSummary { imported: 2, skipped: 1 });
+
+        // Host-only cookies of IP addresses are kept for that address
+        let store = manager.store.lock().unwrap();
+        assert_eq!(store.all().len(), 2);
+        let local = store.get(&Url::parse("http://127.0.0.1/").unwrap()).unwrap();
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].name(), "local");
+    }
+}
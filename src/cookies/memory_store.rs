@@ -0,0 +1,122 @@
+use crate::cookies::jar::{StorageBackend, StoredCookie};
+use cookie::time::OffsetDateTime;
+use cookie::Cookie;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use url::Url;
+
+/// Storage backend that keeps all cookies (including session cookies) in memory. Everything is
+/// lost as soon as the storage is dropped, which makes it suitable for private browsing.
+#[derive(Default)]
+pub struct MemoryStorage {
+    /// Cookies, keyed by (domain, path, name)
+    cookies: Mutex<HashMap<(String, String, String), Cookie<'static>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Returns true when the cookie has an expiry date that lies in the past
+fn is_expired(cookie: &Cookie) -> bool {
+    match cookie.expires_datetime() {
+        Some(expires) => expires <= OffsetDateTime::now_utc(),
+        None => false,
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn store(&self, url: &Url, cookie: &Cookie) -> bool {
+        let Some(domain) = cookie.domain().or(url.host_str()) else {
+            return false;
+        };
+
+        let key = (
+            domain.to_string(),
+            cookie.path().unwrap_or("/").to_string(),
+            cookie.name().to_string(),
+        );
+
+        let mut cookies = self.cookies.lock().unwrap();
+        if is_expired(cookie) {
+            cookies.remove(&key);
+            return false;
+        }
+
+        cookies.insert(key, cookie.clone().into_owned());
+        true
+    }
+
+    fn get(&self, url: &Url) -> Option<Vec<Cookie>> {
+        let host = url.host_str()?;
+        let path = url.path();
+
+        let cookies = self.cookies.lock().unwrap();
+        let found = cookies
+            .iter()
+            .filter(|((domain, cookie_path, _), cookie)| {
+                // Host-only cookies are not sent to subdomains
+                let domain_matches = host == domain || (cookie.domain().is_some() && host.ends_with(&format!(".{}", domain)));
+                domain_matches && path.starts_with(cookie_path.as_str()) && !is_expired(cookie)
+            })
+            .map(|(_, cookie)| cookie.clone())
+            .collect();
+
+        Some(found)
+    }
+
+    fn all(&self) -> Vec<StoredCookie> {
+        let cookies = self.cookies.lock().unwrap();
+        cookies
+            .iter()
+            .filter(|(_, cookie)| !is_expired(cookie))
+            .map(|((domain, _, _), cookie)| StoredCookie {
+                domain: domain.clone(),
+                host_only: cookie.domain().is_none(),
+                cookie: cookie.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_storage() {
+        let store = MemoryStorage::new();
+        let url = Url::parse("https://www.example.com/account/settings").unwrap();
+
+        store.store(&url, &Cookie::parse("session=abc; Path=/account").unwrap());
+        store.store(&url, &Cookie::parse("lang=nl; Domain=example.com; Path=/").unwrap());
+
+        let cookies = store.get(&url).unwrap();
+        assert_eq!(cookies.len(), 2);
+
+        let other = Url::parse("https://static.example.com/").unwrap();
+        let cookies = store.get(&other).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name(), "lang");
+
+        // Cookies without a Domain attribute are not sent to subdomains
+        let subdomain = Url::parse("https://eu.www.example.com/account").unwrap();
+        let cookies = store.get(&subdomain).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name(), "lang");
+
+        let mut all = store.all();
+        all.sort_by(|a, b| a.cookie.name().cmp(b.cookie.name()));
+        assert_eq!((all[0].domain.as_str(), all[0].host_only), ("example.com", false));
+        assert_eq!((all[1].domain.as_str(), all[1].host_only), ("www.example.com", true));
+
+        // An expired cookie removes the stored one
+        store.store(
+            &url,
+            &Cookie::parse("lang=; Domain=example.com; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT").unwrap(),
+        );
+        assert_eq!(store.all().len(), 1);
+    }
+}
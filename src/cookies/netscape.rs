@@ -0,0 +1,228 @@
+use crate::cookies::jar::StoredCookie;
+use cookie::time::OffsetDateTime;
+use cookie::{Cookie, Expiration};
+use std::fmt::Write;
+
+// The Netscape cookies.txt format is the de-facto exchange format between curl, wget and most
+// browsers. Each line holds a single cookie with seven tab-separated fields:
+//
+//   domain  include_subdomains  path  secure  expires  name  value
+//
+// Lines starting with `#` are comments, except for the `#HttpOnly_` prefix that curl uses to mark
+// a cookie as HttpOnly. An expiry of 0 denotes a session cookie. Host-only cookies have FALSE as
+// include_subdomains, and their domain is written without a leading dot.
+
+/// Header that is written at the top of every exported cookies.txt file
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File\n# This file was generated by Gosub. Edit at your own risk.\n\n";
+
+/// Prefix curl uses on the domain field to mark HttpOnly cookies
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Options that define which cookies are imported or exported
+#[derive(Debug, Clone, Default)]
+pub struct NetscapeOptions {
+    /// Only handle cookies for this domain (and its subdomains). All domains when None.
+    pub domain: Option<String>,
+    /// Also handle session cookies (cookies without an expiry date)
+    pub include_session: bool,
+}
+
+impl NetscapeOptions {
+    /// Returns true when the given cookie passes the filters of these options
+    pub fn matches(&self, stored: &StoredCookie) -> bool {
+        if !self.include_session && is_session(&stored.cookie) {
+            return false;
+        }
+
+        match &self.domain {
+            None => true,
+            Some(filter) => {
+                let filter = filter.trim_start_matches('.');
+                stored.domain == filter || stored.domain.ends_with(&format!(".{}", filter))
+            }
+        }
+    }
+}
+
+/// Returns true when the cookie has no (fixed) expiry date
+fn is_session(cookie: &Cookie) -> bool {
+    cookie.expires_datetime().is_none()
+}
+
+/// Parses the contents of a cookies.txt file. Lines that cannot be parsed are skipped.
+pub fn parse(content: &str, options: &NetscapeOptions) -> Vec<StoredCookie> {
+    content
+        .lines()
+        .filter_map(parse_line)
+        .filter(|cookie| options.matches(cookie))
+        .collect()
+}
+
+/// Parses a single line of a cookies.txt file into a cookie
+fn parse_line(line: &str) -> Option<StoredCookie> {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+        Some(rest) => (rest, true),
+        None => (line, false),
+    };
+
+    if line.trim().is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 7 {
+        return None;
+    }
+
+    let domain = fields[0].trim_start_matches('.');
+    if domain.is_empty() {
+        return None;
+    }
+    let host_only = !fields[1].eq_ignore_ascii_case("TRUE");
+    let secure = fields[3].eq_ignore_ascii_case("TRUE");
+    let expires: i64 = fields[4].parse().ok()?;
+
+    let mut builder = Cookie::build((fields[5].to_string(), fields[6].to_string()))
+        .path(fields[2].to_string())
+        .secure(secure)
+        .http_only(http_only);
+
+    // Host-only cookies are cookies without a Domain attribute
+    if !host_only {
+        builder = builder.domain(domain.to_string());
+    }
+
+    builder = if expires == 0 {
+        builder.expires(Expiration::Session)
+    } else {
+        builder.expires(OffsetDateTime::from_unix_timestamp(expires).ok()?)
+    };
+
+    Some(StoredCookie {
+        domain: domain.to_string(),
+        host_only,
+        cookie: builder.build(),
+    })
+}
+
+/// Formats the given cookies as a cookies.txt file
+pub fn format<'a>(cookies: impl IntoIterator<Item = &'a StoredCookie>, options: &NetscapeOptions) -> String {
+    let mut out = String::from(NETSCAPE_HEADER);
+
+    for stored in cookies.into_iter().filter(|c| options.matches(c)) {
+        let cookie = &stored.cookie;
+        let domain_prefix = if stored.host_only { "" } else { "." };
+
+        let expires = cookie.expires_datetime().map(|dt| dt.unix_timestamp()).unwrap_or(0);
+        let prefix = if cookie.http_only().unwrap_or(false) {
+            HTTP_ONLY_PREFIX
+        } else {
+            ""
+        };
+
+        _ = writeln!(
+            out,
+            "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            prefix,
+            domain_prefix,
+            stored.domain,
+            bool_str(!stored.host_only),
+            cookie.path().unwrap_or("/"),
+            bool_str(cookie.secure().unwrap_or(false)),
+            expires,
+            cookie.name(),
+            cookie.value(),
+        );
+    }
+
+    out
+}
+
+fn bool_str(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "# Netscape HTTP Cookie File\n\
+        .example.com\tTRUE\t/\tFALSE\t2000000000\tsession_id\tabc123\n\
+        #HttpOnly_.example.com\tTRUE\t/account\tTRUE\t2000000000\ttoken\tsecret\n\
+        sub.example.org\tFALSE\t/\tFALSE\t0\ttransient\tyes\n\
+        this line is broken\n\
+        \n";
+
+    #[test]
+    fn test_parse() {
+        let options = NetscapeOptions {
+            domain: None,
+            include_session: true,
+        };
+        let cookies = parse(FIXTURE, &options);
+        assert_eq!(cookies.len(), 3);
+
+        assert_eq!(cookies[0].domain, "example.com");
+        assert!(!cookies[0].host_only);
+        assert_eq!(cookies[0].cookie.name(), "session_id");
+        assert_eq!(cookies[0].cookie.value(), "abc123");
+        assert_eq!(cookies[0].cookie.domain(), Some("example.com"));
+        assert_eq!(cookies[0].cookie.expires_datetime().unwrap().unix_timestamp(), 2000000000);
+
+        assert_eq!(cookies[1].cookie.path(), Some("/account"));
+        assert_eq!(cookies[1].cookie.http_only(), Some(true));
+        assert_eq!(cookies[1].cookie.secure(), Some(true));
+
+        // A host-only cookie has no Domain attribute
+        assert_eq!(cookies[2].domain, "sub.example.org");
+        assert!(cookies[2].host_only);
+        assert_eq!(cookies[2].cookie.domain(), None);
+        assert!(is_session(&cookies[2].cookie));
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let options = NetscapeOptions::default();
+        assert_eq!(parse(FIXTURE, &options).len(), 2);
+
+        let options = NetscapeOptions {
+            domain: Some("example.org".into()),
+            include_session: true,
+        };
+        let cookies = parse(FIXTURE, &options);
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].cookie.name(), "transient");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let options = NetscapeOptions {
+            domain: None,
+            include_session: true,
+        };
+        let cookies = parse(FIXTURE, &options);
+        let output = format(cookies.iter(), &options);
+        let reparsed = parse(&output, &options);
+
+        assert!(output.starts_with("# Netscape HTTP Cookie File"));
+        assert!(output.contains("\nsub.example.org\tFALSE\t/\t"));
+        assert_eq!(reparsed.len(), cookies.len());
+        for (a, b) in cookies.iter().zip(reparsed.iter()) {
+            assert_eq!(a.domain, b.domain);
+            assert_eq!(a.host_only, b.host_only);
+            assert_eq!(a.cookie.name(), b.cookie.name());
+            assert_eq!(a.cookie.value(), b.cookie.value());
+            assert_eq!(a.cookie.domain(), b.cookie.domain());
+            assert_eq!(a.cookie.path(), b.cookie.path());
+            assert_eq!(a.cookie.secure(), b.cookie.secure());
+            assert_eq!(a.cookie.http_only(), b.cookie.http_only());
+            assert_eq!(a.cookie.expires_datetime(), b.cookie.expires_datetime());
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use log::warn;
+use rusqlite::{params, Connection};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Permission that can be set for a single site, overriding the global cookie mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SitePermission {
+    /// Always accept cookies for this site
+    Allow,
+    /// Never accept cookies for this site
+    Block,
+}
+
+impl Display for SitePermission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SitePermission::Allow => write!(f, "allow"),
+            SitePermission::Block => write!(f, "block"),
+        }
+    }
+}
+
+impl FromStr for SitePermission {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(SitePermission::Allow),
+            "block" => Ok(SitePermission::Block),
+            _ => Err(anyhow::anyhow!("unknown site permission: {}", s)),
+        }
+    }
+}
+
+/// Stores the per-site cookie permissions in a SQLite database
+pub struct SqlitePermissionStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl SqlitePermissionStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS cookie_permissions (
+                site TEXT PRIMARY KEY,
+                permission TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Sets the permission for the given site, replacing any existing permission
+    pub fn set(&self, site: &str, permission: SitePermission) {
+        let e = self.conn.lock().unwrap().execute(
+            "INSERT INTO cookie_permissions (site, permission) VALUES (?1, ?2)
+             ON CONFLICT(site) DO UPDATE SET permission = excluded.permission",
+            params![site, permission.to_string()],
+        );
+        if let Err(e) = e {
+            warn!("failed to store cookie permission: {:?}", e);
+        }
+    }
+
+    /// Removes the permission for the given site
+    pub fn remove(&self, site: &str) {
+        let e = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM cookie_permissions WHERE site = ?1", params![site]);
+        if let Err(e) = e {
+            warn!("failed to remove cookie permission: {:?}", e);
+        }
+    }
+
+    /// Returns the permission for the given site, if any
+    pub fn get(&self, site: &str) -> Option<SitePermission> {
+        let locked_conn = self.conn.lock().unwrap();
+        let permission: Result<String, _> =
+            locked_conn.query_row("SELECT permission FROM cookie_permissions WHERE site = ?1", params![site], |row| {
+                row.get(0)
+            });
+
+        permission.ok().and_then(|p| p.parse().ok())
+    }
+
+    /// Returns all stored permissions, ordered by site
+    pub fn all(&self) -> Vec<(String, SitePermission)> {
+        let locked_conn = self.conn.lock().unwrap();
+
+        let stmt = locked_conn.prepare("SELECT site, permission FROM cookie_permissions ORDER BY site");
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let rows = stmt.query_map([], |row| {
+            let site: String = row.get(0)?;
+            let permission: String = row.get(1)?;
+            Ok((site, permission))
+        });
+
+        let Ok(rows) = rows else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok())
+            .filter_map(|(site, permission)| Some((site, permission.parse().ok()?)))
+            .collect()
+    }
+}
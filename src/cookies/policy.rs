@@ -0,0 +1,192 @@
+use crate::cookies::permission_store::{SitePermission, SqlitePermissionStore};
+use publicsuffix::{List, Psl};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::{OnceLock, RwLock};
+use url::Url;
+
+/// Global mode that decides which cookies are accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CookieMode {
+    /// Accept all cookies
+    #[default]
+    AcceptAll,
+    /// Accept only cookies from the site that is loaded in the tab
+    BlockThirdParty,
+    /// Do not accept any cookies
+    BlockAll,
+}
+
+impl CookieMode {
+    pub const ALL: [CookieMode; 3] = [CookieMode::AcceptAll, CookieMode::BlockThirdParty, CookieMode::BlockAll];
+}
+
+impl Display for CookieMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CookieMode::AcceptAll => write!(f, "Accept all cookies"),
+            CookieMode::BlockThirdParty => write!(f, "Block third-party cookies"),
+            CookieMode::BlockAll => write!(f, "Block all cookies"),
+        }
+    }
+}
+
+/// The cookie policy decides if a cookie may be stored or sent for a given URL. It consists of a
+/// global mode, and a list of per-site exceptions that take precedence over that mode.
+pub struct CookiePolicy {
+    mode: RwLock<CookieMode>,
+    permissions: SqlitePermissionStore,
+}
+
+impl CookiePolicy {
+    pub fn new(mode: CookieMode, permissions: SqlitePermissionStore) -> Self {
+        Self {
+            mode: RwLock::new(mode),
+            permissions,
+        }
+    }
+
+    pub fn mode(&self) -> CookieMode {
+        *self.mode.read().unwrap()
+    }
+
+    pub fn set_mode(&self, mode: CookieMode) {
+        *self.mode.write().unwrap() = mode;
+    }
+
+    pub fn permissions(&self) -> &SqlitePermissionStore {
+        &self.permissions
+    }
+
+    /// Returns true when cookies for the given URL are allowed when loaded inside a tab that has
+    /// `top_level` as its top-level URL. When no top-level URL is known, the request is considered
+    /// to be a first-party request.
+    pub fn allows(&self, url: &Url, top_level: Option<&Url>) -> bool {
+        let Some(site) = site_for_url(url) else {
+            return false;
+        };
+
+        match self.permissions.get(&site) {
+            Some(SitePermission::Allow) => return true,
+            Some(SitePermission::Block) => return false,
+            None => {}
+        }
+
+        match self.mode() {
+            CookieMode::AcceptAll => true,
+            CookieMode::BlockAll => false,
+            CookieMode::BlockThirdParty => !is_third_party(url, top_level),
+        }
+    }
+}
+
+/// Returns true when the given URL belongs to a different site than the top-level URL
+pub fn is_third_party(url: &Url, top_level: Option<&Url>) -> bool {
+    let Some(top_level) = top_level else {
+        return false;
+    };
+
+    site_for_url(url) != site_for_url(top_level)
+}
+
+/// Mozilla's public suffix list, which is bundled with the browser
+const PUBLIC_SUFFIX_LIST: &str = include_str!("../../resources/public_suffix_list.dat");
+
+fn public_suffix_list() -> &'static List {
+    static LIST: OnceLock<List> = OnceLock::new();
+    LIST.get_or_init(|| PUBLIC_SUFFIX_LIST.parse().expect("bundled public suffix list is valid"))
+}
+
+/// Returns the site (the registrable domain) of the given URL, like `example.co.uk` for
+/// `www.example.co.uk`. Hosts that have no registrable domain, like IP addresses, single-label hosts
+/// and public suffixes themselves, are used as-is.
+pub fn site_for_url(url: &Url) -> Option<String> {
+    match url.host()? {
+        url::Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            let site = public_suffix_list()
+                .domain(domain.as_bytes())
+                .and_then(|site| std::str::from_utf8(site.as_bytes()).ok().map(str::to_string));
+            Some(site.unwrap_or(domain))
+        }
+        host => Some(host.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: CookieMode) -> CookiePolicy {
+        CookiePolicy::new(mode, SqlitePermissionStore::new(":memory:").unwrap())
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_site_for_url() {
+        assert_eq!(site_for_url(&url("https://www.example.com/foo")), Some("example.com".into()));
+        assert_eq!(site_for_url(&url("https://a.b.example.com")), Some("example.com".into()));
+        assert_eq!(site_for_url(&url("https://example.com")), Some("example.com".into()));
+        assert_eq!(site_for_url(&url("https://www.example.co.uk")), Some("example.co.uk".into()));
+        assert_eq!(site_for_url(&url("https://gosub.github.io/x")), Some("gosub.github.io".into()));
+        assert_eq!(site_for_url(&url("https://co.uk")), Some("co.uk".into()));
+        assert_eq!(site_for_url(&url("http://localhost:8080")), Some("localhost".into()));
+        assert_eq!(site_for_url(&url("http://127.0.0.1/")), Some("127.0.0.1".into()));
+        assert_eq!(site_for_url(&url("about:blank")), None);
+    }
+
+    #[test]
+    fn test_third_party() {
+        let top = url("https://www.example.com");
+        assert!(!is_third_party(&url("https://cdn.example.com/x.js"), Some(&top)));
+        assert!(is_third_party(&url("https://tracker.net/pixel"), Some(&top)));
+        assert!(is_third_party(
+            &url("https://tracker.github.io/pixel"),
+            Some(&url("https://gosub.github.io"))
+        ));
+        assert!(is_third_party(&url("https://shop.co.uk"), Some(&url("https://news.co.uk"))));
+        assert!(!is_third_party(&url("https://tracker.net/pixel"), None));
+    }
+
+    #[test]
+    fn test_modes() {
+        let top = url("https://www.example.com");
+        let first = url("https://login.example.com");
+        let third = url("https://tracker.net");
+
+        let p = policy(CookieMode::AcceptAll);
+        assert!(p.allows(&first, Some(&top)));
+        assert!(p.allows(&third, Some(&top)));
+
+        p.set_mode(CookieMode::BlockThirdParty);
+        assert!(p.allows(&first, Some(&top)));
+        assert!(!p.allows(&third, Some(&top)));
+
+        p.set_mode(CookieMode::BlockAll);
+        assert!(!p.allows(&first, Some(&top)));
+        assert!(!p.allows(&third, Some(&top)));
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let top = url("https://www.example.com");
+        let third = url("https://tracker.net");
+
+        let p = policy(CookieMode::BlockAll);
+        p.permissions().set("example.com", SitePermission::Allow);
+        assert!(p.allows(&top, None));
+        assert!(!p.allows(&third, Some(&top)));
+
+        p.set_mode(CookieMode::AcceptAll);
+        p.permissions().set("tracker.net", SitePermission::Block);
+        assert!(!p.allows(&third, Some(&top)));
+
+        p.permissions().remove("tracker.net");
+        assert!(p.allows(&third, Some(&top)));
+        assert_eq!(p.permissions().all(), vec![("example.com".to_string(), SitePermission::Allow)]);
+    }
+}
@@ -1,7 +1,8 @@
-use crate::cookies::jar::StorageBackend;
+use crate::cookies::jar::{StorageBackend, StoredCookie};
 use cookie::Cookie;
 use log::warn;
 use rusqlite::{params, Connection};
+use std::path::Path;
 use std::sync::Mutex;
 use url::Url;
 use uuid::Uuid;
@@ -12,7 +13,7 @@ pub struct SqliteStorage {
 }
 
 impl SqliteStorage {
-    pub fn new(database_path: &str) -> Result<Self, rusqlite::Error> {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
         let conn = Connection::open(database_path)?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS cookies (
@@ -33,23 +34,23 @@ impl SqliteStorage {
 }
 
 impl StorageBackend for SqliteStorage {
-    fn store(&self, url: &Url, cookie: &Cookie) {
+    fn store(&self, url: &Url, cookie: &Cookie) -> bool {
         if cookie.expires().is_none() {
             // No expires found
-            return;
+            return false;
         }
 
         let expires = cookie.expires().unwrap();
         if expires.is_session() {
             // Session cookie, do not store
-            return;
+            return false;
         }
 
         let expires_at = expires.datetime().unwrap().unix_timestamp();
 
-        let domain = match cookie.domain() {
-            Some(d) => d.to_string(),
-            None => url.domain().unwrap().to_string(),
+        // Host-only cookies belong to the host of the URL, which can be an IP address
+        let Some(domain) = cookie.domain().or(url.host_str()).map(str::to_string) else {
+            return false;
         };
 
         let e = self.conn.lock().unwrap().execute(
@@ -66,11 +67,13 @@ impl StorageBackend for SqliteStorage {
         );
         if let Err(e) = e {
             warn!("failed to store cookie: {:?}", e);
+            return false;
         }
+        true
     }
 
     fn get(&self, url: &Url) -> Option<Vec<Cookie>> {
-        let domain = url.domain().unwrap().to_string();
+        let domain = url.host_str()?.to_string();
         let path = url.path().to_string();
 
         let locked_conn = self.conn.lock().unwrap();
@@ -93,4 +96,36 @@ impl StorageBackend for SqliteStorage {
 
         Some(cookies)
     }
+
+    fn all(&self) -> Vec<StoredCookie> {
+        let locked_conn = self.conn.lock().unwrap();
+
+        let stmt = locked_conn.prepare("SELECT domain, cookie FROM cookies WHERE expires_at > ?1");
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let rows = stmt.query_map(params![chrono::Utc::now().timestamp()], |row| {
+            let domain: String = row.get(0)?;
+            let cookie_str: String = row.get(1)?;
+            Ok((domain, cookie_str))
+        });
+
+        let Ok(rows) = rows else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok())
+            .filter_map(|(domain, cookie_str)| {
+                let cookie = Cookie::parse(cookie_str).ok()?;
+                Some(StoredCookie {
+                    domain,
+                    host_only: cookie.domain().is_none(),
+                    cookie,
+                })
+            })
+            .collect()
+    }
 }
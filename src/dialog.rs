@@ -1,2 +1,10 @@
 pub mod about;
+pub mod bookmarks;
+pub mod containers;
+pub mod downloads;
+pub mod history;
+pub mod profiles;
+pub mod search_engines;
+pub mod settings;
 pub mod shortcuts;
+pub mod site_permissions;
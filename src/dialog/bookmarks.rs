@@ -0,0 +1,466 @@
+use crate::application::Application;
+use crate::bookmarks::{bookmark_store, export_bookmarks, import_bookmarks, parse_tags, Bookmark, BookmarkId, ImportFormat};
+use gtk4::gdk::{ContentProvider, DragAction};
+use gtk4::prelude::*;
+use gtk4::{
+    gio, glib, Align, Button, DragSource, DropTarget, Entry, FileDialog, Grid, Image, Label, ListBox, MenuButton, Orientation, PolicyType,
+    Popover, ScrolledWindow, SearchEntry, SelectionMode, Window,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use url::Url;
+
+/// Indentation of every level of nested folders, in pixels
+const FOLDER_INDENT: i32 = 20;
+
+/// Maximum number of bookmarks shown when searching
+const MAX_SEARCH_RESULTS: usize = 200;
+
+pub struct BookmarksDialog;
+
+impl BookmarksDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Bookmarks")
+            .default_width(640)
+            .default_height(480)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let view = BookmarksView::new(app);
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&view.list)
+            .build();
+
+        // Dropping a bookmark below the last row moves it to the end of the top level
+        let drop_target = DropTarget::new(String::static_type(), DragAction::MOVE);
+        drop_target.connect_drop({
+            let view = view.clone();
+            move |_, value, _, _| {
+                let Some(id) = dragged_bookmark(value) else {
+                    return false;
+                };
+                view.move_bookmark(id, None, usize::MAX)
+            }
+        });
+        scroller.add_controller(drop_target);
+
+        vbox.append(&view.search);
+        vbox.append(&scroller);
+        vbox.append(&view.edit_form());
+        vbox.append(&view.button_row());
+
+        view.populate(None);
+
+        window.set_child(Some(&vbox));
+        window
+    }
+}
+
+/// Returns the id of the bookmark that is dropped
+fn dragged_bookmark(value: &glib::Value) -> Option<BookmarkId> {
+    value.get::<String>().ok()?.parse().ok()
+}
+
+/// State of the bookmarks window
+struct BookmarksView {
+    app: Application,
+    search: SearchEntry,
+    list: ListBox,
+    /// Bookmarks that are currently shown, in the same order as the rows in the list
+    rows: RefCell<Vec<Bookmark>>,
+    title: Entry,
+    url: Entry,
+    keyword: Entry,
+    tags: Entry,
+    /// Result of the last import or export
+    status: Label,
+}
+
+impl BookmarksView {
+    fn new(app: &Application) -> Rc<Self> {
+        let view = Rc::new(Self {
+            app: app.clone(),
+            search: SearchEntry::builder().placeholder_text("Search bookmarks").build(),
+            list: ListBox::builder().selection_mode(SelectionMode::Single).build(),
+            rows: RefCell::new(Vec::new()),
+            title: Entry::builder().hexpand(true).build(),
+            url: Entry::builder().hexpand(true).build(),
+            keyword: Entry::builder().hexpand(true).build(),
+            tags: Entry::builder().hexpand(true).placeholder_text("Comma separated").build(),
+            status: Label::builder().hexpand(true).halign(Align::End).build(),
+        });
+
+        view.search.connect_search_changed({
+            let view = view.clone();
+            move |_| {
+                view.populate(None);
+            }
+        });
+
+        view.list.connect_row_selected({
+            let view = view.clone();
+            move |_, _| {
+                view.fill_form();
+            }
+        });
+
+        // Activating a bookmark opens it in the current tab of the browser
+        view.list.connect_row_activated({
+            let view = view.clone();
+            move |_, row| {
+                let url = view.rows.borrow().get(row.index() as usize).and_then(|b| b.url.clone());
+                if let (Some(url), Some(window)) = (url, view.app.browser_window()) {
+                    _ = WidgetExt::activate_action(&window, "win.open-url", Some(&url.as_str().to_variant()));
+                }
+            }
+        });
+
+        view
+    }
+
+    /// Form to edit the selected bookmark
+    fn edit_form(self: &Rc<Self>) -> Grid {
+        let grid = Grid::builder().row_spacing(5).column_spacing(10).build();
+
+        for (row, (label, entry)) in [
+            ("Name", &self.title),
+            ("URL", &self.url),
+            ("Keyword", &self.keyword),
+            ("Tags", &self.tags),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let label = Label::new(Some(label));
+            label.set_halign(Align::End);
+            grid.attach(&label, 0, row as i32, 1, 1);
+            grid.attach(entry, 1, row as i32, 1, 1);
+        }
+
+        let save_button = Button::with_label("Save");
+        save_button.set_halign(Align::End);
+        save_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.save_selected();
+            }
+        });
+        grid.attach(&save_button, 1, 4, 1, 1);
+
+        grid.set_sensitive(false);
+        self.list.connect_row_selected({
+            let grid = grid.clone();
+            move |_, row| grid.set_sensitive(row.is_some())
+        });
+
+        grid
+    }
+
+    /// Row with the buttons to add folders, remove bookmarks and import and export bookmarks
+    fn button_row(self: &Rc<Self>) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let folder_button = Button::with_label("New Folder");
+        folder_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.add_folder();
+            }
+        });
+        hbox.append(&folder_button);
+
+        let remove_button = Button::with_label("Delete");
+        remove_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.remove_selected();
+            }
+        });
+        hbox.append(&remove_button);
+
+        hbox.append(&self.status);
+
+        // Every format has its own entry, as the files cannot be told apart by their name
+        let formats = gtk4::Box::new(Orientation::Vertical, 0);
+        let popover = Popover::builder().child(&formats).build();
+        for format in ImportFormat::ALL {
+            let button = Button::builder().label(format.name()).has_frame(false).build();
+            button.connect_clicked({
+                let view = self.clone();
+                let popover = popover.clone();
+                move |_| {
+                    popover.popdown();
+                    view.import(format);
+                }
+            });
+            formats.append(&button);
+        }
+        let import_button = MenuButton::builder().label("Import").popover(&popover).build();
+        hbox.append(&import_button);
+
+        let export_button = Button::with_label("Export");
+        export_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.export();
+            }
+        });
+        hbox.append(&export_button);
+
+        hbox
+    }
+
+    /// Asks for a file in the given format and imports its bookmarks
+    fn import(self: &Rc<Self>, format: ImportFormat) {
+        let dialog = FileDialog::builder()
+            .title(format!("Import bookmarks from {}", format.name()))
+            .modal(true)
+            .build();
+
+        let window = self.list.root().and_downcast::<Window>();
+        dialog.open(window.as_ref(), gio::Cancellable::NONE, {
+            let view = self.clone();
+            move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+
+                match import_bookmarks(&bookmark_store(), format, &path) {
+                    Ok(count) => {
+                        view.status.set_text(&format!("Imported {} bookmarks", count));
+                        view.populate(None);
+                        view.app.bookmarks_changed();
+                    }
+                    Err(e) => view.status.set_text(&format!("Import failed: {}", e)),
+                }
+            }
+        });
+    }
+
+    /// Asks for a file and exports all bookmarks to it as HTML
+    fn export(self: &Rc<Self>) {
+        let dialog = FileDialog::builder()
+            .title("Export bookmarks")
+            .initial_name("bookmarks.html")
+            .modal(true)
+            .build();
+
+        let window = self.list.root().and_downcast::<Window>();
+        dialog.save(window.as_ref(), gio::Cancellable::NONE, {
+            let view = self.clone();
+            move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+
+                match export_bookmarks(&bookmark_store(), &path) {
+                    Ok(count) => view.status.set_text(&format!("Exported {} bookmarks", count)),
+                    Err(e) => view.status.set_text(&format!("Export failed: {}", e)),
+                }
+            }
+        });
+    }
+
+    /// (Re)fills the list with the bookmarks tree, or with the search results when searching. The
+    /// given bookmark is selected afterwards.
+    fn populate(self: &Rc<Self>, select: Option<BookmarkId>) {
+        self.list.remove_all();
+
+        let query = self.search.text();
+        let mut rows = Vec::new();
+        if query.trim().is_empty() {
+            collect_tree(None, 0, &mut rows);
+        } else {
+            rows = bookmark_store()
+                .search(&query, MAX_SEARCH_RESULTS)
+                .into_iter()
+                .map(|bookmark| (bookmark, 0))
+                .collect();
+        }
+
+        for (bookmark, depth) in &rows {
+            self.list.append(&self.create_row(bookmark, *depth));
+        }
+        *self.rows.borrow_mut() = rows.into_iter().map(|(bookmark, _)| bookmark).collect();
+
+        let index = select.and_then(|id| self.rows.borrow().iter().position(|bookmark| bookmark.id == id));
+        match index {
+            Some(index) => self.list.select_row(self.list.row_at_index(index as i32).as_ref()),
+            None => self.list.unselect_all(),
+        }
+    }
+
+    fn create_row(self: &Rc<Self>, bookmark: &Bookmark, depth: i32) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+        hbox.set_margin_start(depth * FOLDER_INDENT);
+
+        let icon = if bookmark.is_folder() {
+            "folder-symbolic"
+        } else {
+            "starred-symbolic"
+        };
+        hbox.append(&Image::from_icon_name(icon));
+
+        let title = Label::new(Some(bookmark.title.as_str()));
+        title.set_halign(Align::Start);
+        title.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        hbox.append(&title);
+
+        let mut details = bookmark.url.as_ref().map(|url| url.to_string()).unwrap_or_default();
+        if let Some(keyword) = &bookmark.keyword {
+            details.push_str(&format!("  [{}]", keyword));
+        }
+        if !bookmark.tags.is_empty() {
+            details.push_str(&format!("  {}", bookmark.tags.join(", ")));
+        }
+        let details = Label::new(Some(details.as_str()));
+        details.set_halign(Align::Start);
+        details.set_hexpand(true);
+        details.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        details.add_css_class("dim-label");
+        hbox.append(&details);
+
+        let drag_source = DragSource::new();
+        drag_source.set_actions(DragAction::MOVE);
+        let id = bookmark.id;
+        drag_source.connect_prepare(move |_, _, _| Some(ContentProvider::for_value(&id.to_string().to_value())));
+        hbox.add_controller(drag_source);
+
+        // Dropping on a folder moves the bookmark into the folder, dropping on a bookmark moves it
+        // right before that bookmark
+        let drop_target = DropTarget::new(String::static_type(), DragAction::MOVE);
+        drop_target.connect_drop({
+            let view = self.clone();
+            let target = bookmark.clone();
+            move |_, value, _, _| {
+                let Some(id) = dragged_bookmark(value) else {
+                    return false;
+                };
+                if id == target.id {
+                    return false;
+                }
+
+                if target.is_folder() {
+                    return view.move_bookmark(id, Some(target.id), usize::MAX);
+                }
+                let position = bookmark_store()
+                    .children(target.parent)
+                    .iter()
+                    .filter(|sibling| sibling.id != id)
+                    .position(|sibling| sibling.id == target.id)
+                    .unwrap_or(0);
+                view.move_bookmark(id, target.parent, position)
+            }
+        });
+        hbox.add_controller(drop_target);
+
+        hbox
+    }
+
+    /// Moves the bookmark, and shows the new tree. The list is rebuilt after the drop has been
+    /// handled, as the row that received the drop is removed.
+    fn move_bookmark(self: &Rc<Self>, id: BookmarkId, parent: Option<BookmarkId>, position: usize) -> bool {
+        if !bookmark_store().move_to(id, parent, position) {
+            return false;
+        }
+
+        let view = self.clone();
+        glib::idle_add_local_once(move || {
+            view.populate(Some(id));
+            view.app.bookmarks_changed();
+        });
+        true
+    }
+
+    fn selected(&self) -> Option<Bookmark> {
+        let row = self.list.selected_row()?;
+        self.rows.borrow().get(row.index() as usize).cloned()
+    }
+
+    /// Shows the selected bookmark in the edit form
+    fn fill_form(&self) {
+        let bookmark = self.selected();
+
+        self.title.set_text(bookmark.as_ref().map_or("", |b| b.title.as_str()));
+        self.url
+            .set_text(bookmark.as_ref().and_then(|b| b.url.as_ref()).map_or("", |url| url.as_str()));
+        self.url.set_sensitive(bookmark.as_ref().is_some_and(|b| !b.is_folder()));
+        self.url.remove_css_class("error");
+        self.keyword
+            .set_text(bookmark.as_ref().and_then(|b| b.keyword.as_deref()).unwrap_or(""));
+        self.keyword.set_sensitive(bookmark.as_ref().is_some_and(|b| !b.is_folder()));
+        self.tags
+            .set_text(&bookmark.as_ref().map(|b| b.tags.join(", ")).unwrap_or_default());
+        self.tags.set_sensitive(bookmark.as_ref().is_some_and(|b| !b.is_folder()));
+    }
+
+    /// Stores the edit form in the selected bookmark
+    fn save_selected(self: &Rc<Self>) {
+        let Some(mut bookmark) = self.selected() else {
+            return;
+        };
+
+        bookmark.title = self.title.text().trim().to_string();
+        if !bookmark.is_folder() {
+            let Ok(url) = Url::parse(self.url.text().trim()) else {
+                self.url.add_css_class("error");
+                return;
+            };
+            bookmark.url = Some(url);
+
+            let keyword = self.keyword.text().trim().to_string();
+            bookmark.keyword = (!keyword.is_empty()).then_some(keyword);
+            bookmark.tags = parse_tags(&self.tags.text());
+        }
+
+        bookmark_store().update(&bookmark);
+        self.populate(Some(bookmark.id));
+        self.app.bookmarks_changed();
+    }
+
+    /// Adds a folder to the selected folder, or next to the selected bookmark
+    fn add_folder(self: &Rc<Self>) {
+        let parent = self
+            .selected()
+            .and_then(|bookmark| if bookmark.is_folder() { Some(bookmark.id) } else { bookmark.parent });
+
+        if let Some(folder) = bookmark_store().add_folder(parent, "New Folder") {
+            self.populate(Some(folder.id));
+            self.app.bookmarks_changed();
+        }
+    }
+
+    /// Removes the selected bookmark, or the selected folder with everything in it
+    fn remove_selected(self: &Rc<Self>) {
+        let Some(bookmark) = self.selected() else {
+            return;
+        };
+
+        bookmark_store().remove(bookmark.id);
+        self.populate(bookmark.parent);
+        self.app.bookmarks_changed();
+    }
+}
+
+/// Collects the bookmarks in the given folder and its subfolders, in the order they are shown,
+/// together with their depth in the tree
+fn collect_tree(parent: Option<BookmarkId>, depth: i32, rows: &mut Vec<(Bookmark, i32)>) {
+    for bookmark in bookmark_store().children(parent) {
+        let folder = bookmark.is_folder().then_some(bookmark.id);
+        rows.push((bookmark, depth));
+        if let Some(folder) = folder {
+            collect_tree(Some(folder), depth + 1, rows);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::application::Application;
+use crate::containers::{container_store, ContainerColor};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, DropDown, Entry, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SelectionMode, Window};
+
+pub struct ContainersDialog;
+
+impl ContainersDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Containers")
+            .default_width(400)
+            .default_height(360)
+            .modal(true)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let list = ListBox::builder().selection_mode(SelectionMode::None).build();
+        Self::populate(&list);
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        vbox.append(&scroller);
+        vbox.append(&Self::add_row(&list));
+
+        window.set_child(Some(&vbox));
+        window
+    }
+
+    /// Row that allows to add a new container
+    fn add_row(list: &ListBox) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let entry = Entry::builder().placeholder_text("Container name").hexpand(true).build();
+        hbox.append(&entry);
+
+        let names: Vec<String> = ContainerColor::ALL.iter().map(|color| color.to_string()).collect();
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let colors = DropDown::from_strings(&names);
+        hbox.append(&colors);
+
+        let button = Button::with_label("Add");
+        let list_clone = list.clone();
+        button.connect_clicked(move |_| {
+            let name = entry.text().trim().to_string();
+            if name.is_empty() {
+                return;
+            }
+            let color = ContainerColor::ALL[colors.selected() as usize % ContainerColor::ALL.len()];
+
+            container_store().add(&name, color);
+            entry.set_text("");
+            Self::populate(&list_clone);
+        });
+        hbox.append(&button);
+
+        hbox
+    }
+
+    /// (Re)fills the list with all the containers
+    fn populate(list: &ListBox) {
+        list.remove_all();
+
+        for container in container_store().all() {
+            let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+            let marker = Label::new(None);
+            marker.set_markup(format!("<span foreground=\"{}\">●</span>", container.color.hex()).as_str());
+            hbox.append(&marker);
+
+            let name_label = Label::new(Some(container.name.as_str()));
+            name_label.set_hexpand(true);
+            name_label.set_halign(Align::Start);
+            hbox.append(&name_label);
+
+            let remove_button = Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_has_frame(false);
+            let list_clone = list.clone();
+            remove_button.connect_clicked(move |_| {
+                container_store().remove(container.id);
+                Self::populate(&list_clone);
+            });
+            hbox.append(&remove_button);
+
+            list.append(&hbox);
+        }
+    }
+}
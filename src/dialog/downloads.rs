@@ -0,0 +1,218 @@
+use crate::application::Application;
+use crate::downloads::{download_manager, format_size, Download, DownloadState};
+use gtk4::prelude::*;
+use gtk4::{
+    gio, glib, Align, Button, FileLauncher, Label, ListBox, Orientation, PolicyType, ProgressBar, ScrolledWindow, SelectionMode, Window,
+};
+use log::warn;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How often the progress of the downloads is updated
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct DownloadsDialog;
+
+impl DownloadsDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Downloads")
+            .default_width(560)
+            .default_height(400)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let view = DownloadsView::new();
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&view.list)
+            .build();
+        vbox.append(&scroller);
+
+        view.refresh();
+
+        // Downloads run on the tokio runtime, so their progress is polled while the window is open
+        let weak_window = window.downgrade();
+        glib::timeout_add_local(REFRESH_INTERVAL, move || {
+            if weak_window.upgrade().is_none() {
+                return glib::ControlFlow::Break;
+            }
+            view.refresh();
+            glib::ControlFlow::Continue
+        });
+
+        window.set_child(Some(&vbox));
+        window
+    }
+}
+
+/// The widgets of a download that change while it is in progress
+struct DownloadRow {
+    progress: ProgressBar,
+    status: Label,
+}
+
+/// State of the downloads window
+struct DownloadsView {
+    list: ListBox,
+    /// Downloads that are currently shown with their rows, in the same order as the rows in the list
+    rows: RefCell<Vec<(Download, DownloadRow)>>,
+}
+
+impl DownloadsView {
+    fn new() -> Rc<Self> {
+        let list = ListBox::builder().selection_mode(SelectionMode::None).build();
+        list.set_placeholder(Some(&Label::new(Some("No downloads"))));
+
+        Rc::new(Self {
+            list,
+            rows: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Updates the progress of the rows. The list is only rebuilt when downloads have been added or
+    /// removed or their state has changed, as the buttons depend on the state.
+    fn refresh(self: &Rc<Self>) {
+        let downloads = download_manager().downloads();
+
+        let unchanged = {
+            let rows = self.rows.borrow();
+            rows.len() == downloads.len()
+                && rows
+                    .iter()
+                    .zip(&downloads)
+                    .all(|((shown, _), download)| shown.id == download.id && shown.state == download.state)
+        };
+        if !unchanged {
+            self.populate(downloads);
+            return;
+        }
+
+        for ((shown, row), download) in self.rows.borrow_mut().iter_mut().zip(downloads) {
+            update_row(row, &download);
+            *shown = download;
+        }
+    }
+
+    fn populate(self: &Rc<Self>, downloads: Vec<Download>) {
+        self.list.remove_all();
+
+        let mut rows = Vec::new();
+        for download in downloads {
+            let (widget, row) = self.create_row(&download);
+            self.list.append(&widget);
+            rows.push((download, row));
+        }
+
+        *self.rows.borrow_mut() = rows;
+    }
+
+    fn create_row(self: &Rc<Self>, download: &Download) -> (gtk4::Box, DownloadRow) {
+        let vbox = gtk4::Box::new(Orientation::Vertical, 5);
+        vbox.set_margin_top(5);
+        vbox.set_margin_bottom(5);
+
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let filename = Label::new(Some(&download.filename()));
+        filename.set_halign(Align::Start);
+        filename.set_hexpand(true);
+        filename.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+        filename.set_tooltip_text(Some(download.url.as_str()));
+        hbox.append(&filename);
+
+        let id = download.id;
+        match download.state {
+            DownloadState::InProgress => {
+                hbox.append(&self.action_button("Pause", move || download_manager().pause(id)));
+                hbox.append(&self.action_button("Cancel", move || download_manager().cancel(id)));
+            }
+            DownloadState::Paused => {
+                hbox.append(&self.action_button("Resume", move || download_manager().resume(id)));
+                hbox.append(&self.action_button("Cancel", move || download_manager().cancel(id)));
+            }
+            DownloadState::Completed => {
+                let open_button = Button::with_label("Open Folder");
+                let file = gio::File::for_path(&download.path);
+                open_button.connect_clicked(move |button| {
+                    let window = button.root().and_downcast::<Window>();
+                    FileLauncher::new(Some(&file)).open_containing_folder(window.as_ref(), None::<&gio::Cancellable>, |result| {
+                        if let Err(e) = result {
+                            warn!("failed to open containing folder: {:?}", e);
+                        }
+                    });
+                });
+                hbox.append(&open_button);
+                hbox.append(&self.action_button("Remove", move || download_manager().remove(id)));
+            }
+            DownloadState::Failed | DownloadState::Cancelled => {
+                hbox.append(&self.action_button("Retry", move || download_manager().retry(id)));
+                hbox.append(&self.action_button("Remove", move || download_manager().remove(id)));
+            }
+        }
+        vbox.append(&hbox);
+
+        let row = DownloadRow {
+            progress: ProgressBar::new(),
+            status: Label::new(None),
+        };
+        row.status.set_halign(Align::Start);
+        row.status.add_css_class("dim-label");
+        row.progress
+            .set_visible(matches!(download.state, DownloadState::InProgress | DownloadState::Paused));
+        update_row(&row, download);
+
+        vbox.append(&row.progress);
+        vbox.append(&row.status);
+
+        (vbox, row)
+    }
+
+    /// Returns a button that runs the action, after which the list is refreshed right away
+    fn action_button(self: &Rc<Self>, label: &str, action: impl Fn() + 'static) -> Button {
+        let button = Button::with_label(label);
+        button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                action();
+                view.refresh();
+            }
+        });
+        button
+    }
+}
+
+fn update_row(row: &DownloadRow, download: &Download) {
+    match download.progress() {
+        Some(fraction) => row.progress.set_fraction(fraction),
+        None if download.state == DownloadState::InProgress => row.progress.pulse(),
+        None => {}
+    }
+    row.status.set_text(&status_text(download));
+}
+
+/// Returns the text below a download, like "Paused, 1.5 MB of 3.0 MB"
+fn status_text(download: &Download) -> String {
+    let size = match download.total {
+        Some(total) => format!("{} of {}", format_size(download.received), format_size(total)),
+        None => format_size(download.received),
+    };
+
+    match download.state {
+        DownloadState::InProgress => size,
+        DownloadState::Paused => format!("Paused, {}", size),
+        DownloadState::Completed => format!("Completed, {}", format_size(download.received)),
+        DownloadState::Failed => format!("Failed: {}", download.error.as_deref().unwrap_or("unknown error")),
+        DownloadState::Cancelled => "Cancelled".to_string(),
+    }
+}
@@ -0,0 +1,229 @@
+use crate::application::Application;
+use crate::history::{history_store, DateGroup, Visit};
+use chrono::{Duration, Local, Utc};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SearchEntry, SelectionMode, Window};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Maximum number of visits shown in the window
+const MAX_VISITS: usize = 500;
+
+pub struct HistoryDialog;
+
+impl HistoryDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("History")
+            .default_width(640)
+            .default_height(480)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let view = HistoryView::new(app);
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&view.list)
+            .build();
+
+        vbox.append(&view.search);
+        vbox.append(&scroller);
+        vbox.append(&view.button_row());
+
+        view.populate();
+
+        window.set_child(Some(&vbox));
+        window
+    }
+}
+
+/// State of the history window
+struct HistoryView {
+    app: Application,
+    search: SearchEntry,
+    list: ListBox,
+    /// Visits that are currently shown, in the same order as the rows in the list
+    rows: Rc<RefCell<Vec<Visit>>>,
+}
+
+impl HistoryView {
+    fn new(app: &Application) -> Rc<Self> {
+        let view = Rc::new(Self {
+            app: app.clone(),
+            search: SearchEntry::builder().placeholder_text("Search history").build(),
+            list: ListBox::builder().selection_mode(SelectionMode::Single).build(),
+            rows: Rc::new(RefCell::new(Vec::new())),
+        });
+
+        view.search.connect_search_changed({
+            let view = view.clone();
+            move |_| {
+                view.populate();
+            }
+        });
+
+        // Every date group starts with a header
+        view.list.set_header_func({
+            let rows = view.rows.clone();
+            move |row, before| {
+                let rows = rows.borrow();
+                let now = Local::now();
+                let group = |index: i32| {
+                    rows.get(index as usize)
+                        .map(|visit| DateGroup::for_date(visit.visited_at.with_timezone(&Local), now))
+                };
+
+                let current = group(row.index());
+                if current.is_none() || before.and_then(|before| group(before.index())) == current {
+                    row.set_header(None::<&gtk4::Widget>);
+                    return;
+                }
+
+                let header = Label::new(current.map(|group| group.label()));
+                header.set_halign(Align::Start);
+                header.set_margin_top(10);
+                header.set_margin_bottom(5);
+                header.add_css_class("heading");
+                row.set_header(Some(&header));
+            }
+        });
+
+        // Activating a visit opens the page in the current tab of the browser
+        view.list.connect_row_activated({
+            let view = view.clone();
+            move |_, row| {
+                let url = view.rows.borrow().get(row.index() as usize).map(|visit| visit.url.clone());
+                if let (Some(url), Some(window)) = (url, view.app.browser_window()) {
+                    _ = WidgetExt::activate_action(&window, "win.open-url", Some(&url.as_str().to_variant()));
+                }
+            }
+        });
+
+        view
+    }
+
+    /// Row with the buttons to remove history
+    fn button_row(self: &Rc<Self>) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let remove_button = Button::with_label("Delete");
+        remove_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.remove_selected();
+            }
+        });
+        hbox.append(&remove_button);
+
+        let forget_button = Button::with_label("Forget This Site");
+        forget_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                view.forget_selected_site();
+            }
+        });
+        hbox.append(&forget_button);
+
+        // Both buttons work on the selected visit
+        remove_button.set_sensitive(false);
+        forget_button.set_sensitive(false);
+        self.list.connect_row_selected(move |_, row| {
+            remove_button.set_sensitive(row.is_some());
+            forget_button.set_sensitive(row.is_some());
+        });
+
+        let clear_button = Button::with_label("Clear Last Hour");
+        clear_button.set_hexpand(true);
+        clear_button.set_halign(Align::End);
+        clear_button.connect_clicked({
+            let view = self.clone();
+            move |_| {
+                history_store().remove_since(Utc::now() - Duration::hours(1));
+                view.populate();
+            }
+        });
+        hbox.append(&clear_button);
+
+        hbox
+    }
+
+    /// (Re)fills the list with the most recent visits that match the search
+    fn populate(&self) {
+        self.list.remove_all();
+
+        let visits = history_store().visits(&self.search.text(), MAX_VISITS);
+        // The rows must be known before they are added, as the headers are based on them
+        *self.rows.borrow_mut() = visits.clone();
+
+        let now = Local::now();
+        for visit in &visits {
+            self.list.append(&create_row(visit, now));
+        }
+    }
+
+    fn selected(&self) -> Option<Visit> {
+        let row = self.list.selected_row()?;
+        self.rows.borrow().get(row.index() as usize).cloned()
+    }
+
+    fn remove_selected(&self) {
+        let Some(visit) = self.selected() else {
+            return;
+        };
+
+        history_store().remove_visit(visit.id);
+        self.populate();
+    }
+
+    /// Removes all visits to the site of the selected visit
+    fn forget_selected_site(&self) {
+        let Some(host) = self.selected().and_then(|visit| visit.url.host_str().map(|host| host.to_string())) else {
+            return;
+        };
+
+        history_store().remove_site(host.strip_prefix("www.").unwrap_or(&host));
+        self.populate();
+    }
+}
+
+fn create_row(visit: &Visit, now: chrono::DateTime<Local>) -> gtk4::Box {
+    let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+    // Visits of today and yesterday only need the time, as the header shows the day
+    let visited_at = visit.visited_at.with_timezone(&Local);
+    let time_format = match DateGroup::for_date(visited_at, now) {
+        DateGroup::Today | DateGroup::Yesterday => "%H:%M",
+        _ => "%d %b %Y %H:%M",
+    };
+    let time = Label::new(Some(&visited_at.format(time_format).to_string()));
+    time.add_css_class("dim-label");
+    hbox.append(&time);
+
+    let title = if visit.title.is_empty() {
+        visit.url.as_str()
+    } else {
+        visit.title.as_str()
+    };
+    let title = Label::new(Some(title));
+    title.set_halign(Align::Start);
+    title.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    hbox.append(&title);
+
+    let url = Label::new(Some(visit.url.as_str()));
+    url.set_halign(Align::Start);
+    url.set_hexpand(true);
+    url.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    url.add_css_class("dim-label");
+    hbox.append(&url);
+
+    hbox
+}
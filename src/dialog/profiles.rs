@@ -0,0 +1,125 @@
+use crate::application::Application;
+use crate::profile::{is_valid_profile_name, list_profiles, select_profile, Profile};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, Entry, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SelectionMode, Window};
+use log::warn;
+
+/// Asks which profile to use when the browser starts and there are several profiles
+pub struct ProfilePickerDialog;
+
+impl ProfilePickerDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Choose a Profile")
+            .default_width(360)
+            .default_height(320)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let list = ListBox::builder().selection_mode(SelectionMode::Single).build();
+        for name in list_profiles() {
+            let label = Label::new(Some(&name));
+            label.set_halign(Align::Start);
+            label.set_margin_top(5);
+            label.set_margin_bottom(5);
+            list.append(&label);
+        }
+        list.select_row(list.row_at_index(0).as_ref());
+
+        list.connect_row_activated({
+            let app = app.clone();
+            let window = window.clone();
+            move |_, row| {
+                if let Some(label) = row.child().and_downcast::<Label>() {
+                    open_profile(&app, &window, &label.text());
+                }
+            }
+        });
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        let heading = Label::new(Some("Which profile do you want to use?"));
+        heading.set_halign(Align::Start);
+        vbox.append(&heading);
+        vbox.append(&scroller);
+        vbox.append(&Self::create_row(app, &window));
+
+        let open_button = Button::with_label("Open");
+        open_button.set_halign(Align::End);
+        open_button.add_css_class("suggested-action");
+        open_button.connect_clicked({
+            let app = app.clone();
+            let window = window.clone();
+            move |_| {
+                let label = list.selected_row().and_then(|row| row.child()).and_downcast::<Label>();
+                if let Some(label) = label {
+                    open_profile(&app, &window, &label.text());
+                }
+            }
+        });
+        vbox.append(&open_button);
+
+        window.set_child(Some(&vbox));
+        window
+    }
+
+    /// Row that allows to create a new profile, which is opened right away
+    fn create_row(app: &Application, window: &Window) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let entry = Entry::builder().placeholder_text("New profile").hexpand(true).build();
+        hbox.append(&entry);
+
+        let button = Button::with_label("Create");
+        button.set_sensitive(false);
+        hbox.append(&button);
+
+        entry.connect_changed({
+            let button = button.clone();
+            move |entry| button.set_sensitive(is_valid_profile_name(entry.text().trim()))
+        });
+
+        let app = app.clone();
+        let window = window.clone();
+        button.connect_clicked(move |_| {
+            let name = entry.text().trim().to_string();
+            if is_valid_profile_name(&name) {
+                open_profile(&app, &window, &name);
+            }
+        });
+
+        hbox
+    }
+}
+
+/// Selects the profile and opens it. The picker is closed afterwards, as a browser window (or an
+/// error message) keeps the application running. Profiles with another application id than this
+/// instance are opened in a new process, see `Application::restart_with_profile`.
+fn open_profile(app: &Application, picker: &Window, name: &str) {
+    let profile = Profile::named(name);
+    if app.application_id().as_deref() != Some(profile.application_id().as_str()) {
+        if let Err(e) = app.restart_with_profile(name) {
+            warn!(target: "gtk", "failed to start the browser with profile {}: {:?}", name, e);
+        }
+        return;
+    }
+
+    if let Err(e) = select_profile(profile) {
+        warn!(target: "gtk", "failed to select profile {}: {:?}", name, e);
+        return;
+    }
+
+    app.open_profile();
+    picker.close();
+}
@@ -0,0 +1,188 @@
+use crate::application::Application;
+use crate::fetcher::fetch_url_body;
+use crate::fetcher::network_profile::{network_profiles, NetworkKind};
+use crate::fetcher::search_engine::{parse_opensearch, search_engines, set_search_engines, SearchEngine};
+use crate::runtime;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Align, Button, CheckButton, Entry, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SelectionMode, Window};
+use log::info;
+use url::Url;
+
+pub struct SearchEnginesDialog;
+
+impl SearchEnginesDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Search Engines")
+            .default_width(560)
+            .default_height(420)
+            .modal(true)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let list = ListBox::builder().selection_mode(SelectionMode::None).build();
+        Self::populate(&list);
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        vbox.append(&scroller);
+        vbox.append(&Self::add_row(&list));
+        vbox.append(&Self::import_row(&list));
+
+        window.set_child(Some(&vbox));
+        window
+    }
+
+    /// Row that allows to add a new search engine by hand
+    fn add_row(list: &ListBox) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let name = Entry::builder().placeholder_text("Name").width_chars(10).build();
+        let keyword = Entry::builder().placeholder_text("Keyword").width_chars(6).build();
+        let template = Entry::builder()
+            .placeholder_text("https://example.com/?q={searchTerms}")
+            .hexpand(true)
+            .build();
+        hbox.append(&name);
+        hbox.append(&keyword);
+        hbox.append(&template);
+
+        let button = Button::with_label("Add");
+        let list_clone = list.clone();
+        button.connect_clicked(move |_| {
+            let name_text = name.text().trim().to_string();
+            let template_text = template.text().trim().to_string();
+            if name_text.is_empty() || !template_text.contains("{searchTerms}") || Url::parse(&template_text).is_err() {
+                return;
+            }
+            let keyword_text = keyword.text().trim().to_string();
+            let keyword_text = (!keyword_text.is_empty()).then_some(keyword_text.as_str());
+
+            let mut engines = (*search_engines()).clone();
+            engines.add(SearchEngine::new(&name_text, keyword_text, &template_text, None));
+            set_search_engines(engines);
+
+            name.set_text("");
+            keyword.set_text("");
+            template.set_text("");
+            Self::populate(&list_clone);
+        });
+        hbox.append(&button);
+
+        hbox
+    }
+
+    /// Row that allows to import a search engine from an OpenSearch description document
+    fn import_row(list: &ListBox) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let entry = Entry::builder()
+            .placeholder_text("URL of an OpenSearch description")
+            .hexpand(true)
+            .build();
+        hbox.append(&entry);
+
+        let button = Button::with_label("Import");
+        let list_clone = list.clone();
+        button.connect_clicked(move |button| {
+            let Ok(url) = Url::parse(entry.text().trim()) else {
+                return;
+            };
+
+            entry.remove_css_class("error");
+            button.set_sensitive(false);
+            let button = button.clone();
+            let entry = entry.clone();
+            let list = list_clone.clone();
+            glib::spawn_future_local(async move {
+                let network = network_profiles().lock().unwrap().get(NetworkKind::Default);
+                let body = runtime().spawn(async move { fetch_url_body(url, &network).await }).await;
+
+                let engine = match body {
+                    Ok(Ok(body)) => parse_opensearch(String::from_utf8_lossy(&body).as_ref()),
+                    Ok(Err(e)) => Err(anyhow::anyhow!("{}", e)),
+                    Err(e) => Err(anyhow::anyhow!("{}", e)),
+                };
+
+                match engine {
+                    Ok(engine) => {
+                        info!(target: "gtk", "imported search engine {}", engine.name);
+                        let mut engines = (*search_engines()).clone();
+                        engines.add(engine);
+                        set_search_engines(engines);
+                        entry.set_text("");
+                        Self::populate(&list);
+                    }
+                    Err(e) => {
+                        info!(target: "gtk", "failed to import search engine: {}", e);
+                        entry.add_css_class("error");
+                    }
+                }
+                button.set_sensitive(true);
+            });
+        });
+        hbox.append(&button);
+
+        hbox
+    }
+
+    /// (Re)fills the list with all the search engines
+    fn populate(list: &ListBox) {
+        list.remove_all();
+
+        let engines = search_engines();
+        let mut group: Option<CheckButton> = None;
+
+        for (index, engine) in engines.engines().iter().enumerate() {
+            let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+            let default_button = CheckButton::new();
+            default_button.set_tooltip_text(Some("Use as default search engine"));
+            default_button.set_group(group.as_ref());
+            default_button.set_active(index == engines.default_index());
+            default_button.connect_toggled(move |button| {
+                if button.is_active() {
+                    let mut engines = (*search_engines()).clone();
+                    engines.set_default(index);
+                    set_search_engines(engines);
+                }
+            });
+            hbox.append(&default_button);
+            group.get_or_insert(default_button);
+
+            let name_label = Label::new(Some(engine.name.as_str()));
+            name_label.set_hexpand(true);
+            name_label.set_halign(Align::Start);
+            hbox.append(&name_label);
+
+            let keyword_label = Label::new(engine.keyword.as_deref());
+            keyword_label.add_css_class("dim-label");
+            hbox.append(&keyword_label);
+
+            let remove_button = Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_has_frame(false);
+            let list_clone = list.clone();
+            remove_button.connect_clicked(move |_| {
+                let mut engines = (*search_engines()).clone();
+                engines.remove(index);
+                set_search_engines(engines);
+                Self::populate(&list_clone);
+            });
+            hbox.append(&remove_button);
+
+            list.append(&hbox);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use crate::application::Application;
+use crate::cookies::cookie_policy;
+use crate::cookies::policy::CookieMode;
+use crate::downloads::download_dir;
+use crate::settings::{settings, update_settings, LogLevel, Settings, StartupMode, DEFAULT_SCHEMES};
+use crate::window::BrowserWindow;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::ObjectSubclassIsExt;
+use gtk4::{
+    gio, Align, Button, DropDown, Entry, FileDialog, Label, Orientation, PolicyType, ScrolledWindow, SpinButton, Stack, StackSidebar,
+    Switch, Window,
+};
+use reqwest::header::HeaderValue;
+
+pub struct SettingsDialog;
+
+impl SettingsDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Settings")
+            .default_width(640)
+            .default_height(440)
+            .build();
+
+        let current = settings();
+
+        let stack = Stack::new();
+        stack.set_hexpand(true);
+        stack.add_titled(&page(Self::general_page(app, &current)), Some("general"), "General");
+        stack.add_titled(&page(Self::network_page(&current)), Some("network"), "Network");
+        stack.add_titled(&page(Self::privacy_page(&current)), Some("privacy"), "Privacy");
+        stack.add_titled(&page(Self::appearance_page(app, &current)), Some("appearance"), "Appearance");
+        stack.add_titled(&page(Self::developer_page(app, &current)), Some("developer"), "Developer");
+
+        let sidebar = StackSidebar::new();
+        sidebar.set_stack(&stack);
+
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 0);
+        hbox.append(&sidebar);
+        hbox.append(&stack);
+
+        window.set_child(Some(&hbox));
+        window
+    }
+
+    fn general_page(app: &Application, current: &Settings) -> gtk4::Box {
+        let vbox = page_box();
+
+        let labels: Vec<&str> = StartupMode::ALL.iter().map(|mode| mode.label()).collect();
+        let startup_mode = DropDown::from_strings(&labels);
+        startup_mode.set_selected(position(&StartupMode::ALL, &current.general.startup_mode));
+        startup_mode.connect_selected_notify({
+            let app = app.clone();
+            move |dropdown| {
+                // The action keeps the radio items in the main menu in sync
+                if let Some(mode) = StartupMode::ALL.get(dropdown.selected() as usize) {
+                    app.activate_action("startup-mode", Some(&mode.key().to_variant()));
+                }
+            }
+        });
+        vbox.append(&row("On startup", &startup_mode));
+
+        let home_page = Entry::builder().text(&current.general.home_page).hexpand(true).build();
+        home_page.connect_changed(|entry| {
+            let text = entry.text().trim().to_string();
+            update_settings(|settings| settings.general.home_page = text);
+        });
+        vbox.append(&row("Home page", &home_page));
+
+        let default_scheme = DropDown::from_strings(&DEFAULT_SCHEMES);
+        default_scheme.set_selected(position(&DEFAULT_SCHEMES, &current.general.default_scheme.as_str()));
+        default_scheme.connect_selected_notify(|dropdown| {
+            if let Some(scheme) = DEFAULT_SCHEMES.get(dropdown.selected() as usize) {
+                update_settings(|settings| settings.general.default_scheme = scheme.to_string());
+            }
+        });
+        vbox.append(&row("Scheme for addresses without one", &default_scheme));
+
+        vbox.append(&Self::download_dir_row(current));
+
+        vbox
+    }
+
+    /// Row with the download directory, which can be typed or chosen with a file chooser
+    fn download_dir_row(current: &Settings) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let entry = Entry::builder()
+            .text(&current.general.download_dir)
+            .placeholder_text(download_dir().to_string_lossy().as_ref())
+            .hexpand(true)
+            .build();
+        entry.connect_changed(|entry| {
+            let text = entry.text().trim().to_string();
+            update_settings(|settings| settings.general.download_dir = text);
+        });
+        hbox.append(&entry);
+
+        let choose_button = Button::with_label("Choose…");
+        let entry_clone = entry.clone();
+        choose_button.connect_clicked(move |button| {
+            let dialog = FileDialog::builder().title("Download directory").modal(true).build();
+            dialog.set_initial_folder(Some(&gio::File::for_path(download_dir())));
+
+            let window = button.root().and_downcast::<Window>();
+            let entry = entry_clone.clone();
+            dialog.select_folder(window.as_ref(), gio::Cancellable::NONE, move |result| {
+                if let Some(path) = result.ok().and_then(|file| file.path()) {
+                    entry.set_text(&path.to_string_lossy());
+                }
+            });
+        });
+        hbox.append(&choose_button);
+
+        row("Download directory", &hbox)
+    }
+
+    fn network_page(current: &Settings) -> gtk4::Box {
+        let vbox = page_box();
+
+        let user_agent = Entry::builder().text(&current.network.user_agent).hexpand(true).build();
+        user_agent.connect_changed(|entry| {
+            // Only values that can be sent as a header are stored
+            let text = entry.text().trim().to_string();
+            if HeaderValue::from_str(&text).is_ok() {
+                entry.remove_css_class("error");
+                update_settings(|settings| settings.network.user_agent = text);
+            } else {
+                entry.add_css_class("error");
+            }
+        });
+        vbox.append(&row("User agent", &user_agent));
+
+        let accept_language = Entry::builder().text(&current.network.accept_language).hexpand(true).build();
+        accept_language.connect_changed(|entry| {
+            let text = entry.text().trim().to_string();
+            update_settings(|settings| settings.network.accept_language = text);
+        });
+        vbox.append(&row("Accept-Language", &accept_language));
+
+        let connect_timeout = timeout_spin(current.network.connect_timeout);
+        connect_timeout.connect_value_changed(|spin| {
+            let seconds = spin.value_as_int() as u64;
+            update_settings(|settings| settings.network.connect_timeout = seconds);
+        });
+        vbox.append(&row("Connect timeout (seconds)", &connect_timeout));
+
+        let read_timeout = timeout_spin(current.network.read_timeout);
+        read_timeout.connect_value_changed(|spin| {
+            let seconds = spin.value_as_int() as u64;
+            update_settings(|settings| settings.network.read_timeout = seconds);
+        });
+        vbox.append(&row("Read timeout (seconds)", &read_timeout));
+
+        vbox.append(&note("Changes apply to new requests."));
+
+        vbox
+    }
+
+    fn privacy_page(current: &Settings) -> gtk4::Box {
+        let vbox = page_box();
+
+        let names: Vec<String> = CookieMode::ALL.iter().map(|mode| mode.to_string()).collect();
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let cookie_mode = DropDown::from_strings(&names);
+        cookie_mode.set_selected(position(&CookieMode::ALL, &current.privacy.cookie_mode));
+        cookie_mode.connect_selected_notify(|dropdown| {
+            if let Some(mode) = CookieMode::ALL.get(dropdown.selected() as usize) {
+                cookie_policy().set_mode(*mode);
+                update_settings(|settings| settings.privacy.cookie_mode = *mode);
+            }
+        });
+        vbox.append(&row("Cookies", &cookie_mode));
+
+        let do_not_track = switch(current.privacy.do_not_track);
+        do_not_track.connect_active_notify(|switch| {
+            let active = switch.is_active();
+            update_settings(|settings| settings.privacy.do_not_track = active);
+        });
+        vbox.append(&row("Send Do Not Track", &do_not_track));
+
+        let remember_history = switch(current.privacy.remember_history);
+        remember_history.connect_active_notify(|switch| {
+            let active = switch.is_active();
+            update_settings(|settings| settings.privacy.remember_history = active);
+        });
+        vbox.append(&row("Remember history", &remember_history));
+
+        vbox
+    }
+
+    fn appearance_page(app: &Application, current: &Settings) -> gtk4::Box {
+        let vbox = page_box();
+
+        let dark_mode = switch(current.appearance.dark_mode);
+        dark_mode.connect_active_notify({
+            let app = app.clone();
+            move |switch| app.set_dark_mode(switch.is_active())
+        });
+        vbox.append(&row("Dark mode", &dark_mode));
+
+        vbox
+    }
+
+    fn developer_page(app: &Application, current: &Settings) -> gtk4::Box {
+        let vbox = page_box();
+
+        let log_level = log_level_dropdown(current.developer.log_level);
+        log_level.connect_selected_notify(|dropdown| {
+            if let Some(level) = LogLevel::ALL.get(dropdown.selected() as usize) {
+                update_settings(|settings| settings.developer.log_level = *level);
+            }
+        });
+        vbox.append(&row("Log level", &log_level));
+
+        for (target, level) in &current.developer.log_targets {
+            let dropdown = log_level_dropdown(*level);
+            let target_clone = target.clone();
+            dropdown.connect_selected_notify(move |dropdown| {
+                if let Some(level) = LogLevel::ALL.get(dropdown.selected() as usize) {
+                    let target = target_clone.clone();
+                    update_settings(|settings| {
+                        settings.developer.log_targets.insert(target, *level);
+                    });
+                }
+            });
+            vbox.append(&row(&format!("Log level of \"{}\"", target), &dropdown));
+        }
+
+        vbox.append(&note("Log levels apply after a restart."));
+
+        let show_log = switch(current.developer.show_log);
+        show_log.connect_active_notify({
+            let app = app.clone();
+            move |switch| {
+                let active = switch.is_active();
+                update_settings(|settings| settings.developer.show_log = active);
+                for window in app.windows() {
+                    if let Ok(window) = window.downcast::<BrowserWindow>() {
+                        window.imp().log_scroller.set_visible(active);
+                    }
+                }
+            }
+        });
+        vbox.append(&row("Show log", &show_log));
+
+        vbox
+    }
+}
+
+/// Wraps the contents of a page in a scrolled window
+fn page(content: &gtk4::Box) -> ScrolledWindow {
+    ScrolledWindow::builder()
+        .hscrollbar_policy(PolicyType::Never)
+        .vscrollbar_policy(PolicyType::Automatic)
+        .child(content)
+        .build()
+}
+
+fn page_box() -> gtk4::Box {
+    let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+    vbox
+}
+
+/// Row with a label and the widget that changes the setting
+fn row(title: &str, widget: &impl IsA<gtk4::Widget>) -> gtk4::Box {
+    let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+    let label = Label::new(Some(title));
+    label.set_halign(Align::Start);
+    label.set_width_chars(16);
+    label.set_xalign(0.0);
+    hbox.append(&label);
+
+    let widget = widget.as_ref();
+    if !widget.hexpands() {
+        let spacer = gtk4::Box::new(Orientation::Horizontal, 0);
+        spacer.set_hexpand(true);
+        hbox.append(&spacer);
+    }
+    hbox.append(widget);
+
+    hbox
+}
+
+fn note(text: &str) -> Label {
+    let label = Label::new(Some(text));
+    label.set_halign(Align::Start);
+    label.add_css_class("dim-label");
+    label
+}
+
+fn switch(active: bool) -> Switch {
+    let switch = Switch::new();
+    switch.set_active(active);
+    switch.set_valign(Align::Center);
+    switch
+}
+
+fn timeout_spin(seconds: u64) -> SpinButton {
+    let spin = SpinButton::with_range(1.0, 300.0, 1.0);
+    spin.set_value(seconds as f64);
+    spin
+}
+
+fn log_level_dropdown(level: LogLevel) -> DropDown {
+    let labels: Vec<&str> = LogLevel::ALL.iter().map(|level| level.label()).collect();
+    let dropdown = DropDown::from_strings(&labels);
+    dropdown.set_selected(position(&LogLevel::ALL, &level));
+    dropdown
+}
+
+/// Returns the position of the value in the list of options, or the first option when it is missing
+fn position<T: PartialEq>(options: &[T], value: &T) -> u32 {
+    options.iter().position(|option| option == value).unwrap_or(0) as u32
+}
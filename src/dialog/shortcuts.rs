@@ -24,6 +24,8 @@ impl ShortcutsDialog {
 
         let group = Self::general_file_group();
         section.append(&group);
+        let group = Self::general_navigation_group();
+        section.append(&group);
         let group = Self::general_developer_group("Developer");
         section.append(&group);
 
@@ -35,16 +37,67 @@ impl ShortcutsDialog {
 
         let new_tab = ShortcutsShortcut::builder().title("New Tab").accelerator("<Ctrl>T").build();
 
+        let new_private_tab = ShortcutsShortcut::builder()
+            .title("New Private Tab")
+            .accelerator("<Ctrl><Shift>P")
+            .build();
+
+        let new_window = ShortcutsShortcut::builder().title("New Window").accelerator("<Ctrl>N").build();
+
+        let reopen_closed_tab = ShortcutsShortcut::builder()
+            .title("Reopen Closed Tab")
+            .accelerator("<Ctrl><Shift>T")
+            .build();
+
         let open_shortcut = ShortcutsShortcut::builder().title("Open File").accelerator("<Ctrl>O").build();
 
         let toggle_darkmode = ShortcutsShortcut::builder()
             .title("Toggle dark mode")
-            .accelerator("<Ctrl>D")
+            .accelerator("<Ctrl><Shift>D")
             .build();
+        let settings = ShortcutsShortcut::builder().title("Settings").accelerator("<Ctrl>comma").build();
 
         group.append(&new_tab);
+        group.append(&new_private_tab);
+        group.append(&new_window);
+        group.append(&reopen_closed_tab);
         group.append(&open_shortcut);
         group.append(&toggle_darkmode);
+        group.append(&settings);
+
+        group
+    }
+
+    fn general_navigation_group() -> ShortcutsGroup {
+        let group = ShortcutsGroup::builder().title("Navigation").build();
+
+        let go_back = ShortcutsShortcut::builder().title("Go back").accelerator("<Alt>Left").build();
+        let go_forward = ShortcutsShortcut::builder().title("Go forward").accelerator("<Alt>Right").build();
+        let bookmark_page = ShortcutsShortcut::builder()
+            .title("Bookmark this page")
+            .accelerator("<Ctrl>D")
+            .build();
+
+        let reload = ShortcutsShortcut::builder().title("Reload").accelerator("<Ctrl>R F5").build();
+        let find = ShortcutsShortcut::builder().title("Find in page").accelerator("<Ctrl>F").build();
+        let find_next = ShortcutsShortcut::builder()
+            .title("Find next / previous")
+            .accelerator("F3 <Shift>F3")
+            .build();
+        let show_history = ShortcutsShortcut::builder().title("Show history").accelerator("<Ctrl>H").build();
+        let show_downloads = ShortcutsShortcut::builder()
+            .title("Show downloads")
+            .accelerator("<Ctrl><Shift>Y")
+            .build();
+
+        group.append(&go_back);
+        group.append(&go_forward);
+        group.append(&reload);
+        group.append(&find);
+        group.append(&find_next);
+        group.append(&bookmark_page);
+        group.append(&show_history);
+        group.append(&show_downloads);
 
         group
     }
@@ -0,0 +1,144 @@
+use crate::application::Application;
+use crate::cookies::cookie_policy;
+use crate::cookies::permission_store::SitePermission;
+use crate::cookies::policy::{site_for_url, CookieMode};
+use crate::settings::update_settings;
+use gtk4::prelude::*;
+use gtk4::{Align, Button, DropDown, Entry, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SelectionMode, Window};
+use url::Url;
+
+pub struct SitePermissionsDialog;
+
+impl SitePermissionsDialog {
+    pub fn create_dialog(app: &Application) -> Window {
+        let window = Window::builder()
+            .application(app)
+            .title("Site Permissions")
+            .default_width(480)
+            .default_height(400)
+            .modal(true)
+            .build();
+
+        let vbox = gtk4::Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        vbox.append(&Self::mode_row());
+
+        let list = ListBox::builder().selection_mode(SelectionMode::None).build();
+        Self::populate(&list);
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        let heading = Label::new(Some("Exceptions"));
+        heading.set_halign(Align::Start);
+        vbox.append(&heading);
+        vbox.append(&scroller);
+        vbox.append(&Self::add_row(&list));
+
+        window.set_child(Some(&vbox));
+        window
+    }
+
+    /// Row with the global cookie mode
+    fn mode_row() -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+        let label = Label::new(Some("Cookies"));
+        label.set_hexpand(true);
+        label.set_halign(Align::Start);
+        hbox.append(&label);
+
+        let names: Vec<String> = CookieMode::ALL.iter().map(|mode| mode.to_string()).collect();
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let dropdown = DropDown::from_strings(&names);
+
+        let current = cookie_policy().mode();
+        let selected = CookieMode::ALL.iter().position(|mode| *mode == current).unwrap_or(0);
+        dropdown.set_selected(selected as u32);
+
+        dropdown.connect_selected_notify(|dropdown| {
+            if let Some(mode) = CookieMode::ALL.get(dropdown.selected() as usize) {
+                cookie_policy().set_mode(*mode);
+                update_settings(|settings| settings.privacy.cookie_mode = *mode);
+            }
+        });
+        hbox.append(&dropdown);
+
+        hbox
+    }
+
+    /// Row that allows to add a new exception
+    fn add_row(list: &ListBox) -> gtk4::Box {
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 5);
+
+        let entry = Entry::builder().placeholder_text("example.com").hexpand(true).build();
+        hbox.append(&entry);
+
+        for (title, permission) in [("Allow", SitePermission::Allow), ("Block", SitePermission::Block)] {
+            let button = Button::with_label(title);
+            let entry_clone = entry.clone();
+            let list_clone = list.clone();
+            button.connect_clicked(move |_| {
+                let Some(site) = site_from_input(entry_clone.text().as_str()) else {
+                    return;
+                };
+                cookie_policy().permissions().set(&site, permission);
+                entry_clone.set_text("");
+                Self::populate(&list_clone);
+            });
+            hbox.append(&button);
+        }
+
+        hbox
+    }
+
+    /// (Re)fills the list with all the stored exceptions
+    fn populate(list: &ListBox) {
+        list.remove_all();
+
+        for (site, permission) in cookie_policy().permissions().all() {
+            let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+            let site_label = Label::new(Some(site.as_str()));
+            site_label.set_hexpand(true);
+            site_label.set_halign(Align::Start);
+            hbox.append(&site_label);
+
+            let permission_label = match permission {
+                SitePermission::Allow => Label::new(Some("Allow")),
+                SitePermission::Block => Label::new(Some("Block")),
+            };
+            hbox.append(&permission_label);
+
+            let remove_button = Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_has_frame(false);
+            let list_clone = list.clone();
+            remove_button.connect_clicked(move |_| {
+                cookie_policy().permissions().remove(&site);
+                Self::populate(&list_clone);
+            });
+            hbox.append(&remove_button);
+
+            list.append(&hbox);
+        }
+    }
+}
+
+/// Converts user input (a host or a URL) into a site
+fn site_from_input(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    let url = Url::parse(input).or_else(|_| Url::parse(&format!("https://{}", input))).ok()?;
+    site_for_url(&url)
+}
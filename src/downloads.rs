@@ -0,0 +1,477 @@
+use crate::downloads::sqlite_store::DownloadStore;
+use crate::fetcher::download::{save_to_file, DownloadResponse};
+use crate::fetcher::network_profile::{network_profiles, NetworkKind, NetworkProfile};
+use crate::fetcher::{fetch_download, FetcherError};
+use crate::profile::profile;
+use crate::runtime;
+use crate::settings::settings;
+use chrono::{DateTime, Utc};
+use gtk4::glib;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::task::JoinHandle;
+use url::Url;
+
+pub mod sqlite_store;
+
+/// Name of the database that holds the download records
+const DOWNLOADS_DB_FILE: &str = "downloads.db";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DownloadId(i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadState {
+    InProgress,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl fmt::Display for DownloadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadState::InProgress => write!(f, "in-progress"),
+            DownloadState::Paused => write!(f, "paused"),
+            DownloadState::Completed => write!(f, "completed"),
+            DownloadState::Failed => write!(f, "failed"),
+            DownloadState::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl FromStr for DownloadState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in-progress" => Ok(DownloadState::InProgress),
+            "paused" => Ok(DownloadState::Paused),
+            "completed" => Ok(DownloadState::Completed),
+            "failed" => Ok(DownloadState::Failed),
+            "cancelled" => Ok(DownloadState::Cancelled),
+            _ => Err(anyhow::anyhow!("unknown download state: {}", s)),
+        }
+    }
+}
+
+/// A file that is (or has been) downloaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Download {
+    pub id: DownloadId,
+    pub url: Url,
+    /// Path of the file on disk
+    pub path: PathBuf,
+    pub mime: Option<String>,
+    /// Number of bytes that have been saved
+    pub received: u64,
+    /// Size of the file, when the server sent it
+    pub total: Option<u64>,
+    pub state: DownloadState,
+    /// Why the download failed
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// Downloads from private tabs are not stored, and are forgotten when the browser is closed
+    pub private: bool,
+}
+
+impl Download {
+    /// Returns the name of the downloaded file
+    pub fn filename(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Returns the fraction of the file that has been downloaded, when the size is known
+    pub fn progress(&self) -> Option<f64> {
+        match self.total {
+            Some(total) if total > 0 => Some((self.received as f64 / total as f64).min(1.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns true when the download has stopped and can be started again
+    pub fn can_retry(&self) -> bool {
+        matches!(self.state, DownloadState::Failed | DownloadState::Cancelled)
+    }
+}
+
+/// Returns the directory downloads are saved in
+pub fn download_dir() -> PathBuf {
+    let dir = &settings().general.download_dir;
+    if !dir.is_empty() {
+        return PathBuf::from(dir);
+    }
+
+    glib::user_special_dir(glib::UserDirectory::Downloads).unwrap_or_else(|| glib::home_dir().join("Downloads"))
+}
+
+/// Returns a path in the directory for the file name that does not exist yet. When the name is taken,
+/// a number is added before the extension: `file.tar.gz` becomes `file (1).tar.gz`.
+pub fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let path = dir.join(filename);
+    if !path.exists() {
+        return path;
+    }
+
+    // Everything after the first dot is the extension, unless the name starts with it
+    let (stem, extension) = match filename.char_indices().skip(1).find(|(_, c)| *c == '.') {
+        Some((dot, _)) => filename.split_at(dot),
+        None => (filename, ""),
+    };
+
+    (1..)
+        .map(|i| dir.join(format!("{} ({}){}", stem, i, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Formats a number of bytes for humans, like `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Keeps track of all downloads, and runs the ones that are in progress on the tokio runtime.
+/// Progress is only kept in memory while a download runs; the store is updated when its state
+/// changes.
+pub struct DownloadManager {
+    store: DownloadStore,
+    /// All downloads, the most recent first
+    downloads: Mutex<Vec<Download>>,
+    /// Tasks of the downloads that are in progress. Aborting a task stops the download.
+    tasks: Mutex<HashMap<DownloadId, JoinHandle<()>>>,
+    /// Network profile each download was started in, so it is resumed with the same cookies
+    networks: Mutex<HashMap<DownloadId, NetworkKind>>,
+    next_id: Mutex<i64>,
+}
+
+impl DownloadManager {
+    /// Creates a manager with the downloads of the store. Downloads that were in progress when the
+    /// browser was closed are paused, so they can be resumed.
+    pub fn new(store: DownloadStore) -> Self {
+        let mut downloads = store.all();
+        for download in downloads.iter_mut().filter(|download| download.state == DownloadState::InProgress) {
+            download.state = DownloadState::Paused;
+            download.received = file_size(&download.path);
+            store.save(download);
+        }
+        let next_id = downloads.iter().map(|download| download.id.0).max().unwrap_or(0) + 1;
+
+        Self {
+            store,
+            downloads: Mutex::new(downloads),
+            tasks: Mutex::new(HashMap::new()),
+            networks: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(next_id),
+        }
+    }
+
+    /// Returns all downloads, the most recent first
+    pub fn downloads(&self) -> Vec<Download> {
+        self.downloads.lock().unwrap().clone()
+    }
+
+    /// Starts saving a response to the download directory, and returns the new download
+    pub fn start(self: &Arc<Self>, response: DownloadResponse, network: NetworkKind) -> Download {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            DownloadId(*next_id - 1)
+        };
+
+        let dir = download_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            warn!("failed to create download directory {}: {:?}", dir.display(), e);
+        }
+
+        let download = Download {
+            id,
+            url: response.url.clone(),
+            path: unique_path(&dir, &response.filename),
+            mime: response.mime.clone(),
+            received: 0,
+            total: response.total,
+            state: DownloadState::InProgress,
+            error: None,
+            started_at: Utc::now(),
+            private: network == NetworkKind::Private,
+        };
+        info!(target: "gtk", "Downloading {} to {}", download.url, download.path.display());
+
+        // The file is created right away, so downloads that start at the same time get different names
+        if let Err(e) = std::fs::File::create(&download.path) {
+            warn!("failed to create {}: {:?}", download.path.display(), e);
+        }
+
+        self.downloads.lock().unwrap().insert(0, download.clone());
+        self.networks.lock().unwrap().insert(id, network);
+        self.save(&download);
+
+        let path = download.path.clone();
+        self.spawn(id, async move { Ok(response) }, path);
+
+        download
+    }
+
+    /// Stops a download that is in progress. The part that has been saved is kept.
+    pub fn pause(&self, id: DownloadId) {
+        self.stop(id, DownloadState::Paused);
+    }
+
+    /// Continues a paused download from where it stopped
+    pub fn resume(self: &Arc<Self>, id: DownloadId) {
+        if !self.has_state(id, |download| download.state == DownloadState::Paused) {
+            return;
+        }
+        let Some(download) = self.set_state(id, DownloadState::InProgress, None) else {
+            return;
+        };
+
+        let network = self.network(id);
+        let offset = file_size(&download.path);
+        let url = download.url.clone();
+        self.spawn(id, async move { fetch_download(url, &network, offset).await }, download.path);
+    }
+
+    /// Downloads a failed or cancelled download again from the start
+    pub fn retry(self: &Arc<Self>, id: DownloadId) {
+        if !self.has_state(id, Download::can_retry) {
+            return;
+        }
+        let Some(download) = self.set_state(id, DownloadState::InProgress, None) else {
+            return;
+        };
+
+        let network = self.network(id);
+        let url = download.url.clone();
+        self.spawn(id, async move { fetch_download(url, &network, 0).await }, download.path);
+    }
+
+    /// Stops a download and removes the part that has been saved
+    pub fn cancel(&self, id: DownloadId) {
+        let Some(download) = self.stop(id, DownloadState::Cancelled) else {
+            return;
+        };
+
+        if let Err(e) = std::fs::remove_file(&download.path) {
+            warn!("failed to remove cancelled download {}: {:?}", download.path.display(), e);
+        }
+    }
+
+    /// Removes a download from the list. The downloaded file is kept.
+    pub fn remove(&self, id: DownloadId) {
+        if let Some(task) = self.tasks.lock().unwrap().remove(&id) {
+            task.abort();
+        }
+        self.downloads.lock().unwrap().retain(|download| download.id != id);
+        self.networks.lock().unwrap().remove(&id);
+        self.store.remove(id);
+    }
+
+    /// Runs a download on the tokio runtime. The response is fetched first, after which its body is
+    /// saved to the path of the download.
+    fn spawn<F>(self: &Arc<Self>, id: DownloadId, response: F, path: PathBuf)
+    where
+        F: std::future::Future<Output = Result<DownloadResponse, FetcherError>> + Send + 'static,
+    {
+        // The task is added while the tasks are locked, so a task that finishes right away cannot try to
+        // remove itself before it has been added
+        let mut tasks = self.tasks.lock().unwrap();
+        let manager = self.clone();
+        let task = runtime().spawn(async move {
+            let result = async {
+                let response = response.await?;
+                // A server that does not support ranges sends the whole file again
+                let offset = response.offset;
+                if offset == 0 {
+                    manager.update(id, |download| download.received = 0);
+                }
+                if response.total.is_some() {
+                    manager.update(id, |download| download.total = response.total);
+                }
+
+                save_to_file(response.body, &path, offset > 0, |written| {
+                    manager.update(id, |download| download.received = offset + written);
+                })
+                .await
+            }
+            .await;
+
+            manager.tasks.lock().unwrap().remove(&id);
+            match result {
+                Ok(_) => {
+                    manager.set_state(id, DownloadState::Completed, None);
+                }
+                Err(e) => {
+                    warn!("download failed: {}", e);
+                    manager.set_state(id, DownloadState::Failed, Some(e.to_string()));
+                }
+            }
+        });
+
+        tasks.insert(id, task);
+    }
+
+    /// Aborts the task of a download, and sets the new state. Returns the download when it was in
+    /// progress or paused.
+    fn stop(&self, id: DownloadId, state: DownloadState) -> Option<Download> {
+        if let Some(task) = self.tasks.lock().unwrap().remove(&id) {
+            task.abort();
+        }
+
+        if !self.has_state(id, |download| {
+            matches!(download.state, DownloadState::InProgress | DownloadState::Paused)
+        }) {
+            return None;
+        }
+
+        self.set_state(id, state, None)
+    }
+
+    /// Returns true when the download exists and matches the predicate
+    fn has_state(&self, id: DownloadId, predicate: impl Fn(&Download) -> bool) -> bool {
+        self.downloads
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|download| download.id == id && predicate(download))
+    }
+
+    /// Changes the state of a download and stores it. Returns the updated download.
+    fn set_state(&self, id: DownloadId, state: DownloadState, error: Option<String>) -> Option<Download> {
+        let download = self.update(id, |download| {
+            download.state = state;
+            download.error = error;
+            if state == DownloadState::Paused {
+                download.received = file_size(&download.path);
+            }
+        })?;
+
+        self.save(&download);
+        Some(download)
+    }
+
+    /// Updates a download in memory, and returns the updated download
+    fn update(&self, id: DownloadId, f: impl FnOnce(&mut Download)) -> Option<Download> {
+        let mut downloads = self.downloads.lock().unwrap();
+        let download = downloads.iter_mut().find(|download| download.id == id)?;
+        f(download);
+        Some(download.clone())
+    }
+
+    fn save(&self, download: &Download) {
+        if !download.private {
+            self.store.save(download);
+        }
+    }
+
+    /// Returns the network profile of a download. Downloads from an earlier run use the default profile.
+    fn network(&self, id: DownloadId) -> Arc<NetworkProfile> {
+        let kind = self.networks.lock().unwrap().get(&id).copied().unwrap_or(NetworkKind::Default);
+        network_profiles().lock().unwrap().get(kind)
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+}
+
+/// Path of the database that holds the download records of the current profile
+pub fn downloads_db_path() -> PathBuf {
+    profile().data_path(DOWNLOADS_DB_FILE)
+}
+
+/// Returns the download manager of the browser
+pub fn download_manager() -> Arc<DownloadManager> {
+    static MANAGER: OnceLock<Arc<DownloadManager>> = OnceLock::new();
+    MANAGER
+        .get_or_init(|| {
+            let store = DownloadStore::new(downloads_db_path()).unwrap_or_else(|e| {
+                warn!("failed to open download store, using in-memory store: {:?}", e);
+                DownloadStore::new(":memory:").expect("in-memory download store")
+            });
+            Arc::new(DownloadManager::new(store))
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_path() {
+        let dir = std::env::temp_dir().join(format!("gosub-downloads-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(unique_path(&dir, "file.tar.gz"), dir.join("file.tar.gz"));
+        std::fs::write(dir.join("file.tar.gz"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "file.tar.gz"), dir.join("file (1).tar.gz"));
+        std::fs::write(dir.join("file (1).tar.gz"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "file.tar.gz"), dir.join("file (2).tar.gz"));
+
+        std::fs::write(dir.join("README"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "README"), dir.join("README (1)"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 bytes");
+        assert_eq!(format_size(1023), "1023 bytes");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024 * 1024 * 1024), "3072.0 TB");
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut download = Download {
+            id: DownloadId(1),
+            url: Url::parse("https://example.com/a.zip").unwrap(),
+            path: PathBuf::from("/tmp/a.zip"),
+            mime: None,
+            received: 250,
+            total: Some(1000),
+            state: DownloadState::InProgress,
+            error: None,
+            started_at: Utc::now(),
+            private: false,
+        };
+        assert_eq!(download.progress(), Some(0.25));
+        assert_eq!(download.filename(), "a.zip");
+
+        download.total = None;
+        assert_eq!(download.progress(), None);
+
+        for state in [
+            DownloadState::InProgress,
+            DownloadState::Paused,
+            DownloadState::Completed,
+            DownloadState::Failed,
+            DownloadState::Cancelled,
+        ] {
+            assert_eq!(state.to_string().parse::<DownloadState>().unwrap(), state);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::downloads::{Download, DownloadId, DownloadState};
+use chrono::DateTime;
+use log::warn;
+use rusqlite::{params, Connection, Row};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+/// Stores the download records in a SQLite database, so the downloads window shows earlier downloads
+/// after a restart. The downloaded files themselves are not touched by the store.
+pub struct DownloadStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl DownloadStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS downloads (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                path TEXT NOT NULL,
+                mime TEXT,
+                received INTEGER NOT NULL,
+                total INTEGER,
+                state TEXT NOT NULL,
+                error TEXT,
+                started_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Adds the download, or updates it when it is already stored
+    pub fn save(&self, download: &Download) {
+        let e = self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO downloads (id, url, path, mime, received, total, state, error, started_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                download.id.0,
+                download.url.to_string(),
+                download.path.to_string_lossy(),
+                download.mime,
+                download.received as i64,
+                download.total.map(|total| total as i64),
+                download.state.to_string(),
+                download.error,
+                download.started_at.timestamp(),
+            ],
+        );
+        if let Err(e) = e {
+            warn!("failed to store download: {:?}", e);
+        }
+    }
+
+    pub fn remove(&self, id: DownloadId) {
+        let e = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM downloads WHERE id = ?1", params![id.0]);
+        if let Err(e) = e {
+            warn!("failed to remove download: {:?}", e);
+        }
+    }
+
+    /// Returns all downloads, the most recent first
+    pub fn all(&self) -> Vec<Download> {
+        let locked_conn = self.conn.lock().unwrap();
+        let stmt = locked_conn.prepare(
+            "SELECT id, url, path, mime, received, total, state, error, started_at
+             FROM downloads ORDER BY started_at DESC, id DESC",
+        );
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let Ok(rows) = stmt.query_map([], row_to_download) else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok()).flatten().collect()
+    }
+}
+
+fn row_to_download(row: &Row) -> Result<Option<Download>, rusqlite::Error> {
+    let url: String = row.get(1)?;
+    let path: String = row.get(2)?;
+    let received: i64 = row.get(4)?;
+    let total: Option<i64> = row.get(5)?;
+    let state: String = row.get(6)?;
+
+    let Ok(url) = Url::parse(&url) else {
+        return Ok(None);
+    };
+    Ok(Some(Download {
+        id: DownloadId(row.get(0)?),
+        url,
+        path: PathBuf::from(path),
+        mime: row.get(3)?,
+        received: received as u64,
+        total: total.map(|total| total as u64),
+        state: state.parse().unwrap_or(DownloadState::Failed),
+        error: row.get(7)?,
+        started_at: DateTime::from_timestamp(row.get(8)?, 0).unwrap_or_default(),
+        private: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn download(id: i64, url: &str, started_at: DateTime<Utc>) -> Download {
+        Download {
+            id: DownloadId(id),
+            url: Url::parse(url).unwrap(),
+            path: PathBuf::from(format!("/tmp/download-{}", id)),
+            mime: Some("application/zip".to_string()),
+            received: 0,
+            total: Some(1000),
+            state: DownloadState::InProgress,
+            error: None,
+            started_at,
+            private: false,
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let store = DownloadStore::new(":memory:").unwrap();
+        let now = Utc::now();
+
+        let mut first = download(1, "https://example.com/a.zip", now - Duration::hours(1));
+        store.save(&first);
+        store.save(&download(2, "https://example.com/b.zip", now));
+
+        let ids: Vec<DownloadId> = store.all().iter().map(|download| download.id).collect();
+        assert_eq!(ids, vec![DownloadId(2), DownloadId(1)]);
+
+        // Saving a stored download updates it
+        first.received = 1000;
+        first.state = DownloadState::Completed;
+        store.save(&first);
+        let all = store.all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].received, 1000);
+        assert_eq!(all[1].state, DownloadState::Completed);
+        assert_eq!(all[1].path, PathBuf::from("/tmp/download-1"));
+        assert_eq!(all[1].started_at.timestamp(), (now - Duration::hours(1)).timestamp());
+
+        store.remove(DownloadId(2));
+        assert_eq!(store.all().len(), 1);
+    }
+}
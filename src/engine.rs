@@ -1,4 +1,9 @@
+use crate::eventloop::WindowEventLoopDummy;
+use crate::find::TextRun;
+use crate::links::LinkArea;
 use gosub_engine::prelude::*;
+use gtk4::cairo;
+use std::sync::mpsc;
 
 /**
  * Defines the gosub engine. It consists of a set of modules that each define a part of the engine.
@@ -40,3 +45,94 @@ impl HasRenderBackend for GosubEngineConfig {
 }
 
 impl ModuleConfiguration for GosubEngineConfig {}
+
+/// Draws the render tree of the drawer on the cairo context, for a viewport of the given size
+pub fn draw_tree(drawer: &mut <GosubEngineConfig as HasTreeDrawer>::TreeDrawer, cr: &cairo::Context, width: u32, height: u32) {
+    let mut render_backend = <GosubEngineConfig as HasRenderBackend>::RenderBackend::new();
+    let size = SizeU32::new(width, height);
+
+    // Drawer.draw will populate the scene with elements from the tree
+    let mut win_data = WindowData {
+        scene: Scene::new(),
+        cr: Some(cr.clone()),
+    };
+    drawer.draw(&mut render_backend, &mut win_data, size, &WindowEventLoopDummy);
+
+    let mut active_win_data = ActiveWindowData { cr: cr.clone() };
+    _ = render_backend.render(&mut win_data, &mut active_win_data);
+}
+
+/// Returns the text of the render tree of the drawer, together with the boxes it is drawn in. The tree
+/// is only laid out once it has been drawn, so pages that were never shown have no text yet.
+pub fn text_runs(drawer: &mut <GosubEngineConfig as HasTreeDrawer>::TreeDrawer) -> Vec<TextRun> {
+    let (sender, receiver) = mpsc::channel();
+    drawer.send_nodes(sender);
+
+    let mut runs = Vec::new();
+    while let Ok(root) = receiver.try_recv() {
+        collect_text_runs(&root, 0.0, 0.0, &mut runs);
+    }
+    runs
+}
+
+fn collect_text_runs(node: &NodeDesc, parent_x: f64, parent_y: f64, runs: &mut Vec<TextRun>) {
+    // Positions of the nodes are relative to their parent
+    let x = parent_x + node.pos.0 as f64;
+    let y = parent_y + node.pos.1 as f64;
+
+    if let Some(text) = node.text.as_ref().filter(|text| !text.trim().is_empty()) {
+        runs.push(TextRun {
+            text: text.clone(),
+            x,
+            y,
+            width: node.size.0 as f64,
+            height: node.size.1 as f64,
+        });
+    }
+
+    for child in &node.children {
+        collect_text_runs(child, x, y, runs);
+    }
+}
+
+/// Returns the boxes of the links on the page of the drawer. A link gets the boxes of all its content,
+/// as its own box does not cover text that is broken over several lines.
+pub fn link_areas(drawer: &mut <GosubEngineConfig as HasTreeDrawer>::TreeDrawer) -> Vec<LinkArea> {
+    let (sender, receiver) = mpsc::channel();
+    drawer.send_nodes(sender);
+
+    let mut areas = Vec::new();
+    while let Ok(root) = receiver.try_recv() {
+        collect_link_areas(&root, 0.0, 0.0, None, &mut areas);
+    }
+    areas
+}
+
+fn collect_link_areas(node: &NodeDesc, parent_x: f64, parent_y: f64, href: Option<&str>, areas: &mut Vec<LinkArea>) {
+    let x = parent_x + node.pos.0 as f64;
+    let y = parent_y + node.pos.1 as f64;
+
+    let href = match node.name.as_str() {
+        "a" => node
+            .attributes
+            .iter()
+            .find(|(name, _)| name == "href")
+            .map(|(_, value)| value.as_str())
+            .or(href),
+        _ => href,
+    };
+
+    if let Some(href) = href.filter(|_| node.size.0 > 0.0 && node.size.1 > 0.0) {
+        areas.push(LinkArea {
+            href: href.to_string(),
+            x,
+            y,
+            width: node.size.0 as f64,
+            height: node.size.1 as f64,
+        });
+    }
+
+    for child in &node.children {
+        collect_link_areas(child, x, y, href, areas);
+    }
+}
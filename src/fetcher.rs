@@ -14,6 +14,12 @@ mod http;
 
 pub mod address_parser;
 mod async_stream;
+pub mod cache;
+pub mod download;
+pub mod idn;
+pub mod network_profile;
+pub mod scheme_handler;
+pub mod search_engine;
 
 #[derive(Error, Debug)]
 pub enum FetcherError {
@@ -29,23 +35,40 @@ pub enum FetcherError {
     #[error("gopher error: {0}")]
     Gopher(#[from] gopher::GopherError),
 
+    #[error("scheme handler error: {0}")]
+    SchemeHandler(String),
+
     #[error("unsupported scheme: {0}")]
     UnsupportedScheme(String),
     #[error("invalid URL: {0}")]
     InvalidUrl(String),
+
+    #[error("unexpected status code: {0}")]
+    Status(u16),
+    #[error("stream error: {0}")]
+    Stream(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[cfg(feature = "proto-http")]
-pub use crate::fetcher::http::{response::HttpResponse, CompleteHttpFetcher, HttpBody, HttpMethod};
+pub use crate::fetcher::http::{response::HttpResponse, CompleteHttpFetcher, HttpBody, HttpMethod, GOSUB_USERAGENT_STRING};
 
 #[cfg(feature = "proto-ftp")]
 pub use crate::fetcher::ftp::{fetcher::FtpFetcher, fetcher::FtpRequest, fetcher::FtpResponse};
 
+use crate::fetcher::address_parser::GosubRenderMode;
+use crate::fetcher::download::{is_download, DownloadResponse};
 #[cfg(feature = "proto-gopher")]
 pub use crate::fetcher::gopher::{fetcher::GopherFetcher, fetcher::GopherRequest, fetcher::GopherResponse};
 use crate::fetcher::http::request::HttpRequestBuilder;
+use crate::fetcher::network_profile::NetworkProfile;
+use crate::fetcher::scheme_handler::{scheme_handlers, SchemeResponse};
 
+#[allow(clippy::large_enum_variant)]
 enum Response {
+    /// Response generated in-process by a scheme handler
+    Generated(SchemeResponse),
     #[cfg(feature = "proto-http")]
     Http(HttpResponse),
     #[cfg(feature = "proto-ftp")]
@@ -83,10 +106,11 @@ impl Fetcher {
         ]
     }
 
-    pub fn new(base_url: Url) -> Self {
+    /// Creates a new fetcher that fetches resources within the given network profile
+    pub fn new(base_url: Url, network: &NetworkProfile) -> Self {
         Fetcher {
             #[cfg(feature = "proto-http")]
-            http_fetcher: CompleteHttpFetcher::new(base_url.clone()),
+            http_fetcher: CompleteHttpFetcher::new(base_url.clone(), network.jar()),
             #[cfg(feature = "proto-ftp")]
             ftp_fetcher: FtpFetcher::new(base_url.clone()),
             #[cfg(feature = "proto-gopher")]
@@ -94,13 +118,22 @@ impl Fetcher {
         }
     }
 
-    async fn fetch(&self, url: Url) -> Result<Response, FetcherError> {
+    /// Fetches the given URL. The `top_level_site` is the URL of the page in the tab that started
+    /// the request, and is used to decide if a request is a third-party request.
+    async fn fetch(&self, url: Url, top_level_site: Option<&Url>) -> Result<Response, FetcherError> {
         let scheme = url.scheme();
 
+        // Schemes with a registered handler are never fetched over the network
+        if let Some(handler) = scheme_handlers().get(scheme) {
+            return handler.handle(&url).map(Response::Generated);
+        }
+
         match scheme {
             #[cfg(feature = "proto-http")]
             "https" | "http" => {
-                let request = HttpRequestBuilder::new(HttpMethod::Get, url).build();
+                let request = HttpRequestBuilder::new(HttpMethod::Get, url)
+                    .top_level_site(top_level_site.cloned())
+                    .build();
                 match self.http_fetcher.fetch_with_request(request).await {
                     Ok(response) => Ok(Response::Http(response)),
                     Err(e) => Err(FetcherError::Http(e)),
@@ -136,16 +169,26 @@ impl Fetcher {
     }
 }
 
-pub async fn fetch_favicon(url: &str) -> Vec<u8> {
+pub async fn fetch_favicon(url: &str, network: &NetworkProfile) -> Vec<u8> {
     let Ok(url) = Url::parse(url) else {
         return Vec::new();
     };
 
-    let url = url.join("/favicon.ico").unwrap();
+    // Pages generated by scheme handlers (and other URLs that cannot be a base) have no favicon
+    let Ok(favicon_url) = url.join("/favicon.ico") else {
+        return Vec::new();
+    };
+    if scheme_handlers().is_registered(favicon_url.scheme()) {
+        return Vec::new();
+    }
+
+    if let Some(data) = network.cache().and_then(|cache| cache.get(&favicon_url)) {
+        return data;
+    }
 
     // This should be a method in Fetcher... it's a lot of boilerplate for fetching a simple favicon
-    let fetcher = Fetcher::new(url.clone());
-    match fetcher.fetch(url).await {
+    let fetcher = Fetcher::new(favicon_url.clone(), network);
+    let data = match fetcher.fetch(favicon_url.clone(), Some(&url)).await {
         // There was a correct response
         Ok(response) => match response {
             Response::Http(http_response) => {
@@ -168,44 +211,115 @@ pub async fn fetch_favicon(url: &str) -> Vec<u8> {
             error!("Failed to fetch favicon from URL: {:?}", e);
             Vec::new()
         }
+    };
+
+    if let Some(cache) = network.cache() {
+        if !data.is_empty() {
+            cache.put(&favicon_url, &data);
+        }
     }
+
+    data
 }
 
-pub async fn fetch_url_body(url: Url) -> Result<Vec<u8>, FetcherError> {
-    let fetcher = Fetcher::new(url.clone());
-    match fetcher.fetch(url).await {
-        // There was a correct response
-        Ok(response) => {
-            match response {
-                // It is an HTTP response
-                Response::Http(http_response) => {
-                    // It is a 200 OK response
-                    if http_response.head().status_code() == 200 {
-                        match http_response.body() {
-                            // We've got a body
-                            HttpBody::Reader(reader) => {
-                                match reader.vec().await {
-                                    // We've got the body as a Vec<u8>
-                                    Ok(data) => Ok(data),
-                                    Err(e) => {
-                                        error!("Failed to fetch body from URL: {:?}", e);
-                                        Err(FetcherError::Http(http::HttpError::UnknownError))
-                                    }
-                                }
-                            }
-                            HttpBody::Empty => Ok(Vec::new()),
-                        }
-                    } else {
-                        Err(FetcherError::Http(http::HttpError::UnknownError))
-                    }
-                }
-                #[allow(unreachable_patterns)]
-                _ => {
-                    error!("Unsupported response type. We expected a HTTP response");
-                    Err(FetcherError::Http(http::HttpError::UnknownError))
-                }
+/// The result of a top-level navigation
+pub enum PageResponse {
+    /// The body of a page that can be rendered
+    Page(Vec<u8>),
+    /// A response that must be saved to disk. The body has not been read yet.
+    Download(DownloadResponse),
+}
+
+/// Fetches the given URL as a top-level navigation that is shown in the given render mode. Responses
+/// that the browser cannot render are returned as downloads, without reading their body.
+pub async fn fetch_page(url: Url, network: &NetworkProfile, mode: &GosubRenderMode) -> Result<PageResponse, FetcherError> {
+    let fetcher = Fetcher::new(url.clone(), network);
+    match fetcher.fetch(url.clone(), Some(&url)).await? {
+        // The response was generated by a scheme handler
+        Response::Generated(generated) => Ok(PageResponse::Page(generated.body)),
+        // It is an HTTP response
+        Response::Http(http_response) => {
+            let status_code = http_response.head().status_code();
+            if status_code != 200 {
+                return Err(FetcherError::Status(status_code));
+            }
+
+            if is_download(http_response.head(), mode) {
+                let head = http_response.head().clone();
+                return Ok(PageResponse::Download(DownloadResponse::new(&head, http_response.body())));
+            }
+
+            match http_response.body() {
+                // We've got a body
+                HttpBody::Reader(reader) => reader.vec().await.map(PageResponse::Page).map_err(|e| {
+                    error!("Failed to fetch body from URL: {:?}", e);
+                    FetcherError::Stream(e.to_string())
+                }),
+                HttpBody::Empty => Ok(PageResponse::Page(Vec::new())),
             }
         }
-        Err(e) => Err(e),
+        #[allow(unreachable_patterns)]
+        _ => {
+            error!("Unsupported response type. We expected a HTTP response");
+            Err(FetcherError::Http(http::HttpError::UnknownError))
+        }
+    }
+}
+
+/// Fetches the body of the given URL as a top-level navigation. Downloads are read into memory as
+/// well, so this should only be used for resources that are expected to be small.
+pub async fn fetch_url_body(url: Url, network: &NetworkProfile) -> Result<Vec<u8>, FetcherError> {
+    match fetch_page(url, network, &GosubRenderMode::Rendered).await? {
+        PageResponse::Page(body) => Ok(body),
+        PageResponse::Download(download) => match download.body {
+            HttpBody::Reader(reader) => reader.vec().await.map_err(|e| FetcherError::Stream(e.to_string())),
+            HttpBody::Empty => Ok(Vec::new()),
+        },
+    }
+}
+
+/// Fetches the given URL for the browser itself, like search suggestions, instead of for a page. No
+/// cookies are sent or stored, so the request cannot be tracked across sites.
+pub async fn fetch_background(url: Url) -> Result<Vec<u8>, FetcherError> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(FetcherError::UnsupportedScheme(url.scheme().to_string()));
+    }
+
+    let http_fetcher = CompleteHttpFetcher::new(url.clone(), None);
+    let response = http_fetcher.fetch(HttpMethod::Get, url).await?;
+
+    let status_code = response.head().status_code();
+    if status_code != 200 {
+        return Err(FetcherError::Status(status_code));
     }
+
+    match response.body() {
+        HttpBody::Reader(reader) => reader.vec().await.map_err(|e| FetcherError::Stream(e.to_string())),
+        HttpBody::Empty => Ok(Vec::new()),
+    }
+}
+
+/// Fetches the given URL to save it to disk. When `offset` is not zero, only the part of the file
+/// from that position is requested, to resume a download. Servers that do not support ranges
+/// send the complete file, in which case the offset of the response is zero.
+pub async fn fetch_download(url: Url, network: &NetworkProfile, offset: u64) -> Result<DownloadResponse, FetcherError> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(FetcherError::UnsupportedScheme(url.scheme().to_string()));
+    }
+
+    let mut request = HttpRequestBuilder::new(HttpMethod::Get, url.clone()).top_level_site(Some(url.clone()));
+    if offset > 0 {
+        request = request.header("Range", &format!("bytes={}-", offset));
+    }
+
+    let fetcher = Fetcher::new(url, network);
+    let response = fetcher.http_fetcher.fetch_with_request(request.build()).await?;
+
+    let status_code = response.head().status_code();
+    if status_code != 200 && status_code != 206 {
+        return Err(FetcherError::Status(status_code));
+    }
+
+    let head = response.head().clone();
+    Ok(DownloadResponse::new(&head, response.body()))
 }
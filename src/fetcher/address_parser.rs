@@ -1,6 +1,11 @@
+use crate::fetcher::idn::{display_address, DisplayAddress};
+use crate::fetcher::scheme_handler::scheme_handlers;
+use crate::fetcher::search_engine::{search_engines, SearchEngines};
+use crate::settings::settings;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
-use url::Url;
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+use url::{Host, Url};
 
 // There is a difference between a Gosub Address and a URL. A Gosub Address is something that a user
 // can enter on the address bar and can be resolved in different ways. For example, the user can enter
@@ -30,23 +35,24 @@ use url::Url;
 //    - Custom renderer: for a custom view of a URL
 //    - Custom scheme: for custom functionality based on the URL
 
-/*
-Ultimately, we want this to be more flexible by using some kind of extension system so user-agent can
-define their own and possibly handle it their own way (by hooking in with a callback system that will
-output html that is rendered back by the engine. Something like this:
-
-fn GosubEngine::add_custom_rendermode(url: Url, mode: GosubRenderMode, callback: Fn) -> String { }
-
-Engine::add_custom_rendermode("reverse-view:", GosubRenderMode::Custom, |str| {
-    // Fetch the URL in the normal way, and reverse the content
-    let html = fetch_url(url);
-    html.chars().rev().collect()
-}
-
-Call on the address bar with:   `reverse-view:https://www.gosub.io
-
+// Custom render modes can be registered at runtime with a prefix and a callback that transforms the
+// fetched content into HTML or plain text:
+//
+//    GosubAddressParser::add_custom_rendermode("reverse-view:", |_url, content| {
+//        CustomRenderOutput::Text(content.chars().rev().collect())
+//    })?;
+//
+// Call on the address bar with:   `reverse-view:https://www.gosub.io`
 
-*/
+// Render mode prefixes are parsed from left to right, and can be composed in a fixed order: first the
+// way the content is presented (`source:` or `raw:`), then the type of the content (`json:` or `xml:`):
+//
+//    source:json:https://example.com/data.json   -> pretty-printed JSON source
+//    source:xml:https://example.com/feed.xml     -> highlighted XML (the same as `xml:`)
+//
+// Any other combination (`raw:json:`, `source:raw:`, `json:source:`, `source:source:`, a custom mode
+// with any other mode) is an error. `GosubAddressParser::format` does the reverse, and returns the
+// canonical address for a render mode and URL.
 
 /// Defines the different rendering modes for a URL.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -61,63 +67,332 @@ pub enum GosubRenderMode {
     Json,
     /// Rendered as highlighted XML
     Xml,
+    /// Rendered as pretty-printed, highlighted JSON source
+    PrettyJson,
     /// Custom rendering mode (user-defined)
     Custom(String),
 }
 
+/// Errors for render mode prefixes that cannot be parsed
+#[derive(Error, Debug, PartialEq)]
+pub enum RenderModeError {
+    #[error("unknown render mode '{0}:'")]
+    Unknown(String),
+    #[error("render mode '{0}:' is given more than once")]
+    Duplicate(String),
+    #[error("render modes '{0}:' and '{1}:' cannot be combined")]
+    Conflict(String, String),
+    #[error("render mode '{1}:' must come before '{0}:'")]
+    Order(String, String),
+}
+
+/// Built-in render mode prefixes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RenderPrefix {
+    Source,
+    RawSource,
+    Json,
+    Xml,
+}
+
+impl RenderPrefix {
+    /// Presentation prefixes define how the content is shown, and must come before content prefixes
+    fn is_presentation(&self) -> bool {
+        matches!(self, RenderPrefix::Source | RenderPrefix::RawSource)
+    }
+}
+
+/// All built-in render mode prefixes (without colon), including their aliases
+const RENDER_PREFIXES: [(&str, RenderPrefix); 6] = [
+    ("source", RenderPrefix::Source),
+    ("view-source", RenderPrefix::Source),
+    ("raw", RenderPrefix::RawSource),
+    ("raw-source", RenderPrefix::RawSource),
+    ("json", RenderPrefix::Json),
+    ("xml", RenderPrefix::Xml),
+];
+
+fn render_prefix(name: &str) -> Option<RenderPrefix> {
+    RENDER_PREFIXES.iter().find(|(n, _)| *n == name).map(|(_, prefix)| *prefix)
+}
+
+/// Output of a custom render mode handler
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CustomRenderOutput {
+    /// HTML that is rendered by the engine
+    Html(String),
+    /// Plain text that is displayed as-is
+    Text(String),
+}
+
+/// Callback that transforms the fetched content of the given URL into something that can be displayed
+pub type CustomRenderHandler = Arc<dyn Fn(&Url, &str) -> CustomRenderOutput + Send + Sync>;
+
 lazy_static! {
-    static ref RENDER_MODES: HashMap<&'static str, GosubRenderMode> = {
-        let mut m = HashMap::new();
-        m.insert("source:", GosubRenderMode::Source);
-        m.insert("view-source:", GosubRenderMode::Source);
-        m.insert("raw:", GosubRenderMode::RawSource);
-        m.insert("raw-source:", GosubRenderMode::RawSource);
-        m.insert("json:", GosubRenderMode::Json);
-        m.insert("xml:", GosubRenderMode::Xml);
-        m
-    };
+    /// Custom render modes that are registered at runtime, keyed by their prefix (including the colon)
+    static ref CUSTOM_RENDER_MODES: RwLock<Vec<(String, CustomRenderHandler)>> = RwLock::new(Vec::new());
 }
 
-/// Default scheme to add when none is present
-const DEFAULT_SCHEME: &str = "https://";
+/// Schemes that are always treated as a URL, even when the URL has no host (like `about:blank`)
+const KNOWN_SCHEMES: [&str; 12] = [
+    "http", "https", "ftp", "file", "about", "data", "gopher", "gemini", "mailto", "ws", "wss", "blob",
+];
+
+/// Prefix that forces the address to be treated as a search query
+const SEARCH_PREFIX: char = '?';
 
 /// Allows to parse a Gosub address (something that you can type on the address bar), and
 /// converts it into a URL and a rendering mode.
 pub struct GosubAddressParser {}
 
 impl GosubAddressParser {
-    /// Parses the given address into a URL and a rendering mode.
+    /// Parses the given address into a URL and a rendering mode. Addresses that do not look like a URL
+    /// are treated as search queries for the configured search engines.
     pub fn parse(address: &str) -> Result<(GosubRenderMode, Url), anyhow::Error> {
+        Self::parse_with(address, &search_engines())
+    }
+
+    /// Parses the given address into a URL and a rendering mode, and uses the given search engines to
+    /// resolve search queries.
+    pub fn parse_with(address: &str, engines: &SearchEngines) -> Result<(GosubRenderMode, Url), anyhow::Error> {
+        let address = address.trim();
         if address.is_empty() {
             return Err(anyhow::anyhow!("Empty address"));
         }
 
-        let mut address = address;
-        let mut mode = GosubRenderMode::Rendered;
-        for (name, value) in RENDER_MODES.iter() {
-            if address.starts_with(name) {
-                address = &address[name.len()..];
-                mode = value.clone();
+        let (mode, address) = Self::split_render_mode(address)?;
+
+        // "?rust" always searches, even if it looks like a URL
+        if let Some(query) = address.strip_prefix(SEARCH_PREFIX) {
+            return Ok((mode, engines.resolve(query)?));
+        }
+
+        // "w rust" searches with the engine that has "w" as keyword
+        if engines.is_keyword_search(address) {
+            return Ok((mode, engines.resolve(address)?));
+        }
+
+        // Schemes with a built-in handler (like "calc:1 + 2") can contain anything, including whitespace
+        if let Some((scheme, _)) = address.split_once(':') {
+            if scheme_handlers().is_registered(scheme) {
+                return Ok((mode, Url::parse(address)?));
             }
         }
 
+        // URLs never contain whitespace, so this must be a search query
+        if address.contains(char::is_whitespace) {
+            return Ok((mode, engines.resolve(address)?));
+        }
+
         match Url::parse(address) {
-            Ok(url) => Ok((mode, url)),
-            Err(url::ParseError::RelativeUrlWithoutBase) => {
-                // dbg!("NO MATCH: ", address);
-                match Self::parse(&format!("{}{}", DEFAULT_SCHEME, address)) {
-                    Ok((_, url)) => Ok((mode, url)),
-                    Err(e) => Err(e),
+            Ok(url) if KNOWN_SCHEMES.contains(&url.scheme()) || !url.cannot_be_a_base() => Ok((mode, url)),
+            // Things like "localhost:8080" or "example.com:8080" are parsed with "localhost" or "example.com"
+            // as the scheme, so we try again with the default scheme.
+            Ok(_) | Err(url::ParseError::RelativeUrlWithoutBase) => {
+                match Url::parse(&format!("{}://{}", settings().general.default_scheme, address)) {
+                    Ok(url) if Self::looks_like_host(&url) => Ok((mode, url)),
+                    _ => Ok((mode, engines.resolve(address)?)),
                 }
             }
             Err(e) => Err(anyhow::anyhow!("Cannot parse URL: {}", e)),
         }
     }
+
+    /// Returns how the given URL should be shown in the address bar. Internationalized domain names are
+    /// decoded from punycode, unless they could be used for spoofing. Typed unicode domain names are
+    /// converted to punycode while parsing, so `bücher.de` resolves to `xn--bcher-kva.de`.
+    pub fn display(url: &Url) -> DisplayAddress {
+        display_address(url)
+    }
+
+    /// Returns the canonical address for the given render mode and URL. Parsing the returned address
+    /// results in the same render mode and URL.
+    pub fn format(mode: &GosubRenderMode, url: &Url) -> String {
+        let prefix = match mode {
+            GosubRenderMode::Rendered => String::new(),
+            GosubRenderMode::Source => "source:".to_string(),
+            GosubRenderMode::RawSource => "raw:".to_string(),
+            GosubRenderMode::Json => "json:".to_string(),
+            GosubRenderMode::Xml => "xml:".to_string(),
+            GosubRenderMode::PrettyJson => "source:json:".to_string(),
+            GosubRenderMode::Custom(name) => format!("{}:", name),
+        };
+
+        format!("{}{}", prefix, url)
+    }
+
+    /// Splits the render mode prefixes from the address. Prefixes are read from left to right until
+    /// something is found that is not a render mode, and are then composed into a single render mode.
+    fn split_render_mode(address: &str) -> Result<(GosubRenderMode, &str), RenderModeError> {
+        let mut prefixes: Vec<(&str, RenderPrefix)> = Vec::new();
+        let mut custom: Option<&str> = None;
+        let mut rest = address;
+
+        while let Some((name, remainder)) = rest.split_once(':') {
+            if let Some(prefix) = render_prefix(name) {
+                prefixes.push((name, prefix));
+            } else if Self::is_custom_rendermode(name) {
+                if let Some(existing) = custom {
+                    return Err(RenderModeError::Conflict(existing.to_string(), name.to_string()));
+                }
+                custom = Some(name);
+            } else if Self::is_unknown_rendermode(name, remainder) {
+                return Err(RenderModeError::Unknown(name.to_string()));
+            } else {
+                break;
+            }
+            rest = remainder;
+        }
+
+        if let Some(custom) = custom {
+            return match prefixes.first() {
+                Some((name, _)) => Err(RenderModeError::Conflict(name.to_string(), custom.to_string())),
+                None => Ok((GosubRenderMode::Custom(custom.to_string()), rest)),
+            };
+        }
+
+        let mut presentation: Option<(&str, RenderPrefix)> = None;
+        let mut content: Option<(&str, RenderPrefix)> = None;
+        for (name, prefix) in prefixes {
+            if let (true, Some((content_name, _))) = (prefix.is_presentation(), content) {
+                return Err(RenderModeError::Order(content_name.to_string(), name.to_string()));
+            }
+
+            let slot = if prefix.is_presentation() {
+                &mut presentation
+            } else {
+                &mut content
+            };
+            if let Some((existing, existing_prefix)) = *slot {
+                return Err(if existing_prefix == prefix {
+                    RenderModeError::Duplicate(name.to_string())
+                } else {
+                    RenderModeError::Conflict(existing.to_string(), name.to_string())
+                });
+            }
+            *slot = Some((name, prefix));
+        }
+
+        let mode = match (presentation, content) {
+            (None, None) => GosubRenderMode::Rendered,
+            (Some((_, RenderPrefix::Source)), None) => GosubRenderMode::Source,
+            (Some((_, RenderPrefix::RawSource)), None) => GosubRenderMode::RawSource,
+            (None, Some((_, RenderPrefix::Json))) => GosubRenderMode::Json,
+            (None, Some((_, RenderPrefix::Xml))) => GosubRenderMode::Xml,
+            (Some((_, RenderPrefix::Source)), Some((_, RenderPrefix::Json))) => GosubRenderMode::PrettyJson,
+            (Some((_, RenderPrefix::Source)), Some((_, RenderPrefix::Xml))) => GosubRenderMode::Xml,
+            (Some((presentation, _)), Some((content, _))) => {
+                return Err(RenderModeError::Conflict(presentation.to_string(), content.to_string()))
+            }
+            _ => unreachable!("prefixes are sorted into the correct slot"),
+        };
+
+        Ok((mode, rest))
+    }
+
+    fn is_custom_rendermode(name: &str) -> bool {
+        CUSTOM_RENDER_MODES
+            .read()
+            .unwrap()
+            .iter()
+            .any(|(prefix, _)| prefix.trim_end_matches(':') == name)
+    }
+
+    /// Returns true when the name is meant as a render mode, but is not a known one. This is the case
+    /// for things like `foo:https://example.com` or `foo:source:example.com`, but not for addresses
+    /// like `localhost:8080` or `foo://example.com`.
+    fn is_unknown_rendermode(name: &str, remainder: &str) -> bool {
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            || KNOWN_SCHEMES.contains(&name)
+            || scheme_handlers().is_registered(name)
+            || remainder.starts_with("//")
+        {
+            return false;
+        }
+
+        match remainder.split_once(':') {
+            Some((next, _)) => {
+                KNOWN_SCHEMES.contains(&next)
+                    || scheme_handlers().is_registered(next)
+                    || render_prefix(next).is_some()
+                    || Self::is_custom_rendermode(next)
+            }
+            None => false,
+        }
+    }
+
+    /// Registers a custom render mode. Addresses that start with the given prefix (like `reverse-view:`)
+    /// get the render mode `GosubRenderMode::Custom`, and their fetched content is passed through the
+    /// handler before it is displayed. Registering an existing prefix replaces its handler.
+    pub fn add_custom_rendermode<F>(prefix: &str, handler: F) -> anyhow::Result<()>
+    where
+        F: Fn(&Url, &str) -> CustomRenderOutput + Send + Sync + 'static,
+    {
+        let name = prefix.strip_suffix(':').unwrap_or(prefix);
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow::anyhow!("Invalid render mode prefix: {}", prefix));
+        }
+
+        let prefix = format!("{}:", name);
+        if render_prefix(name).is_some() || KNOWN_SCHEMES.contains(&name) || scheme_handlers().is_registered(name) {
+            return Err(anyhow::anyhow!("Render mode prefix {} is already in use", prefix));
+        }
+
+        let mut modes = CUSTOM_RENDER_MODES.write().unwrap();
+        modes.retain(|(existing, _)| *existing != prefix);
+        modes.push((prefix, Arc::new(handler)));
+        Ok(())
+    }
+
+    /// Removes a custom render mode that was registered before
+    pub fn remove_custom_rendermode(prefix: &str) {
+        let name = prefix.strip_suffix(':').unwrap_or(prefix);
+        CUSTOM_RENDER_MODES
+            .write()
+            .unwrap()
+            .retain(|(existing, _)| existing.trim_end_matches(':') != name);
+    }
+
+    /// Transforms the content of the given URL with the handler of the custom render mode. Returns None
+    /// when no handler is registered for the render mode.
+    pub fn render_custom(name: &str, url: &Url, content: &str) -> Option<CustomRenderOutput> {
+        let handler = CUSTOM_RENDER_MODES
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(prefix, _)| prefix.trim_end_matches(':') == name)
+            .map(|(_, handler)| handler.clone())?;
+
+        // The lock is released before calling the handler, so handlers can (un)register modes themselves
+        Some(handler(url, content))
+    }
+
+    /// Returns true when the host of the URL looks like something that is meant to be visited
+    /// instead of searched for: localhost, an IP address, an explicit port, or a domain name with a
+    /// top level domain.
+    fn looks_like_host(url: &Url) -> bool {
+        match url.host() {
+            Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) => true,
+            Some(Host::Domain(domain)) => {
+                if domain == "localhost" || url.port().is_some() {
+                    return true;
+                }
+
+                match domain.trim_end_matches('.').rsplit_once('.') {
+                    Some((_, tld)) => tld.starts_with("xn--") || (tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())),
+                    None => false,
+                }
+            }
+            None => false,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     macro_rules! address_valid {
         ($($name:ident: $value:expr,)*) => {
@@ -125,7 +400,7 @@ mod tests {
                 #[test]
                 fn $name() {
                     let (address, expected_mode, expected_scheme, expected_host, expected_path) = $value;
-                    let (found_mode, url) = GosubAddressParser::parse(address).expect(&format!("Failed to parse address: {}", address));
+                    let (found_mode, url) = GosubAddressParser::parse_with(address, &SearchEngines::default()).expect(&format!("Failed to parse address: {}", address));
                     assert_eq!(
                         found_mode, expected_mode,
                         "Mode mismatch for address: {}. Expected: {:?}, Found: {:?}",
@@ -156,18 +431,141 @@ mod tests {
         test_2: ("example.com", GosubRenderMode::Rendered, "https", "example.com", "/"),
         test_3: ("http://example.com", GosubRenderMode::Rendered, "http", "example.com", "/"),
         test_4: ("http://example", GosubRenderMode::Rendered, "http", "example", "/"),
-        test_5: ("example", GosubRenderMode::Rendered, "https", "example", "/"),
+        test_5: ("localhost", GosubRenderMode::Rendered, "https", "localhost", "/"),
+        test_6: ("localhost:8080/foo", GosubRenderMode::Rendered, "https", "localhost", "/foo"),
+        test_7: ("example.com:8080", GosubRenderMode::Rendered, "https", "example.com", "/"),
+        test_8: ("127.0.0.1", GosubRenderMode::Rendered, "https", "127.0.0.1", "/"),
+        test_9: ("xn--bcher-kva.example", GosubRenderMode::Rendered, "https", "xn--bcher-kva.example", "/"),
 
         test_11: ("source:https://example.com", GosubRenderMode::Source, "https", "example.com", "/"),
         test_12: ("raw:example.com", GosubRenderMode::RawSource, "https", "example.com", "/"),
         test_14: ("view-source:http://example", GosubRenderMode::Source, "http", "example", "/"),
-        test_15: ("source:example", GosubRenderMode::Source, "https", "example", "/"),
+        test_15: ("source:example.com", GosubRenderMode::Source, "https", "example.com", "/"),
 
         test_31: ("source:ftp://example.com/foo/bar", GosubRenderMode::Source, "ftp", "example.com", "/foo/bar"),
         test_32: ("raw:gopher://example.com", GosubRenderMode::RawSource, "gopher", "example.com", ""),
-        test_34: ("xml:example", GosubRenderMode::Xml, "https", "example", "/"),
+        test_34: ("xml:example.com", GosubRenderMode::Xml, "https", "example.com", "/"),
 
         test_40: ("about:blank", GosubRenderMode::Rendered, "about", "", "blank"),
+        test_16: ("view-source:json:http://example.com/a.json", GosubRenderMode::PrettyJson, "http", "example.com", "/a.json"),
+        test_17: ("source:xml:example.com", GosubRenderMode::Xml, "https", "example.com", "/"),
+        test_18: ("json:localhost:8080", GosubRenderMode::Json, "https", "localhost", "/"),
+
         test_41: ("source:about:blank", GosubRenderMode::Source, "about", "", "blank"),
+        test_42: ("calc:2+2", GosubRenderMode::Rendered, "calc", "", "2+2"),
+        test_43: ("calc:(1 + 2) * 3", GosubRenderMode::Rendered, "calc", "", "(1 + 2) * 3"),
+
+        test_44: ("bücher.de", GosubRenderMode::Rendered, "https", "xn--bcher-kva.de", "/"),
+        test_45: ("пример.рф", GosubRenderMode::Rendered, "https", "xn--e1afmkfd.xn--p1ai", "/"),
+        test_46: ("source:https://Bücher.DE/a", GosubRenderMode::Source, "https", "xn--bcher-kva.de", "/a"),
+        test_47: ("例え.テスト", GosubRenderMode::Rendered, "https", "xn--r8jz45g.xn--zckzah", "/"),
+        test_48: ("example。com", GosubRenderMode::Rendered, "https", "example.com", "/"),
+
+        test_50: ("example", GosubRenderMode::Rendered, "https", "duckduckgo.com", "/"),
+        test_51: ("rust gtk tutorial", GosubRenderMode::Rendered, "https", "duckduckgo.com", "/"),
+        test_52: ("w rust", GosubRenderMode::Rendered, "https", "en.wikipedia.org", "/wiki/Special:Search"),
+        test_53: ("?example.com", GosubRenderMode::Rendered, "https", "duckduckgo.com", "/"),
+        test_54: ("source:rust lang", GosubRenderMode::Source, "https", "duckduckgo.com", "/"),
+    }
+
+    #[test]
+    fn test_render_mode_errors() {
+        let cases = [
+            ("source:source:example.com", RenderModeError::Duplicate("source".into())),
+            ("source:view-source:example.com", RenderModeError::Duplicate("view-source".into())),
+            ("source:raw:example.com", RenderModeError::Conflict("source".into(), "raw".into())),
+            ("json:xml:example.com", RenderModeError::Conflict("json".into(), "xml".into())),
+            ("raw:json:example.com", RenderModeError::Conflict("raw".into(), "json".into())),
+            ("json:source:example.com", RenderModeError::Order("json".into(), "source".into())),
+            ("foo:https://example.com", RenderModeError::Unknown("foo".into())),
+            ("source:foo:json:example.com", RenderModeError::Unknown("foo".into())),
+        ];
+
+        for (address, expected) in cases {
+            let err = GosubAddressParser::parse_with(address, &SearchEngines::default()).unwrap_err();
+            assert_eq!(err.downcast_ref::<RenderModeError>(), Some(&expected), "address: {}", address);
+        }
+
+        // Unknown schemes with an authority are not mistaken for render modes
+        assert!(GosubAddressParser::parse_with("foo://example.com", &SearchEngines::default()).is_ok());
+    }
+
+    #[test]
+    fn test_search_query() {
+        let engines = SearchEngines::default();
+
+        let (_, url) = GosubAddressParser::parse_with("rust gtk tutorial", &engines).unwrap();
+        assert_eq!(url.query(), Some("q=rust+gtk+tutorial"));
+
+        let (_, url) = GosubAddressParser::parse_with("w rust", &engines).unwrap();
+        assert_eq!(url.query(), Some("search=rust"));
+
+        assert!(GosubAddressParser::parse_with("   ", &engines).is_err());
+    }
+
+    #[test]
+    fn test_custom_rendermode() {
+        let engines = SearchEngines::default();
+
+        GosubAddressParser::add_custom_rendermode("reverse-view:", |_, content| {
+            CustomRenderOutput::Text(content.chars().rev().collect())
+        })
+        .unwrap();
+
+        let (mode, url) = GosubAddressParser::parse_with("reverse-view:https://example.com", &engines).unwrap();
+        assert_eq!(mode, GosubRenderMode::Custom("reverse-view".into()));
+        assert_eq!(url.as_str(), "https://example.com/");
+
+        let output = GosubAddressParser::render_custom("reverse-view", &url, "abc");
+        assert_eq!(output, Some(CustomRenderOutput::Text("cba".into())));
+
+        // Built-in prefixes and schemes cannot be overridden
+        assert!(GosubAddressParser::add_custom_rendermode("source:", |_, c| CustomRenderOutput::Text(c.into())).is_err());
+        assert!(GosubAddressParser::add_custom_rendermode("https", |_, c| CustomRenderOutput::Text(c.into())).is_err());
+        assert!(GosubAddressParser::add_custom_rendermode("a b:", |_, c| CustomRenderOutput::Text(c.into())).is_err());
+
+        GosubAddressParser::remove_custom_rendermode("reverse-view");
+        assert_eq!(GosubAddressParser::render_custom("reverse-view", &url, "abc"), None);
+    }
+
+    fn render_mode_strategy() -> impl Strategy<Value = GosubRenderMode> {
+        prop_oneof![
+            Just(GosubRenderMode::Rendered),
+            Just(GosubRenderMode::Source),
+            Just(GosubRenderMode::RawSource),
+            Just(GosubRenderMode::Json),
+            Just(GosubRenderMode::Xml),
+            Just(GosubRenderMode::PrettyJson),
+        ]
+    }
+
+    fn url_strategy() -> impl Strategy<Value = Url> {
+        (
+            prop_oneof![Just("http"), Just("https"), Just("ftp"), Just("gopher")],
+            "[a-z][a-z0-9-]{0,10}\\.(com|org|io|dev)",
+            proptest::option::of(1u16..),
+            "(/[a-zA-Z0-9._~-]{1,8}){0,3}",
+            proptest::option::of("[a-z]{1,5}=[a-z0-9]{0,5}"),
+        )
+            .prop_map(|(scheme, host, port, path, query)| {
+                let port = port.map(|p| format!(":{}", p)).unwrap_or_default();
+                let query = query.map(|q| format!("?{}", q)).unwrap_or_default();
+                Url::parse(&format!("{}://{}{}{}{}", scheme, host, port, path, query)).unwrap()
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_format_roundtrip(mode in render_mode_strategy(), url in url_strategy()) {
+            let address = GosubAddressParser::format(&mode, &url);
+            let (parsed_mode, parsed_url) = GosubAddressParser::parse_with(&address, &SearchEngines::default()).unwrap();
+            prop_assert_eq!(parsed_mode, mode);
+            prop_assert_eq!(parsed_url, url);
+        }
+
+        #[test]
+        fn test_parse_never_panics(address in "\\PC{0,40}") {
+            _ = GosubAddressParser::parse_with(&address, &SearchEngines::default());
+        }
     }
 }
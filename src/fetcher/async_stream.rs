@@ -88,6 +88,12 @@ impl AsyncStream {
         }
     }
 
+    /// Returns the next chunk of the stream, or None when the stream has ended. This allows a stream
+    /// to be processed without keeping all of it in memory.
+    pub async fn next_chunk(&mut self) -> Option<anyhow::Result<Bytes>> {
+        std::future::poll_fn(|cx| self.stream.as_mut().poll_next(cx)).await
+    }
+
     /// This function captures a whole stream and turns it into a Bytes::bytes
     pub fn bytes(self) -> impl Future<Output = anyhow::Result<Bytes>> {
         let to_vec = self.vec();
@@ -0,0 +1,89 @@
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// Simple disk cache that stores response bodies keyed by their URL. Each network profile has its
+/// own partition (directory), so cached content is never shared between profiles.
+pub struct DiskCache {
+    /// Directory of this cache partition
+    dir: PathBuf,
+    /// Entries older than this are considered stale
+    max_age: Duration,
+}
+
+impl DiskCache {
+    /// Creates a cache partition with the given name inside the given root directory
+    pub fn new(root: &Path, partition: &str, max_age: Duration) -> Self {
+        Self {
+            dir: root.join(partition),
+            max_age,
+        }
+    }
+
+    /// Returns the path of the cache entry for the given URL. The hash is not guaranteed to be
+    /// stable between builds, which only results in a cache miss.
+    fn entry_path(&self, url: &Url) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        self.dir.join(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Returns the cached data for the given URL, if present and not stale
+    pub fn get(&self, url: &Url) -> Option<Vec<u8>> {
+        let path = self.entry_path(url);
+
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if age > self.max_age {
+            _ = fs::remove_file(&path);
+            return None;
+        }
+
+        fs::read(path).ok()
+    }
+
+    /// Stores the data for the given URL
+    pub fn put(&self, url: &Url, data: &[u8]) {
+        if let Err(e) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(self.entry_path(url), data)) {
+            warn!(target: "fetcher", "failed to write cache entry for {}: {:?}", url, e);
+        }
+    }
+
+    /// Removes all entries from this cache partition
+    pub fn clear(&self) {
+        if self.dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                warn!(target: "fetcher", "failed to clear cache {}: {:?}", self.dir.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_cache() {
+        let root = std::env::temp_dir().join(format!("gosub-cache-test-{}", uuid::Uuid::new_v4()));
+        let cache_a = DiskCache::new(&root, "a", Duration::from_secs(60));
+        let cache_b = DiskCache::new(&root, "b", Duration::from_secs(60));
+        let url = Url::parse("https://example.com/favicon.ico").unwrap();
+
+        assert_eq!(cache_a.get(&url), None);
+        cache_a.put(&url, b"icon");
+        assert_eq!(cache_a.get(&url), Some(b"icon".to_vec()));
+
+        // Partitions are isolated from each other
+        assert_eq!(cache_b.get(&url), None);
+
+        cache_a.clear();
+        assert_eq!(cache_a.get(&url), None);
+
+        _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::fetcher::address_parser::GosubRenderMode;
+use crate::fetcher::http::response::ResponseHeader;
+use crate::fetcher::{FetcherError, HttpBody};
+use percent_encoding::percent_decode_str;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use url::Url;
+
+/// MIME types besides `text/*`, `*+json` and `*+xml` that the browser shows itself instead of
+/// downloading them
+const RENDERABLE_TYPES: [&str; 4] = [
+    "application/xml",
+    "application/json",
+    "application/javascript",
+    "application/ecmascript",
+];
+
+/// File name used when neither the response nor the URL has a usable file name
+const DEFAULT_FILENAME: &str = "download";
+
+/// A response that is saved to disk instead of being rendered
+pub struct DownloadResponse {
+    pub url: Url,
+    /// File name suggested by the server or the URL
+    pub filename: String,
+    pub mime: Option<String>,
+    /// Size of the complete file, when the server sent it
+    pub total: Option<u64>,
+    /// Position in the file where the body starts. Only non-zero when a download is resumed.
+    pub offset: u64,
+    pub body: HttpBody,
+}
+
+impl DownloadResponse {
+    /// Creates a download from a response
+    pub fn new(head: &ResponseHeader, body: HttpBody) -> Self {
+        let filename = head
+            .header("content-disposition")
+            .and_then(disposition_filename)
+            .unwrap_or_else(|| url_filename(head.url()));
+        let (offset, total) = match head.header("content-range").and_then(parse_content_range) {
+            Some((start, total)) => (start, total),
+            None => (0, head.content_length()),
+        };
+
+        Self {
+            url: head.url().clone(),
+            filename: sanitize_filename(&filename),
+            mime: head.header("content-type").map(mime_type),
+            total,
+            offset,
+            body,
+        }
+    }
+}
+
+/// Returns true when the response should be downloaded instead of rendered: the server asks for it
+/// with `Content-Disposition: attachment`, or the browser cannot render the content type. Only pages
+/// that are rendered are downloaded, as the source and JSON/XML modes show any response as text.
+pub fn is_download(head: &ResponseHeader, mode: &GosubRenderMode) -> bool {
+    if *mode != GosubRenderMode::Rendered {
+        return false;
+    }
+
+    if head.header("content-disposition").is_some_and(is_attachment) {
+        return true;
+    }
+
+    // Responses without a content type are rendered, as most servers that leave it out serve HTML
+    head.header("content-type")
+        .is_some_and(|content_type| !is_renderable(&mime_type(content_type)))
+}
+
+/// Returns true when the browser renders content of the given MIME type
+pub fn is_renderable(mime: &str) -> bool {
+    mime.starts_with("text/") || mime.ends_with("+json") || mime.ends_with("+xml") || RENDERABLE_TYPES.contains(&mime)
+}
+
+/// Returns the MIME type of a `Content-Type` header, without its parameters
+fn mime_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+fn is_attachment(disposition: &str) -> bool {
+    disposition
+        .split(';')
+        .next()
+        .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("attachment"))
+}
+
+/// Returns the file name of a `Content-Disposition` header. The UTF-8 `filename*` parameter is
+/// preferred over the plain `filename` parameter.
+pub fn disposition_filename(disposition: &str) -> Option<String> {
+    let mut filename = None;
+
+    for (name, value) in disposition_parameters(disposition) {
+        match name.to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // The value is `charset'language'percent-encoded-name`
+                let mut parts = value.splitn(3, '\'');
+                let charset = parts.next().unwrap_or_default();
+                let encoded = parts.nth(1)?;
+                if charset.eq_ignore_ascii_case("utf-8") {
+                    return Some(percent_decode_str(encoded).decode_utf8_lossy().to_string());
+                }
+            }
+            "filename" => filename = Some(value),
+            _ => {}
+        }
+    }
+
+    filename.filter(|name| !name.is_empty())
+}
+
+/// Splits the parameters of a header like `attachment; filename="a;b.txt"` into names and values.
+/// Quoted values are unquoted.
+fn disposition_parameters(header: &str) -> Vec<(String, String)> {
+    let mut parameters = Vec::new();
+    // Skip the disposition type
+    let mut rest = header.split_once(';').map_or("", |(_, rest)| rest);
+
+    loop {
+        rest = rest.trim_start_matches([';', ' ', '\t']);
+        let Some((name, value)) = rest.split_once('=') else {
+            break;
+        };
+        let value = value.trim_start();
+
+        let (value, after) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let mut unquoted = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => unquoted.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        c => unquoted.push(c),
+                    }
+                }
+                (unquoted, &quoted[end..])
+            }
+            None => {
+                let end = value.find(';').unwrap_or(value.len());
+                (value[..end].trim().to_string(), &value[end..])
+            }
+        };
+
+        parameters.push((name.trim().to_string(), value));
+        rest = after;
+    }
+
+    parameters
+}
+
+/// Returns the last segment of the URL path as file name
+fn url_filename(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_FILENAME.to_string())
+}
+
+/// Makes a file name suggested by a server safe to use: directories are stripped, and names that
+/// would be hidden or are empty are replaced
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim().trim_start_matches('.');
+
+    if name.is_empty() {
+        DEFAULT_FILENAME.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Parses a `Content-Range` header like `bytes 100-199/200` into the position of the first byte and
+/// the size of the complete file (when known)
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (range, total) = range.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// Streams the body to the given file, appending to it when `append` is set. After every chunk the
+/// progress function is called with the number of bytes written so far. Returns the number of bytes
+/// that have been written.
+pub async fn save_to_file(body: HttpBody, path: &Path, append: bool, mut progress: impl FnMut(u64)) -> Result<u64, FetcherError> {
+    let file = if append {
+        OpenOptions::new().append(true).create(true).open(path)?
+    } else {
+        File::create(path)?
+    };
+    let mut writer = BufWriter::new(file);
+
+    let HttpBody::Reader(mut stream) = body else {
+        return Ok(0);
+    };
+
+    let mut written = 0;
+    while let Some(chunk) = stream.next_chunk().await {
+        let chunk = chunk.map_err(|e| FetcherError::Stream(e.to_string()))?;
+        writer.write_all(&chunk)?;
+        written += chunk.len() as u64;
+        progress(written);
+    }
+    writer.flush()?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::http::response::HttpVersion;
+    use std::collections::HashMap;
+
+    fn head(url: &str, headers: &[(&str, &str)]) -> ResponseHeader {
+        ResponseHeader::new(
+            HttpVersion::Http11,
+            200,
+            headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            Some(1000),
+            HashMap::new(),
+            Url::parse(url).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_is_download() {
+        let rendered = &GosubRenderMode::Rendered;
+        let download = |url, headers: &[(&str, &str)]| is_download(&head(url, headers), rendered);
+
+        assert!(!download("https://example.com/", &[("content-type", "text/html; charset=utf-8")]));
+        assert!(!download("https://example.com/", &[]));
+        assert!(!download("https://example.com/", &[("Content-Type", "application/JSON")]));
+        assert!(!download("https://example.com/feed", &[("content-type", "application/atom+xml")]));
+        assert!(!download("https://example.com/api", &[("content-type", "application/ld+json")]));
+        assert!(!download(
+            "https://example.com/app.js",
+            &[("content-type", "application/javascript")]
+        ));
+        assert!(!download("https://example.com/logo.svg", &[("content-type", "image/svg+xml")]));
+        assert!(download("https://example.com/a.zip", &[("content-type", "application/zip")]));
+        assert!(download("https://example.com/a.png", &[("content-type", "image/png")]));
+        assert!(download(
+            "https://example.com/report",
+            &[
+                ("content-type", "text/csv"),
+                ("content-disposition", "Attachment; filename=report.csv")
+            ]
+        ));
+        assert!(!download(
+            "https://example.com/",
+            &[("content-type", "text/html"), ("content-disposition", "inline")]
+        ));
+
+        // Viewing the source of a response never starts a download
+        let zip = head("https://example.com/a.zip", &[("content-type", "application/zip")]);
+        assert!(!is_download(&zip, &GosubRenderMode::Source));
+        assert!(!is_download(&zip, &GosubRenderMode::RawSource));
+        let attachment = head(
+            "https://example.com/data",
+            &[("content-type", "application/json"), ("content-disposition", "attachment")],
+        );
+        assert!(is_download(&attachment, rendered));
+        assert!(!is_download(&attachment, &GosubRenderMode::PrettyJson));
+    }
+
+    #[test]
+    fn test_disposition_filename() {
+        assert_eq!(disposition_filename("attachment; filename=report.csv").unwrap(), "report.csv");
+        assert_eq!(
+            disposition_filename("attachment; filename=\"a; \\\"b\\\".txt\"").unwrap(),
+            "a; \"b\".txt"
+        );
+        assert_eq!(
+            disposition_filename("attachment; filename=\"plain.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt").unwrap(),
+            "€ rates.txt"
+        );
+        assert_eq!(
+            disposition_filename("attachment; filename*=ISO-8859-1''x.txt; filename=y.txt").unwrap(),
+            "y.txt"
+        );
+        assert_eq!(disposition_filename("attachment"), None);
+        assert_eq!(disposition_filename("attachment; filename=\"\""), None);
+    }
+
+    #[test]
+    fn test_download_response() {
+        let response = DownloadResponse::new(
+            &head(
+                "https://example.com/files/my%20file.tar.gz?x=1",
+                &[("content-type", "application/gzip")],
+            ),
+            HttpBody::Empty,
+        );
+        assert_eq!(response.filename, "my file.tar.gz");
+        assert_eq!(response.mime.as_deref(), Some("application/gzip"));
+        assert_eq!(response.total, Some(1000));
+        assert_eq!(response.offset, 0);
+
+        // A resumed download starts at the offset of the range
+        let response = DownloadResponse::new(
+            &head("https://example.com/", &[("content-range", "bytes 500-999/1500")]),
+            HttpBody::Empty,
+        );
+        assert_eq!(response.filename, "download");
+        assert_eq!(response.offset, 500);
+        assert_eq!(response.total, Some(1500));
+
+        assert_eq!(parse_content_range("bytes 0-99/*"), Some((0, None)));
+        assert_eq!(parse_content_range("bytes */1500"), None);
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\Users\\me\\file.txt"), "file.txt");
+        assert_eq!(sanitize_filename(".bashrc"), "bashrc");
+        assert_eq!(sanitize_filename("a\nb.txt"), "ab.txt");
+        assert_eq!(sanitize_filename("/"), "download");
+    }
+}
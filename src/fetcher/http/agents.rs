@@ -1,6 +1,8 @@
+use crate::cookies::jar::CookieJar;
 use crate::fetcher::http::request::HttpRequest;
 use crate::fetcher::http::response::HttpResponse;
 use crate::fetcher::http::HttpError;
+use std::sync::Arc;
 
 #[cfg(all(feature = "http-agent", not(any(feature = "http-agent-reqwest", feature = "http-agent-ureq"))))]
 compile_error!(
@@ -18,8 +20,8 @@ pub mod ureq;
 // dedicated libraries like reqwest, ureq, hyper, or surf to make the actual request and return
 // it back into a more generic Gosub Http Request/Response format that is used by the engine.
 pub trait HttpRequestAgent {
-    /// Create a new request agent
-    fn new() -> Self;
+    /// Create a new request agent that uses the given cookie jar (if any)
+    fn new(jar: Option<Arc<CookieJar>>) -> Self;
 
     /// Executes the given request and returns the response or an error
     async fn execute(&self, req: HttpRequest) -> Result<HttpResponse, HttpError>;
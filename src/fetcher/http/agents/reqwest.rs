@@ -1,62 +1,69 @@
 use crate::cookies::jar::CookieJar;
-use crate::cookies::sqlite_store::SqliteStorage;
 use crate::fetcher::async_stream::AsyncStream;
 use crate::fetcher::http::agents::HttpRequestAgent;
 use crate::fetcher::http::request::HttpRequest;
 use crate::fetcher::http::response::{HttpResponse, HttpVersion, ResponseHeader};
+use crate::fetcher::http::HttpBody;
 use crate::fetcher::http::HttpError;
-use crate::fetcher::http::{HttpBody, GOSUB_USERAGENT_STRING};
-use crate::fetcher::HttpMethod;
-use log::info;
-use reqwest::header::{HeaderMap, HeaderName};
-use reqwest::{Method, Version};
+use crate::fetcher::{HttpMethod, GOSUB_USERAGENT_STRING};
+use crate::settings::settings;
+use log::{error, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, LOCATION, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Method, Response, StatusCode, Version};
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
+use url::Url;
+
+/// Maximum number of redirects that are followed for a single request
+const MAX_REDIRECTS: usize = 10;
 
 /// Http agent that uses reqwest library to make HTTP requests
 pub struct ReqwestAgent {
     client: reqwest::Client,
+    /// Cookie jar that is used for all requests made by this agent
+    jar: Option<Arc<CookieJar>>,
 }
 
 impl HttpRequestAgent for ReqwestAgent {
-    fn new() -> Self {
+    fn new(jar: Option<Arc<CookieJar>>) -> Self {
+        // Agents are created for every fetch, so changed settings are used right away. There is no
+        // timeout for the whole request, as that would abort large downloads.
+        let network = settings().network.clone();
+
+        // The user agent can be edited in the settings. One that cannot be sent as a header would
+        // make every request fail, so the default is used instead.
+        let user_agent = HeaderValue::from_str(&network.user_agent).unwrap_or_else(|_| {
+            warn!(target: "fetcher", "invalid user agent {:?}, using the default", network.user_agent);
+            HeaderValue::from_static(GOSUB_USERAGENT_STRING)
+        });
+
         // Create the actual client that will handle the requests
-        let mut builder = reqwest::Client::builder()
-            .user_agent(GOSUB_USERAGENT_STRING)
-            .timeout(Duration::from_secs(5))
+        let builder = reqwest::Client::builder()
+            .user_agent(user_agent)
             .use_rustls_tls() // For HTTP2
-            .connect_timeout(Duration::from_secs(5))
+            .connect_timeout(Duration::from_secs(network.connect_timeout))
             .connection_verbose(true)
-            .read_timeout(Duration::from_secs(5))
+            .read_timeout(Duration::from_secs(network.read_timeout))
             .brotli(true)
             .gzip(true)
-            .deflate(true);
-
-        // Cookies should be dealt with more flexible
-        let jar = match SqliteStorage::new("./gosub_cookies.db") {
-            Ok(store) => {
-                info!(target: "fetcher", "successfully created SqliteStorage");
-                Some(CookieJar::new(Arc::new(Mutex::new(store))))
-            }
-            Err(e) => {
-                info!(target: "fetcher", "failed to create SqliteStorage: {:?}", e);
-                None
-            }
-        };
+            .deflate(true)
+            // Redirects are followed by the agent, so the cookies of every hop end up in the jar
+            .redirect(Policy::none());
 
-        match jar {
-            Some(jar) => {
-                builder = builder.cookie_provider(Arc::new(jar));
-            }
-            None => {
-                info!(target: "fetcher", "no cookie jar");
-            }
+        // Cookies are not handled by reqwest itself, as the jar needs to know the top-level site
+        // of each request in order to apply the cookie policy.
+        if jar.is_none() {
+            info!(target: "fetcher", "no cookie jar");
         }
 
-        Self {
-            client: builder.build().unwrap(),
-        }
+        let client = builder.build().unwrap_or_else(|e| {
+            error!(target: "fetcher", "cannot create the http client, using the defaults: {}", e);
+            reqwest::Client::builder().redirect(Policy::none()).build().unwrap_or_default()
+        });
+
+        Self { client, jar }
     }
 
     async fn execute(&self, req: HttpRequest) -> Result<HttpResponse, HttpError> {
@@ -70,19 +77,77 @@ impl HttpRequestAgent for ReqwestAgent {
             "Accept",
             "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".parse().unwrap(),
         );
-        headers.insert("Accept-Language", "en-US,en;q=0.5".parse().unwrap());
-        headers.insert("DNT", "1".parse().unwrap());
+        let settings = settings();
+        if let Ok(accept_language) = settings.network.accept_language.parse() {
+            headers.insert("Accept-Language", accept_language);
+        }
+        if settings.privacy.do_not_track {
+            headers.insert("DNT", "1".parse().unwrap());
+        }
 
         for (key, value) in req.headers {
             headers.insert(HeaderName::from_str(&key).unwrap(), value.parse().unwrap());
         }
 
-        // Cookies are automatically added through the CookieJar we defined in the constructor
+        // Follow the redirects. Cookies are sent to and stored for the URL of each hop, as login flows
+        // usually set their cookies on a redirect.
+        let mut method: Method = req.method.into();
+        let mut url = req.url.clone();
+        // A top-level navigation moves to the site it is redirected to, so the hops of a login flow on
+        // another site are not third-party requests
+        let top_level_navigation = req.top_level_site.as_ref() == Some(&req.url);
+        let mut top_level_site = req.top_level_site.clone();
+        let mut redirects = 0;
+        let result = loop {
+            if top_level_navigation {
+                top_level_site = Some(url.clone());
+            }
+
+            let mut hop_headers = headers.clone();
+            if let Some(jar) = &self.jar {
+                if let Some(cookie_header) = jar.cookies_for(&url, top_level_site.as_ref()) {
+                    hop_headers.insert(COOKIE, cookie_header);
+                }
+            }
+
+            let request_builder = self.client.request(method.clone(), url.clone()).headers(hop_headers);
+            let request = request_builder.build().map_err(|e| HttpError::AgentError(Box::new(e)))?;
+
+            let response = match self.client.execute(request).await {
+                Ok(response) => response,
+                Err(e) => break Err(e),
+            };
+
+            if let Some(jar) = &self.jar {
+                jar.set_cookies_for(&mut response.headers().get_all(SET_COOKIE).iter(), &url, top_level_site.as_ref());
+            }
+
+            let Some(location) = redirect_location(&response, &url) else {
+                break Ok(response);
+            };
+
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(HttpError::TooManyRedirects(req.url.clone()));
+            }
+            info!(target: "fetcher", "redirect {} to {}", response.status(), location);
 
-        let request_builder = self.client.request(req.method.into(), req.url.clone()).headers(headers.clone());
-        let request = request_builder.build().map_err(|e| HttpError::AgentError(Box::new(e)))?;
+            // See Other always continues with a GET, and so do the older redirects for a POST
+            let status = response.status();
+            if status == StatusCode::SEE_OTHER
+                || (method == Method::POST && (status == StatusCode::MOVED_PERMANENTLY || status == StatusCode::FOUND))
+            {
+                method = Method::GET;
+            }
+
+            // Credentials are not sent to other hosts
+            if location.host_str() != url.host_str() {
+                headers.remove(AUTHORIZATION);
+            }
+            url = location;
+        };
 
-        match self.client.execute(request).await {
+        match result {
             Ok(response) => {
                 let res_header = ResponseHeader::new(
                     response.version().into(),
@@ -94,7 +159,8 @@ impl HttpRequestAgent for ReqwestAgent {
                         .collect(),
                     response.content_length(),
                     response.cookies().map(|c| (c.name().to_string(), c.value().to_string())).collect(),
-                    req.url.clone(),
+                    // The URL of the resource after the redirects
+                    url,
                 );
 
                 // Check if we have a content length, if so, we can decide if we want to read the
@@ -128,6 +194,21 @@ impl HttpRequestAgent for ReqwestAgent {
     }
 }
 
+/// Returns the URL the response redirects to, or None when the response is not a redirect
+fn redirect_location(response: &Response, url: &Url) -> Option<Url> {
+    match response.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => {}
+        _ => return None,
+    }
+
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
 impl From<reqwest::Version> for HttpVersion {
     fn from(value: Version) -> Self {
         match value {
@@ -1,13 +1,15 @@
+use crate::cookies::jar::CookieJar;
 use crate::fetcher::http::agents::HttpRequestAgent;
 use crate::fetcher::http::request::HttpRequest;
 use crate::fetcher::http::response::HttpResponse;
 use crate::fetcher::http::HttpError;
+use std::sync::Arc;
 use url::Url;
 
 pub struct UreqAgent;
 
 impl HttpRequestAgent for UreqAgent {
-    fn new() -> Self {
+    fn new(_jar: Option<Arc<CookieJar>>) -> Self {
         Self
     }
 
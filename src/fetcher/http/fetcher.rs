@@ -1,9 +1,11 @@
+use crate::cookies::jar::CookieJar;
 use crate::fetcher::http::agents::HttpRequestAgent;
 use crate::fetcher::http::request::{HttpRequest, HttpRequestBuilder};
 use crate::fetcher::http::response::HttpResponse;
 use crate::fetcher::http::HttpError;
 use crate::fetcher::http::HttpMethod;
 use log::info;
+use std::sync::Arc;
 use url::Url;
 
 /// The HTTP fetcher is the main entry point for fetching HTTP resources (starting with https:// or http://).
@@ -18,11 +20,11 @@ pub struct HttpFetcher<R: HttpRequestAgent> {
 }
 
 impl<R: HttpRequestAgent> HttpFetcher<R> {
-    /// Creates a new HTTP fetcher for the given baseUrl
-    pub fn new(base_url: Url) -> Self {
+    /// Creates a new HTTP fetcher for the given baseUrl, that uses the given cookie jar
+    pub fn new(base_url: Url, jar: Option<Arc<CookieJar>>) -> Self {
         Self {
             base_url,
-            agent: R::new(),
+            agent: R::new(jar),
             middleware: None,
         }
     }
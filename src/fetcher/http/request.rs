@@ -17,6 +17,8 @@ pub struct HttpRequest {
     pub body: HttpBody,
     /// Cookies to send with the request
     pub cookies: HashMap<String, String>,
+    /// URL of the page in the tab that started this request. Used to detect third-party requests.
+    pub top_level_site: Option<Url>,
 }
 
 /// Builder for HttpRequest
@@ -28,6 +30,7 @@ pub struct HttpRequestBuilder {
     headers: HashMap<String, String>,
     body: HttpBody,
     cookies: HashMap<String, String>,
+    top_level_site: Option<Url>,
 }
 
 impl HttpRequestBuilder {
@@ -39,6 +42,7 @@ impl HttpRequestBuilder {
             headers: HashMap::new(),
             body: HttpBody::Empty,
             cookies: HashMap::new(),
+            top_level_site: None,
         }
     }
 
@@ -62,6 +66,11 @@ impl HttpRequestBuilder {
         self
     }
 
+    pub fn top_level_site(mut self, url: Option<Url>) -> Self {
+        self.top_level_site = url;
+        self
+    }
+
     pub fn build(self) -> HttpRequest {
         HttpRequest {
             method: self.method,
@@ -70,6 +79,7 @@ impl HttpRequestBuilder {
             headers: self.headers,
             body: self.body,
             cookies: self.cookies,
+            top_level_site: self.top_level_site,
         }
     }
 }
@@ -87,6 +97,7 @@ mod test {
             .header("Content-Type", "application/json")
             .cookie("session", "123456")
             .body(HttpBody::Empty)
+            .top_level_site(Some(Url::parse("https://www.example.com").unwrap()))
             .build();
 
         assert_eq!(request.method, HttpMethod::Get);
@@ -98,5 +109,6 @@ mod test {
         assert_eq!(request.headers.get("Content-Type"), Some(&"application/json".to_string()));
         assert_eq!(request.cookies.len(), 1);
         assert_eq!(request.cookies.get("session"), Some(&"123456".to_string()));
+        assert_eq!(request.top_level_site.unwrap().as_str(), "https://www.example.com/");
     }
 }
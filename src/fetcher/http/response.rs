@@ -81,6 +81,14 @@ impl ResponseHeader {
         &self.headers
     }
 
+    /// Returns the value of the given header. Header names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
     pub fn content_length(&self) -> Option<u64> {
         self.content_length
     }
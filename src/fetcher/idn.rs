@@ -0,0 +1,111 @@
+use unicode_security::{skeleton, RestrictionLevel, RestrictionLevelDetection};
+use url::{Host, Position, Url};
+
+/// How an address should be displayed in the address bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayAddress {
+    /// Text to show
+    pub text: String,
+    /// The host contains characters that can be confused with other characters, so it is shown as
+    /// punycode instead. The address bar should show a warning.
+    pub spoof_warning: bool,
+}
+
+/// Returns the address to display for the given URL. Internationalized domain names are shown in
+/// unicode when every label passes the mixed-script and confusable checks, and as punycode otherwise.
+pub fn display_address(url: &Url) -> DisplayAddress {
+    let punycode = DisplayAddress {
+        text: url.to_string(),
+        spoof_warning: false,
+    };
+
+    let Some(Host::Domain(host)) = url.host() else {
+        return punycode;
+    };
+    if !host.split('.').any(|label| label.starts_with("xn--")) {
+        return punycode;
+    }
+
+    let (unicode, result) = idna::domain_to_unicode(host);
+    if result.is_err() || !unicode.split('.').all(is_safe_label) {
+        return DisplayAddress {
+            spoof_warning: true,
+            ..punycode
+        };
+    }
+
+    DisplayAddress {
+        text: format!("{}{}{}", &url[..Position::BeforeHost], unicode, &url[Position::AfterHost..]),
+        spoof_warning: false,
+    }
+}
+
+/// Returns true when the (unicode) domain label can be shown safely. This is the case for labels
+/// that do not mix scripts (apart from the combinations that are common in CJK languages), and that
+/// cannot be confused with an ASCII label as a whole (like a cyrillic "аррӏе" for "apple").
+pub fn is_safe_label(label: &str) -> bool {
+    if label.is_ascii() {
+        return true;
+    }
+
+    // Hyphens are allowed in labels, but are not identifier characters
+    let without_hyphens: String = label.chars().filter(|c| *c != '-').collect();
+    if !without_hyphens
+        .as_str()
+        .check_restriction_level(RestrictionLevel::HighlyRestrictive)
+    {
+        return false;
+    }
+
+    !skeleton(label).all(|c| c.is_ascii())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_labels() {
+        // Single-script labels
+        assert!(is_safe_label("bücher"));
+        assert!(is_safe_label("münchen-straße"));
+        assert!(is_safe_label("пример"));
+        assert!(is_safe_label("例え"));
+        assert!(is_safe_label("日本語かな"));
+
+        // Latin mixed with cyrillic
+        assert!(!is_safe_label("pаypal"));
+        // Whole-script confusable with an ASCII label
+        assert!(!is_safe_label("аррӏе"));
+        assert!(!is_safe_label("ехаmрlе"));
+        // Symbols are not allowed in identifiers
+        assert!(!is_safe_label("exa⁄mple"));
+    }
+
+    #[test]
+    fn test_display_address() {
+        let url = Url::parse("https://xn--bcher-kva.de/path?q=1").unwrap();
+        assert_eq!(
+            display_address(&url),
+            DisplayAddress {
+                text: "https://bücher.de/path?q=1".into(),
+                spoof_warning: false
+            }
+        );
+
+        let url = Url::parse("https://пример.рф/").unwrap();
+        assert_eq!(display_address(&url).text, "https://пример.рф/");
+
+        // Cyrillic "аррӏе.com" stays punycode, with a warning
+        let url = Url::parse("https://аррӏе.com/").unwrap();
+        let display = display_address(&url);
+        assert!(display.spoof_warning);
+        assert_eq!(display.text, url.to_string());
+        assert!(display.text.contains("xn--"));
+
+        // ASCII hosts are left alone
+        let url = Url::parse("https://user@example.com:8080/").unwrap();
+        assert_eq!(display_address(&url).text, "https://user@example.com:8080/");
+        assert!(!display_address(&url).spoof_warning);
+    }
+}
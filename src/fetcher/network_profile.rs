@@ -0,0 +1,125 @@
+use crate::containers::{container_store, ContainerId};
+use crate::cookies::jar::CookieJar;
+use crate::cookies::memory_store::MemoryStorage;
+use crate::cookies::sqlite_store::SqliteStorage;
+use crate::cookies::{cookie_db_path, cookie_policy};
+use crate::fetcher::cache::DiskCache;
+use crate::profile::profile;
+use log::info;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Maximum age of entries in the disk cache
+const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Defines which network profile a tab uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkKind {
+    /// The regular profile
+    Default,
+    /// The ephemeral profile shared by all private tabs
+    Private,
+    /// The profile of the given container
+    Container(ContainerId),
+}
+
+/// A network profile defines the state that is used while fetching resources for a tab, like the
+/// cookie jar and the cache. Tabs that share a profile share this state, while tabs with different
+/// profiles are isolated from each other.
+pub struct NetworkProfile {
+    /// Cookie jar for all requests made within this profile
+    jar: Option<Arc<CookieJar>>,
+    /// Disk cache partition of this profile. Private profiles have no disk cache.
+    cache: Option<DiskCache>,
+    /// Ephemeral profile: nothing is written to disk, and everything is lost when dropped
+    private: bool,
+}
+
+impl NetworkProfile {
+    /// Creates a persistent profile that stores its cookies in the given SQLite database, and its
+    /// cached data in the given cache partition.
+    pub fn persistent(cookie_db_path: &Path, cache_partition: &str) -> Self {
+        let jar = match SqliteStorage::new(cookie_db_path) {
+            Ok(store) => {
+                info!(target: "fetcher", "successfully created SqliteStorage at {}", cookie_db_path.display());
+                Some(Arc::new(CookieJar::new(Arc::new(Mutex::new(store)), cookie_policy())))
+            }
+            Err(e) => {
+                info!(target: "fetcher", "failed to create SqliteStorage: {:?}", e);
+                None
+            }
+        };
+
+        Self {
+            jar,
+            cache: Some(DiskCache::new(&profile().cache_dir, cache_partition, CACHE_MAX_AGE)),
+            private: false,
+        }
+    }
+
+    /// Creates a private profile that keeps everything in memory
+    pub fn private() -> Self {
+        let store = MemoryStorage::new();
+        Self {
+            jar: Some(Arc::new(CookieJar::new(Arc::new(Mutex::new(store)), cookie_policy()))),
+            cache: None,
+            private: true,
+        }
+    }
+
+    pub fn jar(&self) -> Option<Arc<CookieJar>> {
+        self.jar.clone()
+    }
+
+    pub fn cache(&self) -> Option<&DiskCache> {
+        self.cache.as_ref()
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+}
+
+/// Keeps track of the network profiles that are in use by the browser
+#[derive(Default)]
+pub struct NetworkProfiles {
+    profiles: HashMap<NetworkKind, Arc<NetworkProfile>>,
+}
+
+impl NetworkProfiles {
+    /// Returns the profile of the given kind, creating it when needed
+    pub fn get(&mut self, kind: NetworkKind) -> Arc<NetworkProfile> {
+        self.profiles
+            .entry(kind)
+            .or_insert_with(|| {
+                info!(target: "fetcher", "creating network profile {:?}", kind);
+                let profile = match kind {
+                    NetworkKind::Default => NetworkProfile::persistent(&cookie_db_path(), "default"),
+                    NetworkKind::Private => NetworkProfile::private(),
+                    NetworkKind::Container(id) => match container_store().get(id) {
+                        Some(container) => NetworkProfile::persistent(&container.cookie_db_path(), &container.cache_partition()),
+                        // The container has been removed in the meantime, so there is nothing we can persist
+                        None => NetworkProfile::private(),
+                    },
+                };
+                Arc::new(profile)
+            })
+            .clone()
+    }
+
+    /// Throws away the private profile and all its state. Should be called when the last private
+    /// tab has been closed. Fetches that are still running keep their own reference to the profile.
+    pub fn discard_private(&mut self) {
+        if self.profiles.remove(&NetworkKind::Private).is_some() {
+            info!(target: "fetcher", "discarded private network profile");
+        }
+    }
+}
+
+/// Returns the network profiles of the browser
+pub fn network_profiles() -> &'static Mutex<NetworkProfiles> {
+    static PROFILES: OnceLock<Mutex<NetworkProfiles>> = OnceLock::new();
+    PROFILES.get_or_init(|| Mutex::new(NetworkProfiles::default()))
+}
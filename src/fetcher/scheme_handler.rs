@@ -0,0 +1,158 @@
+use crate::fetcher::FetcherError;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use url::Url;
+
+mod calc;
+
+pub use calc::CalcHandler;
+
+/// A response that is generated in-process by a scheme handler
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeResponse {
+    /// Mime type of the body
+    pub content_type: String,
+    /// Generated body
+    pub body: Vec<u8>,
+}
+
+impl SchemeResponse {
+    pub fn html(html: impl Into<String>) -> Self {
+        Self {
+            content_type: "text/html".to_string(),
+            body: html.into().into_bytes(),
+        }
+    }
+}
+
+/// A scheme handler provides built-in functionality for a custom scheme (like `calc:2+2`). Instead of
+/// fetching a resource over the network, the handler generates the response itself.
+pub trait SchemeHandler: Send + Sync {
+    /// Short description of the scheme, as shown on `about:protocols`
+    fn description(&self) -> &str;
+
+    /// Generates the response for the given URL
+    fn handle(&self, url: &Url) -> Result<SchemeResponse, FetcherError>;
+}
+
+/// Registry of all the scheme handlers. The fetcher checks this registry before it falls back to the
+/// protocol fetchers.
+pub struct SchemeHandlers {
+    handlers: RwLock<BTreeMap<String, Arc<dyn SchemeHandler>>>,
+}
+
+impl Default for SchemeHandlers {
+    fn default() -> Self {
+        let handlers = Self::empty();
+        handlers.register("calc", CalcHandler);
+        handlers
+    }
+}
+
+impl SchemeHandlers {
+    /// Creates a registry without any handlers
+    pub fn empty() -> Self {
+        Self {
+            handlers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Registers a handler for the given scheme (without colon). Any existing handler is replaced.
+    pub fn register(&self, scheme: &str, handler: impl SchemeHandler + 'static) {
+        self.handlers
+            .write()
+            .unwrap()
+            .insert(scheme.to_ascii_lowercase(), Arc::new(handler));
+    }
+
+    pub fn unregister(&self, scheme: &str) {
+        self.handlers.write().unwrap().remove(&scheme.to_ascii_lowercase());
+    }
+
+    pub fn get(&self, scheme: &str) -> Option<Arc<dyn SchemeHandler>> {
+        self.handlers.read().unwrap().get(&scheme.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn is_registered(&self, scheme: &str) -> bool {
+        self.handlers.read().unwrap().contains_key(&scheme.to_ascii_lowercase())
+    }
+
+    /// Returns all registered schemes with their description, sorted by scheme
+    pub fn schemes(&self) -> Vec<(String, String)> {
+        self.handlers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(scheme, handler)| (scheme.clone(), handler.description().to_string()))
+            .collect()
+    }
+}
+
+/// Returns the scheme handlers of the browser
+pub fn scheme_handlers() -> &'static SchemeHandlers {
+    static HANDLERS: OnceLock<SchemeHandlers> = OnceLock::new();
+    HANDLERS.get_or_init(SchemeHandlers::default)
+}
+
+/// Returns the path of the URL with all percent-encoded characters decoded
+pub(crate) fn decoded_path(url: &Url) -> String {
+    percent_encoding::percent_decode_str(url.path()).decode_utf8_lossy().to_string()
+}
+
+/// Escapes the given text so it can be safely embedded in HTML
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoHandler;
+
+    impl SchemeHandler for EchoHandler {
+        fn description(&self) -> &str {
+            "Echoes the path"
+        }
+
+        fn handle(&self, url: &Url) -> Result<SchemeResponse, FetcherError> {
+            Ok(SchemeResponse::html(decoded_path(url)))
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let handlers = SchemeHandlers::empty();
+        assert!(!handlers.is_registered("echo"));
+
+        handlers.register("Echo", EchoHandler);
+        assert!(handlers.is_registered("echo"));
+        assert_eq!(handlers.schemes(), vec![("echo".to_string(), "Echoes the path".to_string())]);
+
+        let url = Url::parse("echo:hello%20world").unwrap();
+        let response = handlers.get(url.scheme()).unwrap().handle(&url).unwrap();
+        assert_eq!(response.body, b"hello world");
+
+        handlers.unregister("echo");
+        assert!(handlers.get("echo").is_none());
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<b>\"a\" & 'b'</b>"),
+            "&lt;b&gt;&quot;a&quot; &amp; &#39;b&#39;&lt;/b&gt;"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::fetcher::scheme_handler::{decoded_path, escape_html, SchemeHandler, SchemeResponse};
+use crate::fetcher::FetcherError;
+use std::iter::Peekable;
+use std::str::Chars;
+use thiserror::Error;
+use url::Url;
+
+/// Maximum nesting of parentheses and unary operators. Deeper expressions would overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Handles `calc:` URLs, like `calc:(1+2)*3`, by evaluating the expression and showing the result
+pub struct CalcHandler;
+
+impl SchemeHandler for CalcHandler {
+    fn description(&self) -> &str {
+        "Calculator (for example calc:(1+2)*3)"
+    }
+
+    fn handle(&self, url: &Url) -> Result<SchemeResponse, FetcherError> {
+        let expression = decoded_path(url);
+        let result = match evaluate(&expression) {
+            Ok(value) => format!("<p><b>{}</b></p>", format_number(value)),
+            Err(e) => format!("<p>Cannot calculate: {}</p>", escape_html(&e.to_string())),
+        };
+
+        Ok(SchemeResponse::html(format!(
+            r#"
+            <html>
+                <head>
+                    <title>calc: {0}</title>
+                </head>
+                <body>
+                    <h1>{0}</h1>
+                    {1}
+                </body>
+            </html>
+            "#,
+            escape_html(&expression),
+            result
+        )))
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CalcError {
+    #[error("unexpected character '{0}'")]
+    UnexpectedCharacter(char),
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("invalid number: {0}")]
+    InvalidNumber(String),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("expression is nested too deeply")]
+    TooDeep,
+}
+
+/// Evaluates an arithmetic expression with `+ - * / % ^`, parentheses and unary minus
+pub fn evaluate(expression: &str) -> Result<f64, CalcError> {
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+        depth: 0,
+    };
+
+    let value = parser.expression()?;
+    match parser.next_token() {
+        None => Ok(value),
+        Some(c) => Err(CalcError::UnexpectedCharacter(c)),
+    }
+}
+
+/// Formats the number without a fraction when it is a whole number
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+/// Recursive descent parser that evaluates while parsing
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Current nesting of `unary`, which every recursion goes through
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Returns the next non-whitespace character without consuming it
+    fn peek_token(&mut self) -> Option<char> {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn next_token(&mut self) -> Option<char> {
+        self.peek_token()?;
+        self.chars.next()
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.term()?;
+        while let Some(op) = self.peek_token() {
+            match op {
+                '+' => {
+                    self.chars.next();
+                    value += self.term()?;
+                }
+                '-' => {
+                    self.chars.next();
+                    value -= self.term()?;
+                }
+                _ => break,
+            }
+        }
+        Ok(value)
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.peek_token() {
+            match op {
+                '*' | 'x' | '×' => {
+                    self.chars.next();
+                    value *= self.unary()?;
+                }
+                '/' | '÷' | '%' => {
+                    self.chars.next();
+                    let rhs = self.unary()?;
+                    if rhs == 0.0 {
+                        return Err(CalcError::DivisionByZero);
+                    }
+                    value = if op == '%' { value % rhs } else { value / rhs };
+                }
+                _ => break,
+            }
+        }
+        Ok(value)
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, CalcError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(CalcError::TooDeep);
+        }
+
+        self.depth += 1;
+        let value = self.unary_operand();
+        self.depth -= 1;
+        value
+    }
+
+    fn unary_operand(&mut self) -> Result<f64, CalcError> {
+        match self.peek_token() {
+            Some('-') => {
+                self.chars.next();
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.chars.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// power := primary ('^' unary)?
+    fn power(&mut self) -> Result<f64, CalcError> {
+        let base = self.primary()?;
+        if self.peek_token() == Some('^') {
+            self.chars.next();
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | '(' expression ')'
+    fn primary(&mut self) -> Result<f64, CalcError> {
+        match self.peek_token() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.expression()?;
+                match self.next_token() {
+                    Some(')') => Ok(value),
+                    Some(c) => Err(CalcError::UnexpectedCharacter(c)),
+                    None => Err(CalcError::UnexpectedEnd),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_digit() && c != '.' {
+                        break;
+                    }
+                    number.push(c);
+                    self.chars.next();
+                }
+                number.parse().map_err(|_| CalcError::InvalidNumber(number))
+            }
+            Some(c) => Err(CalcError::UnexpectedCharacter(c)),
+            None => Err(CalcError::UnexpectedEnd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("2+2"), Ok(4.0));
+        assert_eq!(evaluate("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(evaluate("2 + 3 * 4"), Ok(14.0));
+        assert_eq!(evaluate("10 / 4"), Ok(2.5));
+        assert_eq!(evaluate("10 % 4"), Ok(2.0));
+        assert_eq!(evaluate("-2^2"), Ok(-4.0));
+        assert_eq!(evaluate("2^3^2"), Ok(512.0));
+        assert_eq!(evaluate("2^-1"), Ok(0.5));
+        assert_eq!(evaluate("--3"), Ok(3.0));
+
+        assert_eq!(evaluate("1/0"), Err(CalcError::DivisionByZero));
+        assert_eq!(evaluate("(1+2"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(evaluate("1+"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(evaluate("2 2"), Err(CalcError::UnexpectedCharacter('2')));
+        assert_eq!(evaluate("1.2.3"), Err(CalcError::InvalidNumber("1.2.3".into())));
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let nested = format!("{}1{}", "(".repeat(50_000), ")".repeat(50_000));
+        assert_eq!(evaluate(&nested), Err(CalcError::TooDeep));
+        assert_eq!(evaluate(&"-".repeat(100_000)), Err(CalcError::TooDeep));
+        assert_eq!(evaluate(&format!("{}2{}", "(".repeat(100), ")".repeat(100))), Ok(2.0));
+    }
+
+    #[test]
+    fn test_handle() {
+        let url = Url::parse("calc:(1%2B2)*3").unwrap();
+        let response = CalcHandler.handle(&url).unwrap();
+        let html = String::from_utf8(response.body).unwrap();
+        assert!(html.contains("<h1>(1+2)*3</h1>"));
+        assert!(html.contains("<b>9</b>"));
+
+        assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+    }
+}
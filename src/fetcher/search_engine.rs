@@ -0,0 +1,423 @@
+use crate::profile::profile;
+use log::warn;
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use url::Url;
+
+/// Name of the database that holds the configured search engines
+const SEARCH_ENGINES_DB_FILE: &str = "search_engines.db";
+
+/// Placeholder in a search URL template that is replaced by the (encoded) search terms
+const SEARCH_TERMS_PLACEHOLDER: &str = "{searchTerms}";
+
+/// A search engine is defined by a URL template in which `{searchTerms}` is replaced by the query.
+/// This is the same template syntax as used in OpenSearch description documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchEngine {
+    /// Name of the search engine
+    pub name: String,
+    /// Optional keyword: typing `<keyword> <query>` searches with this engine
+    pub keyword: Option<String>,
+    /// URL template of the search results page
+    pub url_template: String,
+    /// Optional URL template that returns search suggestions (OpenSearch suggestions JSON)
+    pub suggest_template: Option<String>,
+}
+
+impl SearchEngine {
+    pub fn new(name: &str, keyword: Option<&str>, url_template: &str, suggest_template: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            keyword: keyword.map(|k| k.to_string()),
+            url_template: url_template.to_string(),
+            suggest_template: suggest_template.map(|s| s.to_string()),
+        }
+    }
+
+    /// Returns the URL of the search results page for the given query
+    pub fn search_url(&self, query: &str) -> anyhow::Result<Url> {
+        Ok(Url::parse(&fill_template(&self.url_template, query))?)
+    }
+
+    /// Returns the URL that returns search suggestions for the given query, if supported
+    pub fn suggest_url(&self, query: &str) -> Option<Url> {
+        let template = self.suggest_template.as_ref()?;
+        Url::parse(&fill_template(template, query)).ok()
+    }
+}
+
+/// Replaces the search terms placeholder in the template with the url-encoded query
+fn fill_template(template: &str, query: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    template.replace(SEARCH_TERMS_PLACEHOLDER, &encoded)
+}
+
+/// The set of known search engines, together with the engine that is used by default
+#[derive(Debug, Clone)]
+pub struct SearchEngines {
+    engines: Vec<SearchEngine>,
+    default: usize,
+}
+
+impl Default for SearchEngines {
+    fn default() -> Self {
+        Self {
+            engines: vec![
+                SearchEngine::new(
+                    "DuckDuckGo",
+                    Some("d"),
+                    "https://duckduckgo.com/?q={searchTerms}",
+                    Some("https://duckduckgo.com/ac/?type=list&q={searchTerms}"),
+                ),
+                SearchEngine::new(
+                    "Google",
+                    Some("g"),
+                    "https://www.google.com/search?q={searchTerms}",
+                    Some("https://suggestqueries.google.com/complete/search?client=firefox&q={searchTerms}"),
+                ),
+                SearchEngine::new(
+                    "Wikipedia",
+                    Some("w"),
+                    "https://en.wikipedia.org/wiki/Special:Search?search={searchTerms}",
+                    Some("https://en.wikipedia.org/w/api.php?action=opensearch&search={searchTerms}"),
+                ),
+                SearchEngine::new("crates.io", Some("crates"), "https://crates.io/search?q={searchTerms}", None),
+                SearchEngine::new("docs.rs", Some("docs"), "https://docs.rs/releases/search?query={searchTerms}", None),
+            ],
+            default: 0,
+        }
+    }
+}
+
+impl SearchEngines {
+    pub fn new(engines: Vec<SearchEngine>, default: usize) -> Self {
+        let default = if default < engines.len() { default } else { 0 };
+        Self { engines, default }
+    }
+
+    pub fn engines(&self) -> &[SearchEngine] {
+        &self.engines
+    }
+
+    /// Returns the default search engine, if there are any engines at all
+    pub fn default_engine(&self) -> Option<&SearchEngine> {
+        self.engines.get(self.default)
+    }
+
+    pub fn default_index(&self) -> usize {
+        self.default
+    }
+
+    /// Adds a search engine. Any engine with the same keyword loses its keyword.
+    pub fn add(&mut self, engine: SearchEngine) {
+        if let Some(keyword) = &engine.keyword {
+            for existing in self.engines.iter_mut() {
+                if existing.keyword.as_ref() == Some(keyword) {
+                    existing.keyword = None;
+                }
+            }
+        }
+        self.engines.push(engine);
+    }
+
+    /// Removes the search engine at the given index. When the default engine is removed, the first
+    /// engine becomes the default.
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.engines.len() {
+            return;
+        }
+        self.engines.remove(index);
+        if index == self.default {
+            self.default = 0;
+        } else if index < self.default {
+            self.default -= 1;
+        }
+    }
+
+    pub fn set_default(&mut self, index: usize) {
+        if index < self.engines.len() {
+            self.default = index;
+        }
+    }
+
+    /// Returns the search engine with the given keyword
+    pub fn by_keyword(&self, keyword: &str) -> Option<&SearchEngine> {
+        self.engines.iter().find(|e| e.keyword.as_deref() == Some(keyword))
+    }
+
+    /// Resolves a search query. Queries that start with a keyword (`w rust`) are sent to the engine
+    /// with that keyword, all other queries are sent to the default engine.
+    pub fn resolve(&self, query: &str) -> anyhow::Result<Url> {
+        let query = query.trim();
+
+        if let Some((keyword, rest)) = query.split_once(char::is_whitespace) {
+            if let Some(engine) = self.by_keyword(keyword) {
+                return engine.search_url(rest.trim());
+            }
+        }
+
+        match self.default_engine() {
+            Some(engine) => engine.search_url(query),
+            None => Err(anyhow::anyhow!("No search engine configured")),
+        }
+    }
+
+    /// Returns true when the given input starts with a known search keyword followed by a query
+    pub fn is_keyword_search(&self, input: &str) -> bool {
+        match input.trim().split_once(char::is_whitespace) {
+            Some((keyword, rest)) => !rest.trim().is_empty() && self.by_keyword(keyword).is_some(),
+            None => false,
+        }
+    }
+}
+
+/// Parses an OpenSearch description document into a search engine
+pub fn parse_opensearch(xml: &str) -> anyhow::Result<SearchEngine> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "OpenSearchDescription" {
+        return Err(anyhow::anyhow!("Not an OpenSearch description document"));
+    }
+
+    let child_text = |name: &str| {
+        root.children()
+            .find(|n| n.is_element() && n.tag_name().name() == name)
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_string())
+    };
+
+    let url_template = |mime: &str| {
+        root.children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "Url")
+            .find(|n| n.attribute("type") == Some(mime))
+            .and_then(|n| n.attribute("template"))
+            .map(|t| t.to_string())
+    };
+
+    let name = child_text("ShortName").ok_or_else(|| anyhow::anyhow!("OpenSearch document has no ShortName"))?;
+    let template = url_template("text/html").ok_or_else(|| anyhow::anyhow!("OpenSearch document has no text/html Url"))?;
+    let suggest = url_template("application/x-suggestions+json");
+
+    Ok(SearchEngine {
+        name,
+        keyword: None,
+        url_template: template,
+        suggest_template: suggest,
+    })
+}
+
+/// Stores the configured search engines in a SQLite database
+pub struct SearchEngineStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl SearchEngineStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS search_engines (
+                position INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                keyword TEXT,
+                url_template TEXT NOT NULL,
+                suggest_template TEXT,
+                is_default INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        let store = Self { conn: Mutex::new(conn) };
+
+        let count: i64 = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM search_engines", [], |row| row.get(0))?;
+        if count == 0 {
+            store.save(&SearchEngines::default());
+        }
+
+        Ok(store)
+    }
+
+    /// Loads the search engines from the database
+    pub fn load(&self) -> SearchEngines {
+        let locked_conn = self.conn.lock().unwrap();
+
+        let stmt =
+            locked_conn.prepare("SELECT name, keyword, url_template, suggest_template, is_default FROM search_engines ORDER BY position");
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return SearchEngines::default();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let rows = stmt.query_map([], |row| {
+            let engine = SearchEngine {
+                name: row.get(0)?,
+                keyword: row.get(1)?,
+                url_template: row.get(2)?,
+                suggest_template: row.get(3)?,
+            };
+            let is_default: bool = row.get(4)?;
+            Ok((engine, is_default))
+        });
+
+        let Ok(rows) = rows else {
+            return SearchEngines::default();
+        };
+
+        let mut engines = Vec::new();
+        let mut default = 0;
+        for (engine, is_default) in rows.filter_map(|row| row.ok()) {
+            if is_default {
+                default = engines.len();
+            }
+            engines.push(engine);
+        }
+
+        SearchEngines::new(engines, default)
+    }
+
+    /// Replaces all stored search engines with the given engines
+    pub fn save(&self, engines: &SearchEngines) {
+        let mut conn = self.conn.lock().unwrap();
+
+        let result = (|| -> Result<(), rusqlite::Error> {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM search_engines", [])?;
+            for (position, engine) in engines.engines().iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO search_engines (position, name, keyword, url_template, suggest_template, is_default)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        position as i64,
+                        engine.name,
+                        engine.keyword,
+                        engine.url_template,
+                        engine.suggest_template,
+                        position == engines.default_index()
+                    ],
+                )?;
+            }
+            tx.commit()
+        })();
+
+        if let Err(e) = result {
+            warn!("failed to store search engines: {:?}", e);
+        }
+    }
+}
+
+/// Path of the database that holds the configured search engines of the current profile
+pub fn search_engines_db_path() -> PathBuf {
+    profile().data_path(SEARCH_ENGINES_DB_FILE)
+}
+
+fn store() -> &'static SearchEngineStore {
+    static STORE: OnceLock<SearchEngineStore> = OnceLock::new();
+    STORE.get_or_init(|| {
+        SearchEngineStore::new(search_engines_db_path()).unwrap_or_else(|e| {
+            warn!("failed to open search engine store, using in-memory store: {:?}", e);
+            SearchEngineStore::new(":memory:").expect("in-memory search engine store")
+        })
+    })
+}
+
+/// Returns the search engines that are configured in the browser
+pub fn search_engines() -> Arc<SearchEngines> {
+    cached_engines().lock().unwrap().clone()
+}
+
+/// Replaces the configured search engines, and stores them on disk
+pub fn set_search_engines(engines: SearchEngines) {
+    store().save(&engines);
+    *cached_engines().lock().unwrap() = Arc::new(engines);
+}
+
+fn cached_engines() -> &'static Mutex<Arc<SearchEngines>> {
+    static ENGINES: OnceLock<Mutex<Arc<SearchEngines>>> = OnceLock::new();
+    ENGINES.get_or_init(|| Mutex::new(Arc::new(store().load())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let engines = SearchEngines::default();
+
+        let url = engines.resolve("rust gtk tutorial").unwrap();
+        assert_eq!(url.as_str(), "https://duckduckgo.com/?q=rust+gtk+tutorial");
+
+        let url = engines.resolve("w rust (programming language)").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://en.wikipedia.org/wiki/Special:Search?search=rust+%28programming+language%29"
+        );
+
+        // Unknown keywords are part of the query
+        let url = engines.resolve("x rust").unwrap();
+        assert_eq!(url.as_str(), "https://duckduckgo.com/?q=x+rust");
+
+        assert!(engines.is_keyword_search("w rust"));
+        assert!(!engines.is_keyword_search("w"));
+        assert!(!engines.is_keyword_search("rust lang"));
+    }
+
+    #[test]
+    fn test_opensearch() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+                <ShortName>Example</ShortName>
+                <Description>Example search</Description>
+                <Url type="application/x-suggestions+json" template="https://example.com/suggest?q={searchTerms}"/>
+                <Url type="text/html" method="get" template="https://example.com/search?q={searchTerms}"/>
+            </OpenSearchDescription>"#;
+
+        let engine = parse_opensearch(xml).unwrap();
+        assert_eq!(engine.name, "Example");
+        assert_eq!(engine.url_template, "https://example.com/search?q={searchTerms}");
+        assert_eq!(engine.suggest_url("a b").unwrap().as_str(), "https://example.com/suggest?q=a+b");
+
+        assert!(parse_opensearch("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut engines = SearchEngines::default();
+        engines.set_default(2);
+        engines.remove(0);
+        assert_eq!(engines.default_engine().unwrap().name, "Wikipedia");
+
+        engines.remove(1);
+        assert_eq!(engines.default_engine().unwrap().name, "Google");
+
+        engines.add(SearchEngine::new("Other", Some("g"), "https://example.com/?q={searchTerms}", None));
+        assert_eq!(engines.by_keyword("g").unwrap().name, "Other");
+    }
+
+    #[test]
+    fn test_store() {
+        let store = SearchEngineStore::new(":memory:").unwrap();
+        let engines = store.load();
+        assert_eq!(engines.engines(), SearchEngines::default().engines());
+
+        let mut list = engines.engines().to_vec();
+        list.push(SearchEngine::new(
+            "Example",
+            Some("ex"),
+            "https://example.com/?q={searchTerms}",
+            None,
+        ));
+        store.save(&SearchEngines::new(list, 2));
+
+        let engines = store.load();
+        assert_eq!(engines.engines().len(), 6);
+        assert_eq!(engines.default_engine().unwrap().name, "Wikipedia");
+        assert_eq!(engines.by_keyword("ex").unwrap().name, "Example");
+    }
+}
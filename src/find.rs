@@ -0,0 +1,217 @@
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// Options of a search in the page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    pub match_case: bool,
+    pub whole_word: bool,
+    /// The query is a regular expression instead of plain text
+    pub regex: bool,
+}
+
+/// Builds the regular expression that finds the query with the given options
+pub fn find_regex(query: &str, options: FindOptions) -> Result<Regex, regex::Error> {
+    let mut pattern = match options.regex {
+        true => query.to_string(),
+        false => regex::escape(query),
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern).case_insensitive(!options.match_case).build()
+}
+
+/// Returns the byte ranges of the matches in the text. Empty matches (like `^` or `a*`) are skipped,
+/// as there is nothing to highlight.
+pub fn find_matches(regex: &Regex, text: &str) -> Vec<Range<usize>> {
+    regex.find_iter(text).filter(|m| !m.is_empty()).map(|m| m.range()).collect()
+}
+
+/// A piece of text on the rendered page, with the box it is drawn in
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The box of a match on the rendered page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Finds the matches in the text of the rendered page, in the order the text appears. Matches cannot
+/// span several runs. The engine does not tell where each character is drawn, so the box of a match
+/// is estimated from its position in the run.
+pub fn find_in_runs(runs: &[TextRun], regex: &Regex) -> Vec<MatchRect> {
+    let mut rects = Vec::new();
+
+    for run in runs {
+        let chars = run.text.chars().count() as f64;
+        if chars == 0.0 {
+            continue;
+        }
+
+        for range in find_matches(regex, &run.text) {
+            let start = run.text[..range.start].chars().count() as f64;
+            let len = run.text[range].chars().count() as f64;
+
+            rects.push(MatchRect {
+                x: run.x + run.width * start / chars,
+                y: run.y,
+                width: run.width * len / chars,
+                height: run.height,
+            });
+        }
+    }
+
+    rects
+}
+
+/// The matches on a rendered page, and the match that is currently selected
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMatches {
+    pub matches: Vec<MatchRect>,
+    pub current: Option<usize>,
+}
+
+impl PageMatches {
+    /// Creates the matches, with the first match selected
+    pub fn new(matches: Vec<MatchRect>) -> Self {
+        let current = (!matches.is_empty()).then_some(0);
+        Self { matches, current }
+    }
+
+    /// Selects the next or previous match. The selection wraps around at both ends.
+    pub fn step(&mut self, forward: bool) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+
+        self.current = Some(match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+        });
+    }
+
+    pub fn current_match(&self) -> Option<&MatchRect> {
+        self.matches.get(self.current?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(query: &str, options: FindOptions, text: &str) -> Vec<Range<usize>> {
+        find_matches(&find_regex(query, options).unwrap(), text)
+    }
+
+    #[test]
+    fn test_find_options() {
+        let text = "Gosub is a browser. The gosub engine renders gosubs.";
+
+        assert_eq!(ranges("gosub", FindOptions::default(), text), vec![0..5, 24..29, 45..50]);
+
+        let match_case = FindOptions {
+            match_case: true,
+            ..FindOptions::default()
+        };
+        assert_eq!(ranges("gosub", match_case, text), vec![24..29, 45..50]);
+
+        let whole_word = FindOptions {
+            whole_word: true,
+            ..FindOptions::default()
+        };
+        assert_eq!(ranges("gosub", whole_word, text), vec![0..5, 24..29]);
+
+        // Plain text is not interpreted as a regular expression
+        assert!(ranges("a.", FindOptions::default(), text).is_empty());
+        assert_eq!(ranges("browser.", FindOptions::default(), text), vec![11..19]);
+
+        let regex = FindOptions {
+            regex: true,
+            ..FindOptions::default()
+        };
+        assert_eq!(ranges("r[se]", regex, text), vec![37..39, 42..44]);
+        assert!(find_regex("(unclosed", regex).is_err());
+
+        // Empty matches have nothing to highlight
+        assert!(ranges("x*", regex, text).is_empty());
+    }
+
+    #[test]
+    fn test_find_in_runs() {
+        let runs = vec![
+            TextRun {
+                text: "find me".to_string(),
+                x: 10.0,
+                y: 20.0,
+                width: 70.0,
+                height: 16.0,
+            },
+            TextRun {
+                text: "ëëë me".to_string(),
+                x: 0.0,
+                y: 40.0,
+                width: 60.0,
+                height: 16.0,
+            },
+        ];
+
+        let rects = find_in_runs(&runs, &find_regex("me", FindOptions::default()).unwrap());
+        assert_eq!(
+            rects,
+            vec![
+                MatchRect {
+                    x: 60.0,
+                    y: 20.0,
+                    width: 20.0,
+                    height: 16.0
+                },
+                // Positions are estimated from characters, not bytes
+                MatchRect {
+                    x: 40.0,
+                    y: 40.0,
+                    width: 20.0,
+                    height: 16.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_page_matches_step() {
+        let rect = MatchRect {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        };
+
+        let mut matches = PageMatches::new(vec![rect; 3]);
+        assert_eq!(matches.current, Some(0));
+        matches.step(false);
+        assert_eq!(matches.current, Some(2));
+        matches.step(true);
+        assert_eq!(matches.current, Some(0));
+        matches.step(true);
+        assert_eq!(matches.current, Some(1));
+
+        let mut empty = PageMatches::new(Vec::new());
+        empty.step(true);
+        assert_eq!(empty.current, None);
+        assert!(empty.current_match().is_none());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::completion::{CompletionCandidate, CompletionKind, CompletionProvider};
+use crate::history::sqlite_store::HistoryStore;
+use crate::profile::profile;
+use chrono::{DateTime, Datelike, Local, Utc};
+use log::warn;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use url::Url;
+
+pub mod sqlite_store;
+
+/// Name of the database that holds the browsing history
+const HISTORY_DB_FILE: &str = "history.db";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VisitId(i64);
+
+/// How the user got to a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// The URL was typed in the address bar, or picked from the suggestions or bookmarks
+    Typed,
+    /// A link was followed, or the page was opened in a new tab
+    Link,
+    /// The page was reloaded
+    Reload,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Typed => write!(f, "typed"),
+            Transition::Link => write!(f, "link"),
+            Transition::Reload => write!(f, "reload"),
+        }
+    }
+}
+
+impl FromStr for Transition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typed" => Ok(Transition::Typed),
+            "link" => Ok(Transition::Link),
+            "reload" => Ok(Transition::Reload),
+            _ => Err(anyhow::anyhow!("unknown transition: {}", s)),
+        }
+    }
+}
+
+/// A single visit of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub id: VisitId,
+    pub url: Url,
+    pub title: String,
+    pub transition: Transition,
+    pub visited_at: DateTime<Utc>,
+}
+
+/// A page in the history, with the statistics of all its visits
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisitedPage {
+    pub url: Url,
+    pub title: String,
+    /// Number of visits, not counting reloads
+    pub visit_count: u32,
+    pub last_visit: Option<DateTime<Utc>>,
+}
+
+/// Groups in which the history is shown, from new to old
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateGroup {
+    Today,
+    Yesterday,
+    LastSevenDays,
+    ThisMonth,
+    Older,
+}
+
+impl DateGroup {
+    /// Returns the group of a visit at the given time. Days are calendar days in local time.
+    pub fn for_date(visited_at: DateTime<Local>, now: DateTime<Local>) -> DateGroup {
+        let days = (now.date_naive() - visited_at.date_naive()).num_days();
+        match days {
+            ..=0 => DateGroup::Today,
+            1 => DateGroup::Yesterday,
+            2..=6 => DateGroup::LastSevenDays,
+            _ if visited_at.year() == now.year() && visited_at.month() == now.month() => DateGroup::ThisMonth,
+            _ => DateGroup::Older,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateGroup::Today => "Today",
+            DateGroup::Yesterday => "Yesterday",
+            DateGroup::LastSevenDays => "Last 7 days",
+            DateGroup::ThisMonth => "This month",
+            DateGroup::Older => "Older",
+        }
+    }
+}
+
+/// Path of the database that holds the browsing history of the current profile
+pub fn history_db_path() -> PathBuf {
+    profile().data_path(HISTORY_DB_FILE)
+}
+
+/// Returns the history store of the browser
+pub fn history_store() -> Arc<HistoryStore> {
+    static STORE: OnceLock<Arc<HistoryStore>> = OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            let store = HistoryStore::new(history_db_path()).unwrap_or_else(|e| {
+                warn!("failed to open history store, using in-memory store: {:?}", e);
+                HistoryStore::new(":memory:").expect("in-memory history store")
+            });
+            Arc::new(store)
+        })
+        .clone()
+}
+
+/// Suggests previously visited pages in the address bar
+pub struct HistoryCompletionProvider;
+
+impl CompletionProvider for HistoryCompletionProvider {
+    fn candidates(&self, query: &str, limit: usize) -> Vec<CompletionCandidate> {
+        history_store()
+            .search(query, limit)
+            .into_iter()
+            .map(|page| CompletionCandidate {
+                url: page.url.to_string(),
+                title: page.title,
+                kind: CompletionKind::History,
+                visit_count: page.visit_count,
+                last_visit: page.last_visit,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_date_group() {
+        let now = Local.with_ymd_and_hms(2024, 5, 20, 10, 0, 0).unwrap();
+        let at = |month, day, hour| Local.with_ymd_and_hms(2024, month, day, hour, 0, 0).unwrap();
+
+        assert_eq!(DateGroup::for_date(at(5, 20, 0), now), DateGroup::Today);
+        assert_eq!(DateGroup::for_date(at(5, 19, 23), now), DateGroup::Yesterday);
+        assert_eq!(DateGroup::for_date(at(5, 14, 12), now), DateGroup::LastSevenDays);
+        assert_eq!(DateGroup::for_date(at(5, 2, 12), now), DateGroup::ThisMonth);
+        assert_eq!(DateGroup::for_date(at(4, 30, 12), now), DateGroup::Older);
+    }
+
+    #[test]
+    fn test_transition() {
+        for transition in [Transition::Typed, Transition::Link, Transition::Reload] {
+            assert_eq!(transition.to_string().parse::<Transition>().unwrap(), transition);
+        }
+        assert!("redirect".parse::<Transition>().is_err());
+    }
+}
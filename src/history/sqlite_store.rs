@@ -0,0 +1,381 @@
+use crate::completion::frecency;
+use crate::history::{Transition, Visit, VisitId, VisitedPage};
+use chrono::{DateTime, Utc};
+use log::warn;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use std::path::Path;
+use std::sync::Mutex;
+use url::Url;
+
+/// Number of matching pages that are ranked by frecency when searching. The pages with the most visits
+/// are ranked, so pages that have been visited only once or twice may be left out on large histories.
+const SEARCH_CANDIDATES: usize = 1000;
+
+/// Stores the browsing history in a SQLite database. Every visited URL is stored once as a place,
+/// with a row for every visit. The visit count and last visit of a place are kept up to date whenever
+/// its visits change. The frecency decays over time, so it is computed when searching.
+pub struct HistoryStore {
+    /// Connection. Should be guarded through a mutex, as it can be used multi-threaded
+    conn: Mutex<Connection>,
+}
+
+impl HistoryStore {
+    pub fn new(database_path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(database_path)?;
+        // Removing a place removes its visits
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS places (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                host TEXT NOT NULL,
+                title TEXT NOT NULL,
+                visit_count INTEGER NOT NULL DEFAULT 0,
+                last_visit INTEGER
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS visits (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                place_id INTEGER NOT NULL REFERENCES places (id) ON DELETE CASCADE,
+                transition TEXT NOT NULL,
+                visited_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_visits_place ON visits (place_id, visited_at)", [])?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_visits_date ON visits (visited_at)", [])?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_places_host ON places (host)", [])?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Records a visit of the given page. An empty title keeps the title of earlier visits.
+    pub fn add_visit(&self, url: &Url, title: &str, transition: Transition, visited_at: DateTime<Utc>) -> Option<VisitId> {
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| -> Result<VisitId, rusqlite::Error> {
+            let tx = conn.transaction()?;
+
+            tx.execute(
+                "INSERT INTO places (url, host, title) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (url) DO UPDATE SET title = excluded.title WHERE excluded.title != ''",
+                params![url.to_string(), url.host_str().unwrap_or_default(), title],
+            )?;
+            let place_id: i64 = tx.query_row("SELECT id FROM places WHERE url = ?1", params![url.to_string()], |row| row.get(0))?;
+
+            tx.execute(
+                "INSERT INTO visits (place_id, transition, visited_at) VALUES (?1, ?2, ?3)",
+                params![place_id, transition.to_string(), visited_at.timestamp()],
+            )?;
+            let id = VisitId(tx.last_insert_rowid());
+
+            update_place(&tx, place_id)?;
+            tx.commit()?;
+            Ok(id)
+        })();
+
+        match result {
+            Ok(id) => Some(id),
+            Err(e) => {
+                warn!("failed to store visit: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Returns the visits where every word of the query is found in the title or URL, the most recent
+    /// visit first. An empty query returns the most recent visits.
+    pub fn visits(&self, query: &str, limit: usize) -> Vec<Visit> {
+        let words: Vec<String> = query.split_whitespace().map(like_pattern).collect();
+        let mut condition = word_conditions(words.len());
+        if condition.is_empty() {
+            condition = "1".to_string();
+        }
+
+        let locked_conn = self.conn.lock().unwrap();
+        let stmt = locked_conn.prepare(&format!(
+            "SELECT visits.id, places.url, places.title, visits.transition, visits.visited_at
+             FROM visits JOIN places ON places.id = visits.place_id
+             WHERE {} ORDER BY visits.visited_at DESC, visits.id DESC LIMIT {}",
+            condition, limit
+        ));
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let Ok(rows) = stmt.query_map(params_from_iter(words), row_to_visit) else {
+            return Vec::new();
+        };
+
+        rows.filter_map(|row| row.ok()).flatten().collect()
+    }
+
+    /// Returns the pages where every word of the query is found in the title or URL, the page with the
+    /// highest frecency first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<VisitedPage> {
+        let words: Vec<String> = query.split_whitespace().map(like_pattern).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let locked_conn = self.conn.lock().unwrap();
+        let stmt = locked_conn.prepare(&format!(
+            "SELECT url, title, visit_count, last_visit FROM places
+             WHERE {} ORDER BY visit_count DESC, last_visit DESC LIMIT {}",
+            word_conditions(words.len()),
+            SEARCH_CANDIDATES
+        ));
+        if let Err(e) = stmt {
+            warn!("failed to prepare statement: {:?}", e);
+            return Vec::new();
+        }
+
+        let mut stmt = stmt.unwrap();
+        let Ok(rows) = stmt.query_map(params_from_iter(words), row_to_page) else {
+            return Vec::new();
+        };
+
+        let now = Utc::now();
+        let mut pages: Vec<(f64, VisitedPage)> = rows
+            .filter_map(|row| row.ok())
+            .flatten()
+            .map(|page| (frecency(page.visit_count, page.last_visit, now), page))
+            .collect();
+        pages.sort_by(|(a, a_page), (b, b_page)| b.total_cmp(a).then(b_page.last_visit.cmp(&a_page.last_visit)));
+        pages.into_iter().take(limit).map(|(_, page)| page).collect()
+    }
+
+    /// Removes a single visit
+    pub fn remove_visit(&self, id: VisitId) {
+        self.remove_visits("visits.id = ?1", params![id.0]);
+    }
+
+    /// Removes all visits since the given time
+    pub fn remove_since(&self, since: DateTime<Utc>) {
+        self.remove_visits("visits.visited_at >= ?1", params![since.timestamp()]);
+    }
+
+    /// Removes all pages of the given host and its subdomains ("forget this site")
+    pub fn remove_site(&self, host: &str) {
+        let e = self.conn.lock().unwrap().execute(
+            "DELETE FROM places WHERE host = ?1 OR host LIKE ?2 ESCAPE '\\'",
+            params![host, format!("%.{}", escape_like(host))],
+        );
+        if let Err(e) = e {
+            warn!("failed to remove site from history: {:?}", e);
+        }
+    }
+
+    /// Removes the visits that match the given SQL condition, and updates the places they belonged to
+    fn remove_visits(&self, condition: &str, params: impl rusqlite::Params + Clone) {
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| -> Result<(), rusqlite::Error> {
+            let tx = conn.transaction()?;
+
+            let place_ids = {
+                let mut stmt = tx.prepare(&format!("SELECT DISTINCT place_id FROM visits WHERE {}", condition))?;
+                let place_ids = stmt
+                    .query_map(params.clone(), |row| row.get::<_, i64>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                place_ids
+            };
+
+            tx.execute(&format!("DELETE FROM visits WHERE {}", condition), params)?;
+            for place_id in place_ids {
+                update_place(&tx, place_id)?;
+            }
+
+            tx.commit()
+        })();
+        if let Err(e) = result {
+            warn!("failed to remove visits: {:?}", e);
+        }
+    }
+}
+
+/// Recomputes the visit count and last visit of a place. Places without any visits left are removed.
+fn update_place(conn: &Connection, place_id: i64) -> Result<(), rusqlite::Error> {
+    let (visit_count, last_visit): (i64, Option<i64>) = conn.query_row(
+        "SELECT COUNT(*) FILTER (WHERE transition != ?2), MAX(visited_at) FROM visits WHERE place_id = ?1",
+        params![place_id, Transition::Reload.to_string()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let Some(last_visit) = last_visit else {
+        conn.execute("DELETE FROM places WHERE id = ?1", params![place_id])?;
+        return Ok(());
+    };
+
+    conn.execute(
+        "UPDATE places SET visit_count = ?1, last_visit = ?2 WHERE id = ?3",
+        params![visit_count, last_visit, place_id],
+    )?;
+
+    Ok(())
+}
+
+fn row_to_visit(row: &Row) -> Result<Option<Visit>, rusqlite::Error> {
+    let url: String = row.get(1)?;
+    let transition: String = row.get(3)?;
+
+    let Ok(url) = Url::parse(&url) else {
+        return Ok(None);
+    };
+    Ok(Some(Visit {
+        id: VisitId(row.get(0)?),
+        url,
+        title: row.get(2)?,
+        transition: transition.parse().unwrap_or(Transition::Link),
+        visited_at: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+    }))
+}
+
+fn row_to_page(row: &Row) -> Result<Option<VisitedPage>, rusqlite::Error> {
+    let url: String = row.get(0)?;
+    let last_visit: Option<i64> = row.get(3)?;
+
+    let Ok(url) = Url::parse(&url) else {
+        return Ok(None);
+    };
+    Ok(Some(VisitedPage {
+        url,
+        title: row.get(1)?,
+        visit_count: row.get(2)?,
+        last_visit: last_visit.and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+    }))
+}
+
+/// Returns a condition that matches every numbered parameter against the title or URL of a place
+fn word_conditions(count: usize) -> String {
+    (1..=count)
+        .map(|i| format!("(places.title LIKE ?{i} ESCAPE '\\' OR places.url LIKE ?{i} ESCAPE '\\')"))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Returns a LIKE pattern that matches the given text anywhere
+fn like_pattern(text: &str) -> String {
+    format!("%{}%", escape_like(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn urls(visits: &[Visit]) -> Vec<&str> {
+        visits.iter().map(|visit| visit.url.as_str()).collect()
+    }
+
+    #[test]
+    fn test_visits() {
+        let store = HistoryStore::new(":memory:").unwrap();
+        let now = Utc::now();
+
+        store.add_visit(&url("https://gosub.io/"), "Gosub", Transition::Typed, now - Duration::hours(3));
+        store.add_visit(&url("https://docs.rs/"), "Docs.rs", Transition::Link, now - Duration::hours(2));
+        store.add_visit(&url("https://gosub.io/"), "", Transition::Reload, now - Duration::hours(1));
+
+        let visits = store.visits("", 10);
+        assert_eq!(urls(&visits), vec!["https://gosub.io/", "https://docs.rs/", "https://gosub.io/"]);
+        assert_eq!(visits[0].transition, Transition::Reload);
+        // An empty title keeps the known title
+        assert_eq!(visits[0].title, "Gosub");
+
+        assert_eq!(urls(&store.visits("docs", 10)), vec!["https://docs.rs/"]);
+        assert_eq!(store.visits("", 1).len(), 1);
+
+        // Reloads are not counted as visits
+        let pages = store.search("gosub", 10);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].visit_count, 1);
+        assert_eq!(pages[0].last_visit.unwrap().timestamp(), (now - Duration::hours(1)).timestamp());
+    }
+
+    #[test]
+    fn test_frecency_order() {
+        let store = HistoryStore::new(":memory:").unwrap();
+        let now = Utc::now();
+
+        // Old visits count less than recent ones, and reloads are not visits
+        for days in [200, 201, 202] {
+            store.add_visit(&url("https://example.com/old"), "Old", Transition::Link, now - Duration::days(days));
+        }
+        store.add_visit(
+            &url("https://example.com/often"),
+            "Often",
+            Transition::Typed,
+            now - Duration::hours(2),
+        );
+        store.add_visit(
+            &url("https://example.com/often"),
+            "Often",
+            Transition::Link,
+            now - Duration::hours(1),
+        );
+        store.add_visit(&url("https://example.com/once"), "Once", Transition::Link, now - Duration::hours(3));
+        store.add_visit(&url("https://example.com/once"), "Once", Transition::Reload, now);
+
+        let pages: Vec<String> = store.search("example", 10).into_iter().map(|page| page.title).collect();
+        assert_eq!(pages, vec!["Often", "Once", "Old"]);
+        assert_eq!(store.search("example", 1).len(), 1);
+        assert!(store.search("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let store = HistoryStore::new(":memory:").unwrap();
+        let now = Utc::now();
+
+        let first = store
+            .add_visit(&url("https://gosub.io/"), "Gosub", Transition::Typed, now - Duration::hours(5))
+            .unwrap();
+        store.add_visit(&url("https://gosub.io/docs"), "Docs", Transition::Link, now - Duration::hours(4));
+        store.add_visit(&url("https://blog.gosub.io/"), "Blog", Transition::Link, now - Duration::hours(3));
+        store.add_visit(&url("https://notgosub.io/"), "Other", Transition::Link, now - Duration::hours(2));
+        store.add_visit(
+            &url("https://example.com/"),
+            "Example",
+            Transition::Link,
+            now - Duration::minutes(30),
+        );
+        store.add_visit(
+            &url("https://example.com/"),
+            "Example",
+            Transition::Link,
+            now - Duration::minutes(10),
+        );
+
+        store.remove_visit(first);
+        assert!(store.search("gosub", 10).iter().all(|page| page.title != "Gosub"));
+        assert_eq!(store.visits("", 10).len(), 5);
+
+        // Forgetting a site removes its subdomains, but not other sites ending with the same name
+        store.remove_site("gosub.io");
+        assert_eq!(
+            urls(&store.visits("", 10)),
+            vec!["https://example.com/", "https://example.com/", "https://notgosub.io/"]
+        );
+
+        // Clearing the last hour updates the pages that keep older visits
+        store.add_visit(&url("https://notgosub.io/"), "Other", Transition::Typed, now - Duration::minutes(5));
+        store.remove_since(now - Duration::hours(1));
+        assert_eq!(urls(&store.visits("", 10)), vec!["https://notgosub.io/"]);
+        let pages = store.search("notgosub", 10);
+        assert_eq!(pages[0].visit_count, 1);
+        assert_eq!(pages[0].last_visit.unwrap().timestamp(), (now - Duration::hours(2)).timestamp());
+        assert!(store.search("example", 10).is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+use url::Url;
+
+/// A box on the rendered page that belongs to a link
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkArea {
+    /// The href of the link, as written in the page
+    pub href: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl LinkArea {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// Returns the link at the given position. Areas are in drawing order, so when areas overlap, the
+/// last one is on top.
+pub fn link_at(areas: &[LinkArea], x: f64, y: f64) -> Option<&LinkArea> {
+    areas.iter().rev().find(|area| area.contains(x, y))
+}
+
+/// Resolves the href of a link against the URL of the page. Returns None when the link cannot be
+/// followed, like script links.
+pub fn resolve_link(base: &Url, href: &str) -> Option<Url> {
+    base.join(href.trim()).ok().filter(|url| url.scheme() != "javascript")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(href: &str, x: f64, y: f64, width: f64, height: f64) -> LinkArea {
+        LinkArea {
+            href: href.to_string(),
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_link_at() {
+        let areas = vec![
+            area("/outer", 0.0, 0.0, 100.0, 50.0),
+            area("/inner", 10.0, 10.0, 20.0, 20.0),
+            area("/below", 0.0, 60.0, 100.0, 20.0),
+        ];
+
+        assert_eq!(link_at(&areas, 5.0, 5.0).unwrap().href, "/outer");
+        assert_eq!(link_at(&areas, 15.0, 15.0).unwrap().href, "/inner");
+        assert_eq!(link_at(&areas, 50.0, 70.0).unwrap().href, "/below");
+
+        // The right and bottom edges are outside of an area
+        assert!(link_at(&areas, 50.0, 55.0).is_none());
+        assert!(link_at(&areas, 100.0, 70.0).is_none());
+        assert!(link_at(&[], 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let base = Url::parse("https://gosub.io/docs/index.html?page=1").unwrap();
+        let resolve = |href| resolve_link(&base, href).map(|url| url.to_string());
+
+        assert_eq!(resolve("about.html").as_deref(), Some("https://gosub.io/docs/about.html"));
+        assert_eq!(resolve("/blog/").as_deref(), Some("https://gosub.io/blog/"));
+        assert_eq!(resolve("../news").as_deref(), Some("https://gosub.io/news"));
+        assert_eq!(
+            resolve("#install").as_deref(),
+            Some("https://gosub.io/docs/index.html?page=1#install")
+        );
+        assert_eq!(resolve("?page=2").as_deref(), Some("https://gosub.io/docs/index.html?page=2"));
+        assert_eq!(resolve("//example.com/a").as_deref(), Some("https://example.com/a"));
+        assert_eq!(resolve(" https://example.org/ ").as_deref(), Some("https://example.org/"));
+
+        assert!(resolve("javascript:void(0)").is_none());
+        assert!(resolve("http://[invalid").is_none());
+    }
+}
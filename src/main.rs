@@ -1,19 +1,41 @@
+mod about;
 mod application;
+mod bookmarks;
+mod command_line;
+mod completion;
+mod containers;
 mod cookies;
 mod dialog;
+mod downloads;
 pub mod engine;
 mod eventloop;
 #[allow(dead_code)]
 mod fetcher;
+mod find;
+mod history;
+mod links;
+mod profile;
+mod screenshot;
+mod session;
+mod settings;
 mod tab;
 mod window;
 
 use crate::application::Application;
+use crate::command_line::{CommandLine, USAGE};
+use crate::cookies::cookie_db_path;
+use crate::cookies::manager::CookieManager;
+use crate::cookies::netscape::NetscapeOptions;
+use crate::cookies::sqlite_store::SqliteStorage;
 use crate::fetcher::Fetcher;
+use crate::profile::{is_valid_profile_name, list_profiles, select_profile, Profile, DEFAULT_PROFILE};
+use crate::screenshot::save_screenshot;
+use crate::settings::LogLevel;
 use gtk4::gdk::Display;
 use gtk4::prelude::ApplicationExt;
 use gtk4::{gio, CssProvider};
-use std::sync::OnceLock;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, Once, OnceLock};
 use tokio::runtime::Runtime;
 
 const APP_ID: &str = "io.gosub.browser-gtk";
@@ -23,19 +45,88 @@ fn runtime() -> &'static Runtime {
     RUNTIME.get_or_init(|| Runtime::new().expect("Setting up tokio runtime needs to succeed."))
 }
 
-fn main() {
-    colog::basic_builder()
-        .format_file(true)
-        .format_indent(Some(2))
-        .format_level(true)
-        .format_suffix(" ")
-        .format_module_path(true)
-        .format_source_path(true)
-        .format_target(true)
-        .filter(None, log::LevelFilter::Error)
-        .filter(Some("fetcher"), log::LevelFilter::Trace)
-        .filter(Some("gtk"), log::LevelFilter::Info)
-        .init();
+/// Log level given with `--log-level`, which overrides the log levels in the settings
+static LOG_LEVEL: OnceLock<LogLevel> = OnceLock::new();
+
+/// Sets up logging with the log levels in the settings of the profile, so it can only be called once
+/// the profile has been selected. Does nothing when logging has already been set up.
+fn init_logging() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let mut developer = settings::settings().developer.clone();
+        if let Some(level) = LOG_LEVEL.get() {
+            developer.log_level = *level;
+            developer.log_targets.clear();
+        }
+
+        let mut logger = colog::basic_builder();
+        logger
+            .format_file(true)
+            .format_indent(Some(2))
+            .format_level(true)
+            .format_suffix(" ")
+            .format_module_path(true)
+            .format_source_path(true)
+            .format_target(true)
+            .filter(None, developer.log_level.filter());
+        for (target, level) in &developer.log_targets {
+            logger.filter(Some(target), level.filter());
+        }
+        logger.init();
+    });
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let command_line = match CommandLine::parse(&args[1..]) {
+        Ok(command_line) => command_line,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if command_line.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if let Some(level) = command_line.log_level {
+        _ = LOG_LEVEL.set(level);
+    }
+
+    let profile = match handle_profile_args(&command_line) {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Without a profile, the application asks for one when it is activated
+    let app_id = profile.as_ref().map_or_else(|| APP_ID.to_string(), Profile::application_id);
+    if let Some(profile) = profile {
+        if let Err(e) = select_profile(profile) {
+            eprintln!("Cannot use profile: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+        init_logging();
+    }
+
+    // Cookie operations and screenshots never ask for a profile, they use the default profile instead
+    if let Some(exit_code) = handle_cookie_args(&command_line) {
+        return exit_code;
+    }
+    if let Some((path, address)) = &command_line.headless_screenshot {
+        return match save_screenshot(address, path) {
+            Ok(()) => {
+                println!("Saved screenshot of {} to {}", address, path.display());
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Failed to take a screenshot of {}: {:?}", address, e);
+                ExitCode::FAILURE
+            }
+        };
+    }
 
     Fetcher::protocols_implemented().iter().for_each(|protocol| {
         println!("Protocol: {}", protocol);
@@ -46,9 +137,88 @@ fn main() {
 
     gio::resources_register_include!("gosub.gresource").expect("Failed to register resources.");
 
-    let app = Application::new();
+    // The command line is handled by the primary instance, which may be another process
+    let app = Application::new(&app_id);
     app.connect_startup(|_| load_css());
-    app.run();
+    app.run(&args);
+
+    ExitCode::SUCCESS
+}
+
+/// Returns the profile that is given with `--profile` or `--profile-dir`. Without these options the
+/// default profile is used, unless there are several profiles. In that case no profile is returned,
+/// and the user is asked to pick one.
+fn handle_profile_args(command_line: &CommandLine) -> Result<Option<Profile>, String> {
+    if let Some(dir) = &command_line.profile_dir {
+        return Ok(Some(Profile::from_dir(dir)));
+    }
+    if let Some(name) = &command_line.profile {
+        if !is_valid_profile_name(name) {
+            return Err(format!("Invalid profile name \"{}\"", name));
+        }
+        return Ok(Some(Profile::named(name)));
+    }
+
+    let profiles = list_profiles();
+    if profiles.len() > 1 {
+        return Ok(None);
+    }
+    let name = profiles.first().map_or(DEFAULT_PROFILE, |name| name.as_str());
+    Ok(Some(Profile::named(name)))
+}
+
+/// Handles the cookie import and export options. Returns the exit code when a cookie operation was
+/// requested, in which case the browser itself is not started.
+fn handle_cookie_args(command_line: &CommandLine) -> Option<ExitCode> {
+    let import_path = command_line.import_cookies.clone();
+    let export_path = command_line.export_cookies.clone();
+    let options = NetscapeOptions {
+        domain: command_line.cookie_domain.clone(),
+        include_session: command_line.include_session_cookies,
+    };
+
+    if import_path.is_none() && export_path.is_none() {
+        return None;
+    }
+
+    let store = match SqliteStorage::new(cookie_db_path()) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Cannot open cookie database: {}", e);
+            return Some(ExitCode::FAILURE);
+        }
+    };
+    let manager = CookieManager::new(Arc::new(Mutex::new(store)));
+
+    if let Some(path) = import_path {
+        match manager.import_netscape(&path, &options) {
+            Ok(summary) => {
+                println!("Imported {} cookies from {}", summary.imported, path.display());
+                if summary.skipped > 0 {
+                    println!(
+                        "Skipped {} cookies. Session cookies are not kept between browser sessions, so they cannot be imported.",
+                        summary.skipped
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to import cookies from {}: {}", path.display(), e);
+                return Some(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    if let Some(path) = export_path {
+        match manager.export_netscape(&path, &options) {
+            Ok(count) => println!("Exported {} cookies to {}", count, path.display()),
+            Err(e) => {
+                eprintln!("Failed to export cookies to {}: {}", path.display(), e);
+                return Some(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    Some(ExitCode::SUCCESS)
 }
 
 fn load_css() {
@@ -0,0 +1,394 @@
+use crate::APP_ID;
+use anyhow::{anyhow, Context};
+use gtk4::glib;
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name of the profile that is used when no profile is given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Directory inside the XDG base directories that holds the profiles of the browser
+const PROFILES_DIR: &str = "gosub/profiles";
+
+/// Name of the lock file in the data directory of a profile
+const LOCK_FILE: &str = "lock";
+
+/// Files that older versions of the browser kept in the working directory, with their names in the
+/// data directory of a profile
+const LEGACY_DATA_FILES: [(&str, &str); 8] = [
+    ("gosub_cookies.db", "cookies.db"),
+    ("gosub_permissions.db", "permissions.db"),
+    ("gosub_containers.db", "containers.db"),
+    ("gosub_search_engines.db", "search_engines.db"),
+    ("bookmarks.db", "bookmarks.db"),
+    ("history.db", "history.db"),
+    ("downloads.db", "downloads.db"),
+    ("gosub_session.json", "session.json"),
+];
+
+/// Settings file that older versions of the browser kept in the working directory, with its name in
+/// the config directory of a profile
+const LEGACY_SETTINGS_FILE: (&str, &str) = ("gosub_settings.toml", "settings.toml");
+
+/// Prefix of the cookie databases of containers that older versions kept in the working directory
+const LEGACY_CONTAINER_COOKIES_PREFIX: &str = "gosub_cookies_container_";
+
+/// A profile holds all state of the browser, like cookies, bookmarks, history and settings. Its
+/// files are stored in separate data, cache and config directories.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// Name of the profile, or the directory when it is stored in a custom location
+    pub name: String,
+    pub data_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub config_dir: PathBuf,
+    /// Set when the profile is stored in a directory given with `--profile-dir`
+    custom: bool,
+}
+
+impl Profile {
+    /// Returns the profile with the given name, stored in the XDG base directories:
+    ///
+    ///   $XDG_DATA_HOME/gosub/profiles/<name>     databases and the session
+    ///   $XDG_CACHE_HOME/gosub/profiles/<name>    the disk cache
+    ///   $XDG_CONFIG_HOME/gosub/profiles/<name>   the settings
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            data_dir: glib::user_data_dir().join(PROFILES_DIR).join(name),
+            cache_dir: glib::user_cache_dir().join(PROFILES_DIR).join(name),
+            config_dir: glib::user_config_dir().join(PROFILES_DIR).join(name),
+            custom: false,
+        }
+    }
+
+    /// Returns a profile that is stored in the given directory. Data and config files are stored in
+    /// the directory itself, the cache in its `cache` subdirectory.
+    pub fn from_dir(dir: &Path) -> Self {
+        Self {
+            name: dir.display().to_string(),
+            data_dir: dir.to_path_buf(),
+            cache_dir: dir.join("cache"),
+            config_dir: dir.to_path_buf(),
+            custom: true,
+        }
+    }
+
+    /// Path of a file in the data directory
+    pub fn data_path(&self, file: &str) -> PathBuf {
+        self.data_dir.join(file)
+    }
+
+    /// Path of a file in the cache directory
+    pub fn cache_path(&self, file: &str) -> PathBuf {
+        self.cache_dir.join(file)
+    }
+
+    /// Path of a file in the config directory
+    pub fn config_path(&self, file: &str) -> PathBuf {
+        self.config_dir.join(file)
+    }
+
+    /// Creates the directories of the profile when they do not exist yet
+    pub fn create_dirs(&self) -> anyhow::Result<()> {
+        for dir in [&self.data_dir, &self.cache_dir, &self.config_dir] {
+            fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Moves the files that older versions of the browser kept in the given directory into this
+    /// profile. Files that the profile already has are left alone. Returns the files that have been
+    /// moved, with their new paths.
+    pub fn migrate_legacy_files(&self, from: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let mut files: Vec<(PathBuf, PathBuf)> = LEGACY_DATA_FILES
+            .iter()
+            .map(|(old, new)| (from.join(old), self.data_path(new)))
+            .collect();
+        files.push((from.join(LEGACY_SETTINGS_FILE.0), self.config_path(LEGACY_SETTINGS_FILE.1)));
+
+        // Every container had its own cookie database
+        if let Ok(entries) = fs::read_dir(from) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some(rest) = name.strip_prefix(LEGACY_CONTAINER_COOKIES_PREFIX) {
+                    files.push((entry.path(), self.data_path(&format!("cookies_container_{}", rest))));
+                }
+            }
+        }
+
+        files
+            .into_iter()
+            .filter(|(old, new)| old.is_file() && !new.exists())
+            .filter(|(old, new)| match move_file(old, new) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(target: "gtk", "cannot move {} to {}: {}", old.display(), new.display(), e);
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// Application id of the browser when running with this profile. Every profile has its own id,
+    /// so a second invocation with the same profile is handled by the running instance, while
+    /// different profiles run in separate instances.
+    pub fn application_id(&self) -> String {
+        if !self.custom && self.name == DEFAULT_PROFILE {
+            return APP_ID.to_string();
+        }
+
+        let mut hasher = DefaultHasher::new();
+        self.data_dir.hash(&mut hasher);
+        format!("{}.Profile{:016x}", APP_ID, hasher.finish())
+    }
+
+    /// Takes the lock of the profile, so no other instance of the browser can use it. The lock is
+    /// released when the returned guard is dropped. A lock that is left behind by an instance that
+    /// is no longer running is taken over.
+    pub fn lock(&self) -> anyhow::Result<ProfileLock> {
+        let path = self.data_path(LOCK_FILE);
+
+        // The second attempt is made after a stale lock file has been removed
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())?;
+                    return Ok(ProfileLock { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let owner = fs::read_to_string(&path).ok().and_then(|pid| pid.trim().parse::<u32>().ok());
+                    if let Some(pid) = owner.filter(|pid| process_is_running(*pid)) {
+                        return Err(anyhow!(
+                            "profile \"{}\" is in use by process {}. Remove {} when that is not the case.",
+                            self.name,
+                            pid,
+                            path.display()
+                        ));
+                    }
+                    warn!(target: "gtk", "removing stale lock file {}", path.display());
+                    fs::remove_file(&path)?;
+                }
+                Err(e) => return Err(e).with_context(|| format!("cannot create {}", path.display())),
+            }
+        }
+
+        Err(anyhow!("cannot lock profile \"{}\"", self.name))
+    }
+}
+
+/// Keeps a profile locked for as long as it exists
+#[derive(Debug)]
+pub struct ProfileLock {
+    path: PathBuf,
+}
+
+impl Drop for ProfileLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(target: "gtk", "failed to remove lock file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Moves a file, copying it when it is moved to another file system
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Returns true when a process with the given id is running. Only Linux can tell, other platforms
+/// always consider the owner of a lock to be running.
+fn process_is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        true
+    }
+}
+
+/// Returns true when the name can be used for a profile. Names become directory names, so they are
+/// limited to letters, digits, dashes and underscores.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns the names of all profiles in the XDG data directory, sorted by name
+pub fn list_profiles() -> Vec<String> {
+    profile_names(&glib::user_data_dir().join(PROFILES_DIR))
+}
+
+fn profile_names(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_valid_profile_name(name))
+        .collect();
+    names.sort();
+    names
+}
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+/// Makes the given profile the profile of the browser and creates its directories. The profile can
+/// only be selected once.
+pub fn select_profile(profile: Profile) -> anyhow::Result<()> {
+    prepare_profile(&profile)?;
+    PROFILE.set(profile).map_err(|_| anyhow!("a profile has already been selected"))
+}
+
+/// Creates the directories of the profile. The first time the default profile is used, the files
+/// that older versions of the browser kept in the working directory are moved into it.
+fn prepare_profile(profile: &Profile) -> anyhow::Result<()> {
+    let first_use = !profile.data_dir.exists();
+    profile.create_dirs()?;
+
+    if first_use && !profile.custom && profile.name == DEFAULT_PROFILE {
+        // Logging is not set up yet, as its settings are stored in the profile
+        for (old, new) in profile.migrate_legacy_files(Path::new(".")) {
+            println!("Moved {} to {}", old.display(), new.display());
+        }
+    }
+    Ok(())
+}
+
+/// Returns true when the profile of the browser has been selected
+pub fn is_profile_selected() -> bool {
+    PROFILE.get().is_some()
+}
+
+/// Returns the profile of the browser. Falls back to the default profile when none has been selected.
+pub fn profile() -> &'static Profile {
+    PROFILE.get_or_init(|| {
+        let profile = Profile::named(DEFAULT_PROFILE);
+        if let Err(e) = prepare_profile(&profile) {
+            warn!(target: "gtk", "failed to create profile directories: {:?}", e);
+        }
+        profile
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gosub_profile_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_profile_names() {
+        assert!(is_valid_profile_name("work"));
+        assert!(is_valid_profile_name("my-profile_2"));
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("../etc"));
+        assert!(!is_valid_profile_name("a b"));
+
+        let dir = temp_dir("names");
+        assert!(profile_names(&dir).is_empty());
+
+        for name in ["work", "default", ".hidden"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("file"), "").unwrap();
+        assert_eq!(profile_names(&dir), vec!["default", "work"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_custom_dir() {
+        let dir = temp_dir("custom");
+        let profile = Profile::from_dir(&dir);
+        profile.create_dirs().unwrap();
+
+        assert_eq!(profile.data_path("cookies.db"), dir.join("cookies.db"));
+        assert_eq!(profile.config_path("settings.toml"), dir.join("settings.toml"));
+        assert_eq!(profile.cache_path("default"), dir.join("cache/default"));
+        assert!(profile.cache_dir.is_dir());
+
+        // Custom profiles never share the id of the default profile
+        assert_ne!(profile.application_id(), APP_ID);
+        assert_eq!(profile.application_id(), Profile::from_dir(&dir).application_id());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_legacy_files() {
+        let from = temp_dir("legacy");
+        let dir = temp_dir("migrated");
+        let profile = Profile::from_dir(&dir);
+        profile.create_dirs().unwrap();
+        fs::create_dir_all(&from).unwrap();
+
+        for file in [
+            "gosub_cookies.db",
+            "history.db",
+            "gosub_settings.toml",
+            "gosub_cookies_container_3.db",
+            "other.db",
+        ] {
+            fs::write(from.join(file), file).unwrap();
+        }
+        // Files that the profile already has are kept
+        fs::write(profile.data_path("history.db"), "new").unwrap();
+
+        let mut moved: Vec<PathBuf> = profile.migrate_legacy_files(&from).into_iter().map(|(_, new)| new).collect();
+        moved.sort();
+        assert_eq!(
+            moved,
+            vec![
+                dir.join("cookies.db"),
+                dir.join("cookies_container_3.db"),
+                dir.join("settings.toml")
+            ]
+        );
+        assert_eq!(fs::read_to_string(profile.data_path("cookies.db")).unwrap(), "gosub_cookies.db");
+        assert_eq!(fs::read_to_string(profile.data_path("history.db")).unwrap(), "new");
+        assert!(!from.join("gosub_cookies.db").exists());
+        assert!(from.join("history.db").exists());
+        assert!(from.join("other.db").exists());
+
+        fs::remove_dir_all(&from).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock() {
+        let dir = temp_dir("lock");
+        let profile = Profile::from_dir(&dir);
+        profile.create_dirs().unwrap();
+
+        let lock = profile.lock().unwrap();
+        assert!(profile.data_path(LOCK_FILE).exists());
+        if cfg!(target_os = "linux") {
+            // This process is running, so the profile cannot be locked again
+            assert!(profile.lock().is_err());
+        }
+        drop(lock);
+        assert!(!profile.data_path(LOCK_FILE).exists());
+
+        // A lock of a process that is not running anymore is taken over
+        fs::write(profile.data_path(LOCK_FILE), "not a pid").unwrap();
+        let lock = profile.lock().unwrap();
+        drop(lock);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use crate::engine::{draw_tree, GosubEngineConfig};
+use crate::fetcher::address_parser::{GosubAddressParser, GosubRenderMode};
+use crate::fetcher::network_profile::{network_profiles, NetworkKind};
+use crate::fetcher::{fetch_page, PageResponse};
+use crate::runtime;
+use anyhow::anyhow;
+use gosub_engine::prelude::*;
+use gtk4::gdk::{MemoryFormat, MemoryTexture};
+use gtk4::prelude::*;
+use gtk4::{cairo, glib};
+use std::path::Path;
+
+/// Size of the viewport that is rendered for a screenshot
+const SCREENSHOT_WIDTH: i32 = 1280;
+const SCREENSHOT_HEIGHT: i32 = 800;
+
+/// Cairo stores its pixels as native-endian 32-bit ARGB values
+#[cfg(target_endian = "little")]
+const SURFACE_FORMAT: MemoryFormat = MemoryFormat::B8g8r8a8Premultiplied;
+#[cfg(target_endian = "big")]
+const SURFACE_FORMAT: MemoryFormat = MemoryFormat::A8r8g8b8Premultiplied;
+
+/// Loads the page at the given address, renders it without a window and saves it as a PNG file
+pub fn save_screenshot(address: &str, path: &Path) -> anyhow::Result<()> {
+    let (_, url) = GosubAddressParser::parse(address)?;
+    if url.scheme() == "about" {
+        return Err(anyhow!("about: pages need a browser window"));
+    }
+
+    let network = network_profiles().lock().unwrap().get(NetworkKind::Default);
+    let content = match runtime().block_on(fetch_page(url.clone(), &network, &GosubRenderMode::Rendered))? {
+        PageResponse::Page(content) => String::from_utf8_lossy(&content).to_string(),
+        PageResponse::Download(download) => return Err(anyhow!("{} is a download ({})", url, download.filename)),
+    };
+
+    let mut drawer = <GosubEngineConfig as HasTreeDrawer>::TreeDrawer::from_source(url, &content, TaffyLayouter, false)
+        .map_err(|e| anyhow!("cannot render page: {:?}", e))?;
+
+    let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, SCREENSHOT_WIDTH, SCREENSHOT_HEIGHT)?;
+    {
+        let cr = cairo::Context::new(&surface)?;
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.paint()?;
+        draw_tree(&mut drawer, &cr, SCREENSHOT_WIDTH as u32, SCREENSHOT_HEIGHT as u32);
+    }
+    surface.flush();
+
+    let stride = surface.stride() as usize;
+    let data = surface.data()?.to_vec();
+    let texture = MemoryTexture::new(
+        SCREENSHOT_WIDTH,
+        SCREENSHOT_HEIGHT,
+        SURFACE_FORMAT,
+        &glib::Bytes::from_owned(data),
+        stride,
+    );
+    texture.save_to_png(path)?;
+
+    Ok(())
+}
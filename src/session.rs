@@ -0,0 +1,270 @@
+use crate::containers::{container_store, ContainerId};
+use crate::fetcher::address_parser::GosubAddressParser;
+use crate::profile::profile;
+use crate::tab::{GosubTab, GosubTabManager, HistoryEntry, SessionHistory, TabId};
+use anyhow::anyhow;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the file that stores the session of the previous run
+const SESSION_FILE: &str = "session.json";
+
+/// Version of the session file format. Files with a newer version are ignored.
+const SESSION_VERSION: u32 = 1;
+
+/// The state of the browser that is restored on the next start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub windows: Vec<WindowSession>,
+}
+
+impl Session {
+    pub fn new(windows: Vec<WindowSession>) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            windows,
+        }
+    }
+}
+
+/// The tabs of a single window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowSession {
+    pub tabs: Vec<TabSession>,
+    /// Index of the active tab
+    pub active_tab: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabSession {
+    pub title: String,
+    pub pinned: bool,
+    #[serde(default)]
+    pub container: Option<String>,
+    pub history: Vec<HistoryEntrySession>,
+    /// Index of the current entry in the history
+    pub history_index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntrySession {
+    /// Address including the render mode prefix (like `source:https://gosub.io`)
+    pub address: String,
+    pub title: String,
+    #[serde(default)]
+    pub scroll_position: f64,
+}
+
+impl WindowSession {
+    /// Captures the tabs of the tab manager. Private tabs are never stored.
+    pub fn from_manager(manager: &GosubTabManager, active_tab_id: Option<TabId>) -> Self {
+        let mut tabs = Vec::new();
+        let mut active_tab = None;
+
+        for tab in manager.order().into_iter().filter_map(|tab_id| manager.get_tab(tab_id)) {
+            if tab.is_private() {
+                continue;
+            }
+            if Some(tab.id()) == active_tab_id {
+                active_tab = Some(tabs.len());
+            }
+
+            let history = tab.history();
+            tabs.push(TabSession {
+                title: tab.title().to_string(),
+                pinned: tab.is_pinned(),
+                container: tab.container().map(|id| id.to_string()),
+                history: history
+                    .entries()
+                    .iter()
+                    .map(|entry| HistoryEntrySession {
+                        address: GosubAddressParser::format(&entry.render_mode, &entry.url),
+                        title: entry.title.clone(),
+                        scroll_position: entry.scroll_position,
+                    })
+                    .collect(),
+                history_index: history.current_index(),
+            });
+        }
+
+        Self { tabs, active_tab }
+    }
+
+    /// Creates the tabs of this window, together with the index of the active tab. Entries that cannot
+    /// be parsed anymore are skipped, and tabs without any entries are dropped.
+    pub fn restore_tabs(&self) -> (Vec<GosubTab>, Option<usize>) {
+        let mut tabs = Vec::new();
+        let mut active_tab = None;
+
+        for (index, tab_session) in self.tabs.iter().enumerate() {
+            let Some(mut tab) = tab_session.restore() else {
+                continue;
+            };
+            if self.active_tab == Some(index) {
+                active_tab = Some(tabs.len());
+            }
+
+            tab.set_pinned(tab_session.pinned);
+            let container = tab_session
+                .container
+                .as_deref()
+                .and_then(|id| id.parse::<ContainerId>().ok())
+                .filter(|id| container_store().get(*id).is_some());
+            tab.set_container(container);
+
+            tabs.push(tab);
+        }
+
+        (tabs, active_tab)
+    }
+}
+
+impl TabSession {
+    fn restore(&self) -> Option<GosubTab> {
+        let mut current = self.history_index;
+        let mut entries = Vec::new();
+
+        for (index, entry) in self.history.iter().enumerate() {
+            let Ok((render_mode, url)) = GosubAddressParser::parse(&entry.address) else {
+                if index < self.history_index {
+                    current = current.saturating_sub(1);
+                }
+                continue;
+            };
+
+            let mut history_entry = HistoryEntry::new(url, &entry.title, render_mode);
+            history_entry.scroll_position = entry.scroll_position;
+            entries.push(history_entry);
+        }
+
+        let mut tab = GosubTab::from_history(SessionHistory::from_entries(entries, current))?;
+        tab.set_title(&self.title);
+        Some(tab)
+    }
+}
+
+/// Reads and writes the session file
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf() }
+    }
+
+    /// Loads the session. Returns None when there is no (readable) session.
+    pub fn load(&self) -> Option<Session> {
+        let content = fs::read_to_string(&self.path).ok()?;
+
+        match Self::parse(&content) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                warn!(target: "gtk", "ignoring session file {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn parse(content: &str) -> anyhow::Result<Session> {
+        let session: Session = serde_json::from_str(content)?;
+        if session.version > SESSION_VERSION {
+            return Err(anyhow!("unsupported session version {}", session.version));
+        }
+        Ok(session)
+    }
+
+    /// Saves the session. The file is replaced atomically, so a crash while writing never leaves a
+    /// broken session behind.
+    pub fn save(&self, session: &Session) -> anyhow::Result<()> {
+        let content = serde_json::to_string_pretty(session)?;
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Path of the session file of the current profile
+pub fn session_path() -> PathBuf {
+    profile().data_path(SESSION_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::address_parser::GosubRenderMode;
+    use url::Url;
+
+    fn manager_with_tabs() -> (GosubTabManager, TabId) {
+        let mut manager = GosubTabManager::new();
+
+        let mut pinned = GosubTab::new(Url::parse("https://gosub.io/").unwrap(), "Gosub");
+        pinned.set_pinned(true);
+        manager.add_tab(pinned, None);
+
+        let mut tab = GosubTab::new(Url::parse("https://example.com/").unwrap(), "Example");
+        tab.set_scroll_position(100.0);
+        tab.navigate(Url::parse("https://example.com/data.json").unwrap(), GosubRenderMode::Json);
+        tab.set_title("Data");
+        tab.navigate_history(0);
+        let tab_id = manager.add_tab(tab, None);
+
+        let mut private = GosubTab::new(Url::parse("https://secret.example/").unwrap(), "Secret");
+        private.set_private(true);
+        manager.add_tab(private, None);
+
+        (manager, tab_id)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let (manager, active_tab_id) = manager_with_tabs();
+        let window = WindowSession::from_manager(&manager, Some(active_tab_id));
+
+        // Private tabs are not stored
+        assert_eq!(window.tabs.len(), 2);
+        assert_eq!(window.active_tab, Some(1));
+        assert_eq!(window.tabs[1].history[1].address, "json:https://example.com/data.json");
+
+        let (tabs, active_tab) = window.restore_tabs();
+        assert_eq!(tabs.len(), 2);
+        assert_eq!(active_tab, Some(1));
+        assert!(tabs[0].is_pinned());
+
+        let tab = &tabs[1];
+        assert_eq!(tab.url().as_str(), "https://example.com/");
+        assert_eq!(tab.title(), "Example");
+        assert_eq!(tab.history().current().unwrap().scroll_position, 100.0);
+        assert!(tab.history().can_go_forward());
+        assert_eq!(tab.history().forward_entries()[0].1.render_mode, GosubRenderMode::Json);
+        assert_eq!(tab.history().forward_entries()[0].1.title, "Data");
+    }
+
+    #[test]
+    fn test_store() {
+        let path = std::env::temp_dir().join(format!("gosub_session_test_{}.json", std::process::id()));
+        let store = SessionStore::new(&path);
+        assert!(store.load().is_none());
+
+        let (manager, active_tab_id) = manager_with_tabs();
+        let session = Session::new(vec![WindowSession::from_manager(&manager, Some(active_tab_id))]);
+        store.save(&session).unwrap();
+        assert_eq!(store.load(), Some(session));
+
+        // Sessions written by a newer version are ignored
+        fs::write(&path, r#"{"version": 99, "windows": []}"#).unwrap();
+        assert!(store.load().is_none());
+
+        // Older sessions that still have the startup mode can be read
+        fs::write(&path, r#"{"version": 1, "startup_mode": "blank", "windows": []}"#).unwrap();
+        assert!(store.load().unwrap().windows.is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
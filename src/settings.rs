@@ -0,0 +1,466 @@
+use crate::cookies::policy::CookieMode;
+use crate::fetcher::GOSUB_USERAGENT_STRING;
+use crate::profile::profile;
+use crate::session::session_path;
+use anyhow::anyhow;
+use log::{warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Name of the file that stores the settings
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Version of the settings file format. Files with a newer version are ignored.
+const SETTINGS_VERSION: u32 = 1;
+
+/// Default page that is opened on startup when the browser does not restore the previous session
+pub const HOME_PAGE: &str = "https://gosub.io";
+
+/// Schemes that can be added to addresses that are typed without one
+pub const DEFAULT_SCHEMES: [&str; 2] = ["https", "http"];
+
+/// What the browser shows when it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartupMode {
+    /// Restore the tabs of the previous session
+    #[default]
+    RestoreSession,
+    /// Open the home page
+    HomePage,
+    /// Open a blank page
+    Blank,
+}
+
+impl StartupMode {
+    pub const ALL: [StartupMode; 3] = [StartupMode::RestoreSession, StartupMode::HomePage, StartupMode::Blank];
+
+    /// Key of the mode, as used in action targets
+    pub fn key(&self) -> &'static str {
+        match self {
+            StartupMode::RestoreSession => "restore-session",
+            StartupMode::HomePage => "home-page",
+            StartupMode::Blank => "blank",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StartupMode::RestoreSession => "Restore previous session",
+            StartupMode::HomePage => "Open the home page",
+            StartupMode::Blank => "Open a blank page",
+        }
+    }
+}
+
+/// Level of the log messages that are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Key of the level, as used in the settings file and on the command line
+    pub fn key(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.key() == key)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "Off",
+            LogLevel::Error => "Error",
+            LogLevel::Warn => "Warning",
+            LogLevel::Info => "Info",
+            LogLevel::Debug => "Debug",
+            LogLevel::Trace => "Trace",
+        }
+    }
+
+    pub fn filter(&self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// All settings of the browser, grouped in the same way as in the settings window. Settings that are
+/// missing from the file get their default value, so new settings can be added without a new version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub general: GeneralSettings,
+    pub network: NetworkSettings,
+    pub privacy: PrivacySettings,
+    pub appearance: AppearanceSettings,
+    pub developer: DeveloperSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            general: GeneralSettings::default(),
+            network: NetworkSettings::default(),
+            privacy: PrivacySettings::default(),
+            appearance: AppearanceSettings::default(),
+            developer: DeveloperSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralSettings {
+    pub startup_mode: StartupMode,
+    pub home_page: String,
+    /// Scheme that is added to addresses that are typed without one, like `gosub.io`
+    pub default_scheme: String,
+    /// Directory downloads are saved in. Empty for the downloads directory of the user.
+    pub download_dir: String,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            startup_mode: StartupMode::default(),
+            home_page: HOME_PAGE.to_string(),
+            default_scheme: DEFAULT_SCHEMES[0].to_string(),
+            download_dir: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub user_agent: String,
+    pub accept_language: String,
+    /// Seconds to wait for a connection to be made
+    pub connect_timeout: u64,
+    /// Seconds to wait for data from the server, before a request fails
+    pub read_timeout: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: GOSUB_USERAGENT_STRING.to_string(),
+            accept_language: "en-US,en;q=0.5".to_string(),
+            connect_timeout: 5,
+            read_timeout: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    pub cookie_mode: CookieMode,
+    /// Send the `DNT` header with every request
+    pub do_not_track: bool,
+    /// Record visited pages in the history
+    pub remember_history: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            cookie_mode: CookieMode::default(),
+            do_not_track: true,
+            remember_history: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    pub dark_mode: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeveloperSettings {
+    /// Level of the log messages of all targets that have no level of their own
+    pub log_level: LogLevel,
+    /// Level of the log messages per target, like `fetcher` or `gtk`
+    pub log_targets: BTreeMap<String, LogLevel>,
+    /// Show the log window when a browser window opens
+    pub show_log: bool,
+}
+
+impl Default for DeveloperSettings {
+    fn default() -> Self {
+        Self {
+            log_level: LogLevel::Error,
+            log_targets: BTreeMap::from([
+                ("cookies".to_string(), LogLevel::Info),
+                ("fetcher".to_string(), LogLevel::Trace),
+                ("gtk".to_string(), LogLevel::Info),
+            ]),
+            show_log: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Returns every setting as a dotted name (like `general.home_page`) and its value
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        if let Ok(value) = toml::Value::try_from(self) {
+            flatten("", &value, &mut entries);
+        }
+        entries
+    }
+}
+
+fn flatten(prefix: &str, value: &toml::Value, entries: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&name, value, entries);
+            }
+        }
+        toml::Value::String(s) => entries.push((prefix.to_string(), s.clone())),
+        value => entries.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+/// Reads and writes the settings file
+pub struct SettingsStore {
+    path: PathBuf,
+}
+
+impl SettingsStore {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf() }
+    }
+
+    /// Loads the settings. Returns None when there is no (readable) settings file.
+    pub fn load(&self) -> Option<Settings> {
+        let content = fs::read_to_string(&self.path).ok()?;
+
+        match Self::parse(&content) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                warn!(target: "gtk", "ignoring settings file {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn parse(content: &str) -> anyhow::Result<Settings> {
+        let settings: Settings = toml::from_str(content)?;
+        if settings.version > SETTINGS_VERSION {
+            return Err(anyhow!("unsupported settings version {}", settings.version));
+        }
+        Ok(settings)
+    }
+
+    /// Saves the settings. The file is replaced atomically, so a crash while writing never leaves a
+    /// broken file behind.
+    pub fn save(&self, settings: &Settings) -> anyhow::Result<()> {
+        let content = toml::to_string_pretty(settings)?;
+
+        let tmp_path = self.path.with_extension("toml.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Path of the settings file of the current profile
+pub fn settings_path() -> PathBuf {
+    profile().config_path(SETTINGS_FILE)
+}
+
+/// Returns the startup mode from a session file of an older version, which stored it there before
+/// there were settings
+fn legacy_startup_mode(session_path: &Path) -> Option<StartupMode> {
+    let content = fs::read_to_string(session_path).ok()?;
+    let session: serde_json::Value = serde_json::from_str(&content).ok()?;
+    serde_json::from_value(session.get("startup_mode")?.clone()).ok()
+}
+
+/// Loads the settings of the browser. Without a settings file, the default settings are used.
+fn load_settings() -> Settings {
+    if let Some(settings) = SettingsStore::new(&settings_path()).load() {
+        return settings;
+    }
+
+    let mut settings = Settings::default();
+    if let Some(startup_mode) = legacy_startup_mode(&session_path()) {
+        settings.general.startup_mode = startup_mode;
+    }
+    settings
+}
+
+/// Returns the current settings of the browser
+pub fn settings() -> Arc<Settings> {
+    cached_settings().lock().unwrap().clone()
+}
+
+/// Time between a change of the settings and writing them to disk. Typing in the settings window
+/// changes the settings on every keystroke, which results in a single write this way.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// The settings have changed, and are not written to disk yet
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Changes the settings. They are used right away, and written to disk shortly after.
+pub fn update_settings(f: impl FnOnce(&mut Settings)) {
+    let mut cached = cached_settings().lock().unwrap();
+    let mut settings = Settings::clone(&cached);
+    f(&mut settings);
+    *cached = Arc::new(settings);
+    drop(cached);
+
+    if !SAVE_PENDING.swap(true, Ordering::SeqCst) {
+        thread::spawn(|| {
+            thread::sleep(SAVE_DELAY);
+            save_settings();
+        });
+    }
+}
+
+/// Writes changed settings to disk. This is done on shutdown as well, so changes that were made just
+/// before are not lost.
+pub fn save_settings() {
+    static SAVING: Mutex<()> = Mutex::new(());
+    let _saving = SAVING.lock().unwrap();
+
+    if !SAVE_PENDING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Err(e) = SettingsStore::new(&settings_path()).save(&settings()) {
+        warn!(target: "gtk", "failed to save settings: {}", e);
+    }
+}
+
+fn cached_settings() -> &'static Mutex<Arc<Settings>> {
+    static SETTINGS: OnceLock<Mutex<Arc<Settings>>> = OnceLock::new();
+    SETTINGS.get_or_init(|| Mutex::new(Arc::new(load_settings())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let path = std::env::temp_dir().join(format!("gosub_settings_test_{}.toml", std::process::id()));
+        let store = SettingsStore::new(&path);
+        assert!(store.load().is_none());
+
+        let mut settings = Settings::default();
+        settings.general.startup_mode = StartupMode::Blank;
+        settings.privacy.cookie_mode = CookieMode::BlockThirdParty;
+        settings.developer.log_targets.insert("fetcher".to_string(), LogLevel::Warn);
+        store.save(&settings).unwrap();
+        assert_eq!(store.load(), Some(settings));
+
+        // Settings written by a newer version are ignored
+        fs::write(&path, "version = 99\n").unwrap();
+        assert!(store.load().is_none());
+
+        // Missing settings get their default value
+        fs::write(&path, "version = 1\n[network]\nread_timeout = 30\n").unwrap();
+        let settings = store.load().unwrap();
+        assert_eq!(settings.network.read_timeout, 30);
+        assert_eq!(settings.network.connect_timeout, 5);
+        assert_eq!(settings.general, GeneralSettings::default());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_startup_mode() {
+        let path = std::env::temp_dir().join(format!("gosub_settings_session_test_{}.json", std::process::id()));
+        assert_eq!(legacy_startup_mode(&path), None);
+
+        fs::write(&path, r#"{"version": 1, "startup_mode": "home-page", "windows": []}"#).unwrap();
+        assert_eq!(legacy_startup_mode(&path), Some(StartupMode::HomePage));
+
+        fs::write(&path, r#"{"version": 1, "windows": []}"#).unwrap();
+        assert_eq!(legacy_startup_mode(&path), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_entries() {
+        let entries = Settings::default().entries();
+        let get = |name: &str| entries.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+
+        assert_eq!(get("version"), Some("1"));
+        assert_eq!(get("general.startup_mode"), Some("restore-session"));
+        assert_eq!(get("general.home_page"), Some(HOME_PAGE));
+        assert_eq!(get("privacy.do_not_track"), Some("true"));
+        assert_eq!(get("developer.log_targets.fetcher"), Some("trace"));
+    }
+
+    #[test]
+    fn test_startup_mode_keys() {
+        for mode in StartupMode::ALL {
+            assert_eq!(StartupMode::from_key(mode.key()), Some(mode));
+        }
+        assert_eq!(StartupMode::from_key("nothing"), None);
+    }
+
+    #[test]
+    fn test_log_level_keys() {
+        for level in LogLevel::ALL {
+            assert_eq!(LogLevel::from_key(level.key()), Some(level));
+            // The keys are the names that are used in the settings file
+            assert_eq!(toml::Value::try_from(level).unwrap().as_str(), Some(level.key()));
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::containers::ContainerId;
 use crate::engine::GosubEngineConfig;
 use crate::fetcher::address_parser::GosubRenderMode;
+use crate::fetcher::network_profile::NetworkKind;
 use gosub_engine::prelude::HasTreeDrawer;
 use gtk4::gdk::Texture;
 use std::collections::{HashMap, VecDeque};
@@ -10,6 +12,10 @@ use std::sync::{Arc, Mutex};
 use url::Url;
 use uuid::Uuid;
 
+mod session_history;
+
+pub use session_history::{HistoryEntry, SessionHistory};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct TabId(Uuid);
 
@@ -49,16 +55,20 @@ pub struct GosubTab {
     render_mode: GosubRenderMode,
     /// Tab is currently loading
     loading: bool,
+    /// Tab has been restored, but its content is not loaded until it is shown for the first time
+    needs_load: bool,
     /// Id of the tab
     id: TabId,
     /// Tab is pinned and cannot be moved from the leftmost position
     pinned: bool,
     /// Tab content is private and not saved in history
     private: bool,
+    /// Container the tab belongs to (if any)
+    container: Option<ContainerId>,
     /// URL that is loaded into the tab
     url: Url,
-    /// History of the tab
-    history: Vec<Url>,
+    /// Back/forward history of the tab
+    history: SessionHistory,
     /// Title of the tab
     title: String,
     /// Loaded favicon of the tab
@@ -80,14 +90,19 @@ impl Debug for GosubTab {
 
 impl GosubTab {
     pub fn new(url: Url, title: &str) -> Self {
+        let mut history = SessionHistory::new();
+        history.push(HistoryEntry::new(url.clone(), title, GosubRenderMode::Rendered));
+
         GosubTab {
             render_mode: GosubRenderMode::Rendered,
             loading: false,
+            needs_load: false,
             id: TabId::new(),
             pinned: false,
             private: false,
+            container: None,
             url,
-            history: Vec::new(),
+            history,
             title: title.to_string(),
             favicon: None,
             content: String::new(),
@@ -95,7 +110,33 @@ impl GosubTab {
         }
     }
 
+    /// Creates a tab from a restored session history. The tab shows the current entry of the history.
+    pub fn from_history(history: SessionHistory) -> Option<Self> {
+        let entry = history.current()?.clone();
+
+        let mut tab = Self::new(entry.url, &entry.title);
+        tab.render_mode = entry.render_mode;
+        tab.history = history;
+        Some(tab)
+    }
+
+    /// Returns a copy of this tab with a new id, the same session history and render mode, and
+    /// nothing loaded yet
+    pub fn duplicate(&self) -> Self {
+        let mut tab = self.clone();
+        tab.id = TabId::new();
+        tab.drawer = Arc::new(Mutex::new(None));
+        tab.content = String::new();
+        tab.favicon = None;
+        tab.loading = true;
+        tab.needs_load = false;
+        tab
+    }
+
     pub(crate) fn set_render_mode(&mut self, mode: GosubRenderMode) {
+        if let Some(entry) = self.history.current_mut() {
+            entry.render_mode = mode.clone();
+        }
         self.render_mode = mode;
     }
     pub(crate) fn render_mode(&self) -> GosubRenderMode {
@@ -122,6 +163,14 @@ impl GosubTab {
         self.loading = loading;
     }
 
+    pub fn needs_load(&self) -> bool {
+        self.needs_load
+    }
+
+    pub fn set_needs_load(&mut self, needs_load: bool) {
+        self.needs_load = needs_load;
+    }
+
     pub fn id(&self) -> TabId {
         self.id
     }
@@ -146,6 +195,38 @@ impl GosubTab {
         self.private = private;
     }
 
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    pub fn set_container(&mut self, container: Option<ContainerId>) {
+        self.container = container;
+    }
+
+    pub fn container(&self) -> Option<ContainerId> {
+        self.container
+    }
+
+    /// Returns the network profile this tab should use for fetching
+    pub fn network_kind(&self) -> NetworkKind {
+        if self.private {
+            return NetworkKind::Private;
+        }
+        match self.container {
+            Some(container) => NetworkKind::Container(container),
+            None => NetworkKind::Default,
+        }
+    }
+
+    /// Sets the private flag and container based on the given network profile
+    pub fn set_network_kind(&mut self, kind: NetworkKind) {
+        self.private = kind == NetworkKind::Private;
+        self.container = match kind {
+            NetworkKind::Container(container) => Some(container),
+            _ => None,
+        };
+    }
+
     pub fn set_content(&mut self, content: &str) {
         self.content = content.to_string();
     }
@@ -154,19 +235,55 @@ impl GosubTab {
         &self.content
     }
 
-    pub fn set_url(&mut self, url: Url) {
-        self.url = url
+    pub fn history(&self) -> &SessionHistory {
+        &self.history
+    }
+
+    /// Navigates the tab to a new page, which is added to the session history
+    pub fn navigate(&mut self, url: Url, render_mode: GosubRenderMode) {
+        self.history.push(HistoryEntry::new(url.clone(), url.as_str(), render_mode.clone()));
+        self.url = url;
+        self.render_mode = render_mode;
+    }
+
+    /// Navigates the tab to the session history entry at the given index. Returns false when there
+    /// is no such entry.
+    pub fn navigate_history(&mut self, index: usize) -> bool {
+        let Some(entry) = self.history.go_to(index).cloned() else {
+            return false;
+        };
+
+        self.url = entry.url;
+        self.title = entry.title;
+        self.render_mode = entry.render_mode;
+        true
     }
 
-    pub fn add_to_history(&mut self, url: Url) {
-        self.history.push(url);
+    /// Undoes the last navigation, when it did not result in a page. The tab shows the previous
+    /// page again. Returns false when there is no previous page.
+    pub fn cancel_navigation(&mut self) -> bool {
+        if !self.history.remove_current() {
+            return false;
+        }
+
+        let entry = self.history.current().cloned().unwrap();
+        self.url = entry.url;
+        self.title = entry.title;
+        self.render_mode = entry.render_mode;
+        true
     }
 
-    pub fn pop_history(&mut self) -> Option<Url> {
-        self.history.pop()
+    /// Stores the scroll position of the current page, so it can be restored when navigating back
+    pub fn set_scroll_position(&mut self, position: f64) {
+        if let Some(entry) = self.history.current_mut() {
+            entry.scroll_position = position;
+        }
     }
 
     pub fn set_title(&mut self, title: &str) {
+        if let Some(entry) = self.history.current_mut() {
+            entry.title = title.to_string();
+        }
         self.title = title.to_string();
     }
 
@@ -179,6 +296,16 @@ impl GosubTab {
     }
 }
 
+/// Maximum number of closed tabs that are remembered, so they can be reopened
+const MAX_CLOSED_TABS: usize = 25;
+
+/// A tab that has been closed, together with the position it had in the tab bar
+#[derive(Debug, Clone)]
+struct ClosedTab {
+    tab: GosubTab,
+    position: usize,
+}
+
 #[derive(Debug)]
 pub enum TabCommand {
     Close(TabId), // Close index
@@ -199,6 +326,8 @@ pub struct GosubTabManager {
     unpinned_tab_order: VecDeque<TabId>,
     // list of commands to execute on the next tab notebook update
     commands: Vec<TabCommand>,
+    // Recently closed tabs, most recently closed first
+    closed_tabs: VecDeque<ClosedTab>,
 }
 
 impl Default for GosubTabManager {
@@ -214,6 +343,7 @@ impl GosubTabManager {
             unpinned_tab_order: VecDeque::new(),
             pinned_tab_order: VecDeque::new(),
             commands: Vec::new(),
+            closed_tabs: VecDeque::new(),
         }
     }
 
@@ -230,6 +360,11 @@ impl GosubTabManager {
         self.tabs.len()
     }
 
+    /// Returns true when there is at least one private tab open
+    pub(crate) fn has_private_tabs(&self) -> bool {
+        self.tabs.values().any(|tab| tab.is_private())
+    }
+
     /// Returns true when the given tab is the leftmost unpinned tab
     pub(crate) fn is_most_left_unpinned_tab(&self, tab_id: TabId) -> bool {
         self.unpinned_tab_order.front() == Some(&tab_id)
@@ -278,48 +413,179 @@ impl GosubTabManager {
         self.commands.push(TabCommand::Move(tab_id, self.pinned_tab_order.len() as u32));
     }
 
+    /// Adds the tab at the given position in the tab bar, or at the end when no position is given.
+    /// Pinned tabs are always placed before the unpinned tabs.
     pub fn add_tab(&mut self, tab: GosubTab, position: Option<usize>) -> TabId {
-        let mut real_position = position.unwrap_or(usize::MAX);
+        let tab_id = tab.id();
+        let real_position = self.insert_tab(tab, position);
+        self.commands.push(TabCommand::Insert(tab_id, real_position as u32));
 
-        if tab.is_pinned() {
-            if real_position > self.pinned_tab_order.len() {
-                self.pinned_tab_order.push_back(tab.id());
-                real_position = self.pinned_tab_order.len() - 1;
-            } else {
-                self.pinned_tab_order.insert(real_position, tab.id());
-            }
-        } else if real_position > self.unpinned_tab_order.len() {
-            self.unpinned_tab_order.push_back(tab.id());
-            real_position = self.unpinned_tab_order.len() - 1;
-        } else {
-            self.unpinned_tab_order.insert(real_position, tab.id());
+        tab_id
+    }
+
+    /// Adds a tab whose page is already in the tab bar at the given position, like a tab that has been
+    /// dragged here from another window. The page is only moved when the pinned tabs require it.
+    pub fn adopt_tab(&mut self, tab: GosubTab, position: usize) -> TabId {
+        let tab_id = tab.id();
+        let real_position = self.insert_tab(tab, Some(position));
+        if real_position != position {
+            self.commands.push(TabCommand::Move(tab_id, real_position as u32));
         }
 
-        self.commands.push(TabCommand::Insert(tab.id(), real_position as u32));
+        tab_id
+    }
+
+    /// Inserts the tab in the tab order, and returns its position in the tab bar
+    fn insert_tab(&mut self, tab: GosubTab, position: Option<usize>) -> usize {
+        let position = position.unwrap_or(usize::MAX);
+        let pinned_count = self.pinned_tab_order.len();
+
+        let real_position = if tab.is_pinned() {
+            let index = position.min(pinned_count);
+            self.pinned_tab_order.insert(index, tab.id());
+            index
+        } else {
+            let index = position.saturating_sub(pinned_count).min(self.unpinned_tab_order.len());
+            self.unpinned_tab_order.insert(index, tab.id());
+            pinned_count + index
+        };
 
-        let tab_id = tab.id;
-        self.tabs.insert(tab_id, tab);
+        self.tabs.insert(tab.id, tab);
         // self.set_active(tab_id);
 
-        tab_id
+        real_position
     }
 
+    /// Removes the tab. The tab is remembered in the list of closed tabs, unless it is private.
     pub fn remove_tab(&mut self, tab_id: TabId) {
-        if let Some(index) = self.unpinned_tab_order.iter().position(|id| id == &tab_id) {
-            self.unpinned_tab_order.remove(index);
-            self.commands.push(TabCommand::Close(tab_id));
-
-            // Set active tab to the last tab. Assumes there is always one tab
-            if index == 0 {
-                if let Some(new_active_tab) = self.unpinned_tab_order.front() {
-                    self.set_active(*new_active_tab);
-                }
-            } else if let Some(new_active_tab) = self.unpinned_tab_order.get(index - 1) {
-                self.set_active(*new_active_tab);
-            }
+        let Some((tab, position)) = self.take_tab(tab_id) else {
+            return;
+        };
+
+        if !tab.is_private() {
+            self.closed_tabs.push_front(ClosedTab { tab, position });
+            self.closed_tabs.truncate(MAX_CLOSED_TABS);
+        }
+    }
+
+    /// Removes the tab without remembering it as closed, so it can be moved to another window
+    pub fn detach_tab(&mut self, tab_id: TabId) -> Option<GosubTab> {
+        self.take_tab(tab_id).map(|(tab, _)| tab)
+    }
+
+    /// Removes the tab, and returns it together with the position it had in the tab bar
+    fn take_tab(&mut self, tab_id: TabId) -> Option<(GosubTab, usize)> {
+        let position = self.order().iter().position(|id| id == &tab_id)?;
+
+        self.pinned_tab_order.retain(|id| id != &tab_id);
+        self.unpinned_tab_order.retain(|id| id != &tab_id);
+        self.commands.push(TabCommand::Close(tab_id));
+
+        // Set active tab to the tab on the left, or the first tab when the leftmost tab is closed
+        if let Some(new_active_tab) = self.order().get(position.saturating_sub(1)) {
+            self.set_active(*new_active_tab);
+        }
+
+        let tab = self.tabs.remove(&tab_id)?;
+        Some((tab, position))
+    }
+
+    /// Removes all given tabs, and returns the tabs that have been removed
+    fn remove_tabs(&mut self, tab_ids: Vec<TabId>) -> Vec<TabId> {
+        let removed: Vec<TabId> = tab_ids.into_iter().filter(|tab_id| self.tabs.contains_key(tab_id)).collect();
+        for tab_id in &removed {
+            self.remove_tab(*tab_id);
+        }
+        removed
+    }
+
+    /// Closes the unpinned tabs left of the given tab, and activates the given tab. Returns the closed tabs.
+    pub fn close_tabs_left(&mut self, tab_id: TabId) -> Vec<TabId> {
+        let Some(index) = self.unpinned_tab_order.iter().position(|id| id == &tab_id) else {
+            return Vec::new();
+        };
+
+        let to_close = self.unpinned_tab_order.iter().take(index).copied().collect();
+        let closed = self.remove_tabs(to_close);
+        self.set_active(tab_id);
+        closed
+    }
+
+    /// Closes the unpinned tabs right of the given tab, and activates the given tab. Returns the closed tabs.
+    pub fn close_tabs_right(&mut self, tab_id: TabId) -> Vec<TabId> {
+        let to_close = match self.unpinned_tab_order.iter().position(|id| id == &tab_id) {
+            Some(index) => self.unpinned_tab_order.iter().skip(index + 1).copied().collect(),
+            // All unpinned tabs are on the right side of a pinned tab
+            None if self.pinned_tab_order.contains(&tab_id) => self.unpinned_tab_order.iter().copied().collect(),
+            None => return Vec::new(),
+        };
+
+        let closed = self.remove_tabs(to_close);
+        self.set_active(tab_id);
+        closed
+    }
+
+    /// Closes all unpinned tabs except the given tab, and activates the given tab. Returns the closed tabs.
+    pub fn close_other_tabs(&mut self, tab_id: TabId) -> Vec<TabId> {
+        if !self.tabs.contains_key(&tab_id) {
+            return Vec::new();
         }
 
-        self.tabs.remove(&tab_id);
+        let to_close = self.unpinned_tab_order.iter().filter(|id| **id != tab_id).copied().collect();
+        let closed = self.remove_tabs(to_close);
+        self.set_active(tab_id);
+        closed
+    }
+
+    /// Closes all unpinned tabs. Returns the closed tabs.
+    pub fn close_all_tabs(&mut self) -> Vec<TabId> {
+        let to_close = self.unpinned_tab_order.iter().copied().collect();
+        self.remove_tabs(to_close)
+    }
+
+    /// Duplicates the tab, including its session history and render mode. The copy is placed right
+    /// of the original tab. Returns the id of the copy.
+    pub fn duplicate_tab(&mut self, tab_id: TabId) -> Option<TabId> {
+        let tab = self.tabs.get(&tab_id)?.duplicate();
+        let position = self.order().iter().position(|id| id == &tab_id)? + 1;
+
+        let new_tab_id = self.add_tab(tab, Some(position));
+        self.notify_tab_changed(new_tab_id);
+        Some(new_tab_id)
+    }
+
+    /// Replaces the tab by a copy that fetches within the given network profile, like another container.
+    /// The copy keeps the position, pinned state, session history and render mode of the tab, and the
+    /// tab is not remembered as closed. Returns the id of the copy.
+    pub fn reopen_in_network(&mut self, tab_id: TabId, kind: NetworkKind) -> Option<TabId> {
+        let mut tab = self.tabs.get(&tab_id)?.duplicate();
+        tab.set_network_kind(kind);
+        let position = self.order().iter().position(|id| id == &tab_id)?;
+
+        let new_tab_id = self.add_tab(tab, Some(position));
+        self.take_tab(tab_id);
+        self.notify_tab_changed(new_tab_id);
+        self.set_active(new_tab_id);
+        Some(new_tab_id)
+    }
+
+    /// Returns the recently closed tabs, most recently closed first
+    pub fn closed_tabs(&self) -> impl Iterator<Item = &GosubTab> {
+        self.closed_tabs.iter().map(|closed| &closed.tab)
+    }
+
+    /// Reopens a closed tab at the position it had before, with its pinned state and session history.
+    /// Index 0 is the most recently closed tab.
+    pub fn reopen_closed_tab(&mut self, index: usize) -> Option<TabId> {
+        let ClosedTab { mut tab, position } = self.closed_tabs.remove(index)?;
+        tab.set_favicon(None);
+        tab.set_loading(true);
+
+        let tab_id = self.add_tab(tab, Some(position));
+        self.notify_tab_changed(tab_id);
+        self.set_active(tab_id);
+
+        Some(tab_id)
     }
 
     pub fn get_tab(&self, tab_id: TabId) -> Option<GosubTab> {
@@ -374,7 +640,10 @@ impl GosubTabManager {
 
 #[cfg(test)]
 mod test {
-    use super::{GosubTab, GosubTabManager, TabId};
+    use super::{GosubTab, GosubTabManager, TabCommand, TabId, MAX_CLOSED_TABS};
+    use crate::containers::ContainerId;
+    use crate::fetcher::address_parser::GosubRenderMode;
+    use crate::fetcher::network_profile::NetworkKind;
     use url::Url;
 
     #[test]
@@ -388,6 +657,26 @@ mod test {
         assert_eq!(id, id_parsed);
     }
 
+    #[test]
+    fn test_tab_navigation() {
+        let mut tab = GosubTab::new(Url::parse("about:blank").unwrap(), "New tab");
+        tab.navigate(Url::parse("https://example.com/data.json").unwrap(), GosubRenderMode::Json);
+        tab.set_title("Data");
+        tab.set_scroll_position(42.0);
+        tab.navigate(Url::parse("https://example.com/").unwrap(), GosubRenderMode::Rendered);
+        assert_eq!(tab.history().back_entries().len(), 2);
+
+        assert!(tab.navigate_history(1));
+        assert_eq!(tab.url().as_str(), "https://example.com/data.json");
+        assert_eq!(tab.title(), "Data");
+        assert_eq!(tab.render_mode(), GosubRenderMode::Json);
+        assert_eq!(tab.history().current().unwrap().scroll_position, 42.0);
+
+        assert!(tab.navigate_history(0));
+        assert_eq!(tab.title(), "New tab");
+        assert!(!tab.navigate_history(3));
+    }
+
     #[test]
     fn test_tab_manager() {
         let mut manager = GosubTabManager::new();
@@ -402,6 +691,23 @@ mod test {
         assert_eq!(manager.tab_count(), 0);
     }
 
+    #[test]
+    fn test_private_tabs() {
+        let mut manager = GosubTabManager::new();
+        let tab1 = GosubTab::new(Url::parse("about:blank").unwrap(), "New tab 1");
+        let mut tab2 = GosubTab::new(Url::parse("about:blank").unwrap(), "New tab 2");
+        tab2.set_private(true);
+
+        manager.add_tab(tab1, None);
+        assert!(!manager.has_private_tabs());
+
+        let tab2_id = manager.add_tab(tab2, None);
+        assert!(manager.has_private_tabs());
+
+        manager.remove_tab(tab2_id);
+        assert!(!manager.has_private_tabs());
+    }
+
     #[test]
     fn test_tab_manager_remove() {
         let mut manager = GosubTabManager::new();
@@ -420,6 +726,36 @@ mod test {
         assert_eq!(manager.order(), vec![tab1_id, tab3_id]);
     }
 
+    #[test]
+    fn test_detach_and_adopt_tab() {
+        let mut source = GosubTabManager::new();
+        let tab1_id = source.add_tab(GosubTab::new(Url::parse("about:blank").unwrap(), "New tab 1"), None);
+        let tab2_id = source.add_tab(GosubTab::new(Url::parse("about:blank").unwrap(), "New tab 2"), None);
+        source.commands();
+
+        // Detached tabs cannot be reopened, as they are not closed
+        let tab = source.detach_tab(tab2_id).unwrap();
+        assert_eq!(source.order(), vec![tab1_id]);
+        assert_eq!(source.closed_tabs().count(), 0);
+        assert!(source.detach_tab(tab2_id).is_none());
+
+        let mut target = GosubTabManager::new();
+        let mut pinned = GosubTab::new(Url::parse("about:blank").unwrap(), "Pinned");
+        pinned.set_pinned(true);
+        let pinned_id = target.add_tab(pinned, None);
+        target.commands();
+
+        // The page is already in the tab bar, so it is only moved when it lands between pinned tabs
+        assert_eq!(target.adopt_tab(tab, 1), tab2_id);
+        assert_eq!(target.order(), vec![pinned_id, tab2_id]);
+        assert!(target.commands().is_empty());
+
+        let tab = source.detach_tab(tab1_id).unwrap();
+        target.adopt_tab(tab, 0);
+        assert_eq!(target.order(), vec![pinned_id, tab1_id, tab2_id]);
+        assert!(matches!(target.commands().as_slice(), [TabCommand::Move(id, 1)] if *id == tab1_id));
+    }
+
     #[test]
     fn test_pinned_tabs() {
         let mut manager = GosubTabManager::new();
@@ -449,4 +785,162 @@ mod test {
         assert!(manager.is_most_right_tab(tab6_id));
         assert!(!manager.is_most_right_tab(tab5_id));
     }
+
+    /// Creates a manager with the tabs [ P1 P2 | 1 2 3 4 ], where P1 and P2 are pinned
+    fn manager_with_pinned_tabs() -> (GosubTabManager, Vec<TabId>) {
+        let mut manager = GosubTabManager::new();
+        let mut tab_ids = Vec::new();
+        for i in 1..=2 {
+            let mut tab = GosubTab::new(Url::parse("about:blank").unwrap(), &format!("Pinned {}", i));
+            tab.set_pinned(true);
+            tab_ids.push(manager.add_tab(tab, None));
+        }
+        for i in 1..=4 {
+            let tab = GosubTab::new(Url::parse("about:blank").unwrap(), &format!("Tab {}", i));
+            tab_ids.push(manager.add_tab(tab, None));
+        }
+        (manager, tab_ids)
+    }
+
+    #[test]
+    fn test_close_tabs_left() {
+        let (mut manager, ids) = manager_with_pinned_tabs();
+
+        assert_eq!(manager.close_tabs_left(ids[4]), vec![ids[2], ids[3]]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[4], ids[5]]);
+
+        // Nothing is left of the leftmost unpinned tab, and pinned tabs are never closed
+        assert!(manager.close_tabs_left(ids[4]).is_empty());
+        assert!(manager.close_tabs_left(ids[1]).is_empty());
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[4], ids[5]]);
+    }
+
+    #[test]
+    fn test_close_tabs_right() {
+        let (mut manager, ids) = manager_with_pinned_tabs();
+
+        assert_eq!(manager.close_tabs_right(ids[3]), vec![ids[4], ids[5]]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[2], ids[3]]);
+
+        assert_eq!(manager.close_tabs_right(ids[0]), vec![ids[2], ids[3]]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1]]);
+    }
+
+    #[test]
+    fn test_close_other_and_all_tabs() {
+        let (mut manager, ids) = manager_with_pinned_tabs();
+
+        assert_eq!(manager.close_other_tabs(ids[3]), vec![ids[2], ids[4], ids[5]]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[3]]);
+        assert!(manager.close_other_tabs(TabId::new()).is_empty());
+
+        assert_eq!(manager.close_all_tabs(), vec![ids[3]]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1]]);
+        assert_eq!(manager.closed_tabs().count(), 4);
+    }
+
+    #[test]
+    fn test_duplicate_tab() {
+        let (mut manager, ids) = manager_with_pinned_tabs();
+        let mut tab = manager.get_tab(ids[3]).unwrap();
+        tab.navigate(Url::parse("https://example.com/data.json").unwrap(), GosubRenderMode::Json);
+        manager.update_tab(ids[3], &tab);
+
+        let copy_id = manager.duplicate_tab(ids[3]).unwrap();
+        assert_ne!(copy_id, ids[3]);
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[2], ids[3], copy_id, ids[4], ids[5]]);
+
+        let copy = manager.get_tab(copy_id).unwrap();
+        assert_eq!(copy.url().as_str(), "https://example.com/data.json");
+        assert_eq!(copy.render_mode(), GosubRenderMode::Json);
+        assert!(copy.history().can_go_back());
+        assert!(copy.is_loading());
+
+        // A pinned tab stays pinned
+        let copy_id = manager.duplicate_tab(ids[0]).unwrap();
+        assert_eq!(&manager.order()[..3], &[ids[0], copy_id, ids[1]]);
+        assert!(manager.duplicate_tab(TabId::new()).is_none());
+    }
+
+    #[test]
+    fn test_reopen_in_network() {
+        let (mut manager, ids) = manager_with_pinned_tabs();
+        let mut tab = manager.get_tab(ids[3]).unwrap();
+        tab.navigate(Url::parse("https://example.com/data.json").unwrap(), GosubRenderMode::Json);
+        manager.update_tab(ids[3], &tab);
+
+        let container: ContainerId = "1".parse().unwrap();
+        manager.commands();
+        let copy_id = manager.reopen_in_network(ids[3], NetworkKind::Container(container)).unwrap();
+        assert_eq!(manager.order(), vec![ids[0], ids[1], ids[2], copy_id, ids[4], ids[5]]);
+        assert!(matches!(manager.commands().last(), Some(TabCommand::Activate(id)) if *id == copy_id));
+        assert!(manager.closed_tabs().next().is_none());
+
+        let copy = manager.get_tab(copy_id).unwrap();
+        assert_eq!(copy.network_kind(), NetworkKind::Container(container));
+        assert_eq!(copy.render_mode(), GosubRenderMode::Json);
+        assert!(copy.history().can_go_back());
+
+        // A pinned tab stays pinned at its position
+        let copy_id = manager.reopen_in_network(ids[1], NetworkKind::Container(container)).unwrap();
+        assert_eq!(&manager.order()[..3], &[ids[0], copy_id, ids[2]]);
+        assert!(manager.get_tab(copy_id).unwrap().is_pinned());
+        assert!(manager.reopen_in_network(TabId::new(), NetworkKind::Default).is_none());
+    }
+
+    #[test]
+    fn test_reopen_closed_tabs() {
+        let mut manager = GosubTabManager::new();
+        let mut tab1 = GosubTab::new(Url::parse("https://example.com/1").unwrap(), "Tab 1");
+        tab1.set_pinned(true);
+        let mut tab2 = GosubTab::new(Url::parse("https://example.com/2").unwrap(), "Tab 2");
+        tab2.navigate(Url::parse("https://example.com/2.json").unwrap(), GosubRenderMode::Json);
+        let tab3 = GosubTab::new(Url::parse("https://example.com/3").unwrap(), "Tab 3");
+        let mut tab4 = GosubTab::new(Url::parse("https://example.com/4").unwrap(), "Tab 4");
+        tab4.set_private(true);
+
+        let tab1_id = manager.add_tab(tab1, None);
+        let tab2_id = manager.add_tab(tab2, None);
+        let tab3_id = manager.add_tab(tab3, None);
+        let tab4_id = manager.add_tab(tab4, None);
+
+        manager.remove_tab(tab2_id);
+        manager.remove_tab(tab1_id);
+        manager.remove_tab(tab4_id);
+        assert_eq!(manager.order(), vec![tab3_id]);
+
+        // Private tabs are not remembered
+        let closed: Vec<TabId> = manager.closed_tabs().map(|tab| tab.id()).collect();
+        assert_eq!(closed, vec![tab1_id, tab2_id]);
+
+        // The pinned tab comes back as pinned tab
+        assert_eq!(manager.reopen_closed_tab(0), Some(tab1_id));
+        assert!(manager.get_tab(tab1_id).unwrap().is_pinned());
+        assert_eq!(manager.order(), vec![tab1_id, tab3_id]);
+
+        // The tab comes back at its old position, with its history and render mode
+        assert_eq!(manager.reopen_closed_tab(0), Some(tab2_id));
+        assert_eq!(manager.order(), vec![tab1_id, tab2_id, tab3_id]);
+        let tab2 = manager.get_tab(tab2_id).unwrap();
+        assert_eq!(tab2.render_mode(), GosubRenderMode::Json);
+        assert!(tab2.history().can_go_back());
+
+        assert_eq!(manager.reopen_closed_tab(0), None);
+    }
+
+    #[test]
+    fn test_closed_tabs_bounded() {
+        let mut manager = GosubTabManager::new();
+        for i in 0..MAX_CLOSED_TABS + 5 {
+            let tab = GosubTab::new(Url::parse("about:blank").unwrap(), &format!("Tab {}", i));
+            let tab_id = manager.add_tab(tab, None);
+            manager.remove_tab(tab_id);
+        }
+
+        assert_eq!(manager.closed_tabs().count(), MAX_CLOSED_TABS);
+        assert_eq!(
+            manager.closed_tabs().next().unwrap().title(),
+            format!("Tab {}", MAX_CLOSED_TABS + 4)
+        );
+    }
 }
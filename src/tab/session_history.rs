@@ -0,0 +1,226 @@
+use crate::fetcher::address_parser::GosubRenderMode;
+use url::Url;
+
+/// Maximum number of entries kept in the session history of a single tab
+const MAX_HISTORY_ENTRIES: usize = 50;
+
+/// A single page that has been visited in a tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub url: Url,
+    pub title: String,
+    /// Render mode the page was shown in, so `source:` or `json:` pages are restored as such
+    pub render_mode: GosubRenderMode,
+    /// Vertical scroll position of the page when the user navigated away from it
+    pub scroll_position: f64,
+}
+
+impl HistoryEntry {
+    pub fn new(url: Url, title: &str, render_mode: GosubRenderMode) -> Self {
+        Self {
+            url,
+            title: title.to_string(),
+            render_mode,
+            scroll_position: 0.0,
+        }
+    }
+}
+
+/// The back/forward history of a tab. Entries before the current index can be reached with "back",
+/// entries after the current index with "forward". Visiting a new page drops all forward entries.
+#[derive(Debug, Clone, Default)]
+pub struct SessionHistory {
+    entries: Vec<HistoryEntry>,
+    current: usize,
+}
+
+impl SessionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a history from previously stored entries
+    pub fn from_entries(mut entries: Vec<HistoryEntry>, current: usize) -> Self {
+        let dropped = entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        entries.drain(..dropped);
+        let current = current.saturating_sub(dropped).min(entries.len().saturating_sub(1));
+
+        Self { entries, current }
+    }
+
+    /// Adds a newly visited page after the current entry, and makes it the current entry
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.current + 1);
+        }
+        self.entries.push(entry);
+
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.current = self.entries.len() - 1;
+    }
+
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.entries.get(self.current)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.entries.get_mut(self.current)
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.entries.is_empty() && self.current > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current + 1 < self.entries.len()
+    }
+
+    /// Makes the entry at the given index the current entry, and returns it
+    pub fn go_to(&mut self, index: usize) -> Option<&HistoryEntry> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        self.current = index;
+        self.entries.get(index)
+    }
+
+    /// Removes the current entry when it is the last one, and makes the entry before it the current
+    /// entry. Used when a navigation does not result in a page, like a download. Returns false when
+    /// there is no entry to go back to.
+    pub fn remove_current(&mut self) -> bool {
+        if !self.can_go_back() || self.can_go_forward() {
+            return false;
+        }
+
+        self.entries.pop();
+        self.current -= 1;
+        true
+    }
+
+    /// Returns the indices and entries that can be reached with "back", nearest first
+    pub fn back_entries(&self) -> Vec<(usize, &HistoryEntry)> {
+        self.entries.iter().enumerate().take(self.current).rev().collect()
+    }
+
+    /// Returns the indices and entries that can be reached with "forward", nearest first
+    pub fn forward_entries(&self) -> Vec<(usize, &HistoryEntry)> {
+        self.entries.iter().enumerate().skip(self.current + 1).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str) -> HistoryEntry {
+        HistoryEntry::new(Url::parse(url).unwrap(), url, GosubRenderMode::Rendered)
+    }
+
+    #[test]
+    fn test_back_forward() {
+        let mut history = SessionHistory::new();
+        assert!(history.current().is_none());
+        assert!(!history.can_go_back());
+        assert!(!history.can_go_forward());
+
+        history.push(entry("https://example.com/1"));
+        history.push(entry("https://example.com/2"));
+        history.push(entry("https://example.com/3"));
+        assert!(history.can_go_back());
+        assert!(!history.can_go_forward());
+
+        assert_eq!(history.go_to(1).unwrap().url.as_str(), "https://example.com/2");
+        assert!(history.can_go_back());
+        assert!(history.can_go_forward());
+
+        let back: Vec<usize> = history.back_entries().iter().map(|(i, _)| *i).collect();
+        let forward: Vec<usize> = history.forward_entries().iter().map(|(i, _)| *i).collect();
+        assert_eq!(back, vec![0]);
+        assert_eq!(forward, vec![2]);
+
+        assert!(history.go_to(3).is_none());
+        assert_eq!(history.current_index(), 1);
+
+        // Visiting a new page drops the forward entries
+        history.push(entry("https://example.com/4"));
+        assert_eq!(history.entries.len(), 3);
+        assert_eq!(history.current().unwrap().url.as_str(), "https://example.com/4");
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn test_remove_current() {
+        let mut history = SessionHistory::new();
+        history.push(entry("https://example.com/1"));
+        assert!(!history.remove_current());
+
+        history.push(entry("https://example.com/file.zip"));
+        assert!(history.remove_current());
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.current().unwrap().url.as_str(), "https://example.com/1");
+
+        // Entries in the middle of the history are kept
+        history.push(entry("https://example.com/2"));
+        history.go_to(0);
+        assert!(!history.remove_current());
+        assert_eq!(history.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_render_mode_and_scroll() {
+        let mut history = SessionHistory::new();
+        history.push(HistoryEntry::new(
+            Url::parse("https://example.com/data.json").unwrap(),
+            "data",
+            GosubRenderMode::Json,
+        ));
+        history.current_mut().unwrap().scroll_position = 120.0;
+        history.push(entry("https://example.com/"));
+
+        let previous = history.go_to(0).unwrap();
+        assert_eq!(previous.render_mode, GosubRenderMode::Json);
+        assert_eq!(previous.scroll_position, 120.0);
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let mut history = SessionHistory::new();
+        for i in 0..MAX_HISTORY_ENTRIES + 5 {
+            history.push(entry(&format!("https://example.com/{}", i)));
+        }
+
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.current_index(), MAX_HISTORY_ENTRIES - 1);
+        assert_eq!(history.entries[0].url.as_str(), "https://example.com/5");
+    }
+
+    #[test]
+    fn test_from_entries() {
+        let history = SessionHistory::from_entries(vec![entry("https://example.com/1"), entry("https://example.com/2")], 5);
+        assert_eq!(history.current_index(), 1);
+        assert!(history.can_go_back());
+
+        let history = SessionHistory::from_entries(Vec::new(), 0);
+        assert!(history.current().is_none());
+
+        // The oldest entries are dropped, and the current entry stays the same page
+        let entries: Vec<HistoryEntry> = (0..MAX_HISTORY_ENTRIES + 10)
+            .map(|i| entry(&format!("https://example.com/{}", i)))
+            .collect();
+        let history = SessionHistory::from_entries(entries.clone(), 30);
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.current().unwrap().url.as_str(), "https://example.com/30");
+        let history = SessionHistory::from_entries(entries, 3);
+        assert_eq!(history.current_index(), 0);
+    }
+}
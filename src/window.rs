@@ -1,19 +1,31 @@
 use gtk4::glib;
-use gtk4::glib::{clone, spawn_future_local};
+use gtk4::glib::{clone, spawn_future_local, VariantTy};
 
+mod address_completion;
+mod find_bar;
+mod history_menu;
 mod imp;
 mod message;
+mod page_links;
 mod tab_context_menu;
 
 use crate::application::Application;
+use crate::fetcher::network_profile::NetworkKind;
 use crate::runtime;
+use crate::session::WindowSession;
+use crate::settings::{settings, StartupMode};
+use crate::tab::GosubTab;
 use crate::window::imp::WidgetExtTabId;
 use crate::window::message::Message;
 use gtk4::gio;
 use gtk4::gio::SimpleAction;
+use gtk4::glib::Propagation;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::ObjectSubclassIsExt;
 
+/// Group name of the tab bars, so tabs can be dragged between the tab bars of all windows
+const TAB_GROUP: &str = "gosub-tabs";
+
 // This wrapper must be in a different module than the implementation, because both will define a
 // `struct BrowserWindow` and they would clash. In this case, the browser window is a subclass of
 // its implementation.
@@ -24,21 +36,68 @@ glib::wrapper! {
 }
 
 impl BrowserWindow {
+    /// Creates a window that shows the start page
     pub fn new(app: &Application) -> Self {
+        Self::with_urls(app, None, Vec::new(), NetworkKind::Default)
+    }
+
+    /// Creates a window that restores the tabs of the given session, and opens the given URLs in new
+    /// tabs. The start page is opened when there is nothing else to show.
+    pub fn with_urls(app: &Application, session: Option<WindowSession>, urls: Vec<String>, network: NetworkKind) -> Self {
+        let window = Self::empty(app);
+        let startup_mode = settings().general.startup_mode;
+
+        // Restore the previous session and open the URLs, or open the start page
+        let window_clone = window.clone();
+        spawn_future_local(async move {
+            let restored = session.is_some_and(|session| window_clone.imp().restore_session(&session));
+
+            if !urls.is_empty() {
+                window_clone.open_urls(urls, network);
+            } else if !restored {
+                let url = match startup_mode {
+                    StartupMode::Blank => "about:blank".to_string(),
+                    _ => settings().general.home_page.clone(),
+                };
+                window_clone
+                    .imp()
+                    .get_sender()
+                    .send(Message::OpenTab(url, "New Tab".to_string()))
+                    .await
+                    .unwrap();
+            }
+
+            // Refresh tabs on startup
+            window_clone.imp().get_sender().send(Message::RefreshTabs()).await.unwrap();
+        });
+
+        window
+    }
+
+    /// Creates a window that shows a tab that has been moved from another window
+    pub fn with_tab(app: &Application, tab: GosubTab) -> Self {
+        let window = Self::empty(app);
+        window.imp().attach_tab(tab, None);
+        window
+    }
+
+    /// Creates a window without any tabs
+    fn empty(app: &Application) -> Self {
         let window: Self = glib::Object::builder().property("application", app).build();
 
         window.set_resizable(true);
         window.set_decorated(true);
         window.set_default_size(1024, 768);
 
-        let builder = gtk4::Builder::from_resource("/io/gosub/browser-gtk/ui/main_menu.ui");
-        let menubar = builder.object::<gio::MenuModel>("app-menu").expect("Could not find app-menu");
-
-        app.set_menubar(Some(&menubar));
+        // The menu bar is shared by all windows, and is set up by the application
         window.set_show_menubar(true);
+        window.imp().log_scroller.set_visible(settings().developer.show_log);
 
-        Self::connect_actions(app, &window);
+        Self::connect_actions(&window);
         Self::connect_accelerators(app, &window);
+        address_completion::setup_address_completion(&window);
+        find_bar::setup_find_bar(&window);
+        history_menu::setup_history_menu(&window);
 
         // Spawn handler
         let window_clone = window.clone();
@@ -56,42 +115,53 @@ impl BrowserWindow {
             }
         });
 
-        // Refresh tabs on startup
-        let window_clone = window.clone();
-        spawn_future_local(async move {
-            let initial_urls = [
-                "gopher://gopher.meulie.net",
-                // "https://gosub.io",
-                // "source:https://httpbin.org/json",
-                // "source:https://gosub.io/test.html",
-                // "https://www.microsoft.com/en-us/",
-                // "https://github.com",
-                // "https://reddit.com",
-            ];
-
-            for url in initial_urls.iter() {
-                window_clone
-                    .imp()
-                    .get_sender()
-                    .send(Message::OpenTab(url.to_string(), "New Tab".to_string()))
-                    .await
-                    .unwrap();
+        window.connect_close_request(|window| {
+            if let Some(app) = window.application().and_downcast::<Application>() {
+                app.browser_window_closing(window);
             }
+            Propagation::Proceed
+        });
 
-            // Refresh tabs on startup
-            window_clone.imp().get_sender().send(Message::RefreshTabs()).await.unwrap();
+        // The "Recently Closed" menu shows the tabs that were closed in the active window
+        window.connect_is_active_notify(|window| {
+            if window.is_active() {
+                window.imp().update_recently_closed_menu();
+            }
         });
 
         window
     }
 
+    /// Opens the URLs in new tabs of this window
+    pub fn open_urls(&self, urls: Vec<String>, network: NetworkKind) {
+        let sender = self.imp().get_sender();
+        spawn_future_local(async move {
+            for url in urls {
+                let message = match network {
+                    NetworkKind::Private => Message::OpenPrivateTab(url, "New Tab".to_string()),
+                    _ => Message::OpenTab(url, "New Tab".to_string()),
+                };
+                sender.send(message).await.unwrap();
+            }
+        });
+    }
+
     fn connect_accelerators(app: &Application, _window: &Self) {
-        app.set_accels_for_action("app.open-new-tab", &["<Primary>T"]);
-        app.set_accels_for_action("app.close-tab", &["<Primary>W"]);
-        app.set_accels_for_action("app.toggle-log", &["<Primary>L"]);
+        app.set_accels_for_action("win.open-new-tab", &["<Primary>T"]);
+        app.set_accels_for_action("win.open-new-private-tab", &["<Primary><Shift>P"]);
+        app.set_accels_for_action("win.close-tab", &["<Primary>W"]);
+        app.set_accels_for_action("win.toggle-log", &["<Primary>L"]);
+        app.set_accels_for_action("win.go-back", &["<Alt>Left"]);
+        app.set_accels_for_action("win.go-forward", &["<Alt>Right"]);
+        app.set_accels_for_action("win.reopen-closed-tab(0)", &["<Primary><Shift>T"]);
+        app.set_accels_for_action("win.bookmark-page", &["<Primary>D"]);
+        app.set_accels_for_action("win.reload", &["<Primary>R", "F5"]);
+        app.set_accels_for_action("win.find", &["<Primary>F"]);
+        app.set_accels_for_action("win.find-next", &["F3", "<Primary>G"]);
+        app.set_accels_for_action("win.find-previous", &["<Shift>F3", "<Primary><Shift>G"]);
     }
 
-    fn connect_actions(app: &Application, window: &Self) {
+    fn connect_actions(window: &Self) {
         let logwindow_action = SimpleAction::new("toggle-log", None);
         logwindow_action.connect_activate({
             let window_clone = window.clone();
@@ -102,7 +172,7 @@ impl BrowserWindow {
                     .set_visible(!window_clone.imp().log_scroller.get_visible());
             }
         });
-        app.add_action(&logwindow_action);
+        window.add_action(&logwindow_action);
 
         // Create new tab
         let window_clone = window.clone();
@@ -117,15 +187,148 @@ impl BrowserWindow {
                 }
             ));
         });
-        app.add_action(&new_tab_action);
+        window.add_action(&new_tab_action);
+
+        // Create new private tab
+        let window_clone = window.clone();
+        let new_private_tab_action = SimpleAction::new("open-new-private-tab", None);
+        new_private_tab_action.connect_activate(move |_, _| {
+            let sender = window_clone.imp().sender.clone();
+            runtime().spawn(clone!(
+                #[strong]
+                sender,
+                async move {
+                    sender
+                        .send(Message::OpenPrivateTab("about:blank".into(), "Private Tab".into()))
+                        .await
+                        .unwrap();
+                }
+            ));
+        });
+        window.add_action(&new_private_tab_action);
+
+        // Navigate through the session history of the current tab
+        let go_back_action = SimpleAction::new("go-back", None);
+        go_back_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                window_clone.imp().go_back();
+            }
+        });
+        window.add_action(&go_back_action);
+
+        let go_forward_action = SimpleAction::new("go-forward", None);
+        go_forward_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                window_clone.imp().go_forward();
+            }
+        });
+        window.add_action(&go_forward_action);
+
+        let reload_action = SimpleAction::new("reload", None);
+        reload_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                window_clone.imp().reload_current_tab();
+            }
+        });
+        window.add_action(&reload_action);
+
+        // Reopen a closed tab. The parameter is the index in the list of closed tabs.
+        let reopen_closed_tab_action = SimpleAction::new("reopen-closed-tab", Some(VariantTy::INT32));
+        reopen_closed_tab_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, param| {
+                let index = param.and_then(|p| p.get::<i32>()).unwrap_or(0);
+                window_clone.imp().reopen_closed_tab(index.max(0) as usize);
+            }
+        });
+        window.add_action(&reopen_closed_tab_action);
+
+        // Bookmark the page of the current tab
+        let bookmark_page_action = SimpleAction::new("bookmark-page", None);
+        bookmark_page_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                window_clone.imp().bookmark_current_page();
+            }
+        });
+        window.add_action(&bookmark_page_action);
+
+        // Open a page from the bookmarks menu or the bookmarks and history windows in the current tab.
+        // The parameter is the URL.
+        let open_url_action = SimpleAction::new("open-url", Some(VariantTy::STRING));
+        open_url_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, param| {
+                let Some(url) = param.and_then(|p| p.get::<String>()) else {
+                    return;
+                };
+                let Some(tab_id) = window_clone.imp().current_tab_id() else {
+                    return;
+                };
+
+                let sender = window_clone.imp().get_sender();
+                runtime().spawn(async move {
+                    sender.send(Message::LoadUrl(tab_id, url)).await.unwrap();
+                });
+            }
+        });
+        window.add_action(&open_url_action);
+
+        // Find text in the page of the current tab
+        let find_action = SimpleAction::new("find", None);
+        find_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                find_bar::show_find_bar(&window_clone);
+            }
+        });
+        window.add_action(&find_action);
+
+        let find_next_action = SimpleAction::new("find-next", None);
+        find_next_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                find_bar::find_next(&window_clone, true);
+            }
+        });
+        window.add_action(&find_next_action);
+
+        let find_previous_action = SimpleAction::new("find-previous", None);
+        find_previous_action.connect_activate({
+            let window_clone = window.clone();
+            move |_, _| {
+                find_bar::find_next(&window_clone, false);
+            }
+        });
+        window.add_action(&find_previous_action);
 
         let tab_bar = window.imp().tab_bar.clone();
+        tab_bar.set_group_name(Some(TAB_GROUP));
         tab_bar.connect_page_added({
             let window_clone = window.clone();
-            move |_notebook, _, page_num| {
+            move |_notebook, page, page_num| {
                 window_clone
                     .imp()
                     .log(format!("[result] added a tab on page {}", page_num).as_str());
+
+                // Pages of other windows are dropped here when a tab is dragged between windows
+                if let Some(tab_id) = page.get_tab_id() {
+                    window_clone.imp().adopt_dropped_tab(tab_id, page_num as usize);
+                }
+            }
+        });
+
+        // A tab that is dropped outside of any tab bar is moved to a new window
+        tab_bar.connect_create_window({
+            let window_clone = window.clone();
+            move |_notebook, _page| {
+                let app = window_clone.application().and_downcast::<Application>()?;
+                let window = Self::empty(&app);
+                window.present();
+                Some(window.imp().tab_bar.get())
             }
         });
 
@@ -153,10 +356,28 @@ impl BrowserWindow {
                 if let Some(tab_id) = page.get_tab_id() {
                     let manager = window_clone.imp().tab_manager.lock().unwrap();
                     let tab = manager.get_tab(tab_id).unwrap();
-                    window_clone.imp().searchbar.set_text(tab.url().as_str());
                     drop(manager);
+                    window_clone.imp().set_searchbar_url(tab.url());
+                    window_clone.imp().update_navigation_buttons(tab_id);
+
+                    // Restored tabs are loaded when they are shown for the first time
+                    if window_clone.imp().needs_load(tab_id) {
+                        let sender = window_clone.imp().get_sender();
+                        runtime().spawn(async move {
+                            sender.send(Message::LoadTab(tab_id)).await.unwrap();
+                        });
+                    }
                 }
             }
         });
+
+        // The find bar searches the tab that is shown. The page has changed when this is notified,
+        // unlike in the switch-page handler.
+        tab_bar.connect_page_notify({
+            let window_clone = window.clone();
+            move |_notebook| {
+                find_bar::update_find(&window_clone);
+            }
+        });
     }
 }
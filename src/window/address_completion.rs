@@ -0,0 +1,345 @@
+use crate::completion::{
+    inline_completion, parse_search_suggestions, provider_candidates, rank, CompletionCandidate, CompletionKind, Suggestion,
+    MAX_SUGGESTIONS,
+};
+use crate::fetcher::fetch_background;
+use crate::fetcher::search_engine::search_engines;
+use crate::runtime;
+use crate::window::message::Message;
+use crate::window::BrowserWindow;
+use chrono::Utc;
+use gtk4::gdk::Key;
+use gtk4::glib::Propagation;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::ObjectSubclassIsExt;
+use gtk4::{
+    glib, Align, Entry, EventControllerFocus, EventControllerKey, Image, Label, ListBox, Orientation, Popover, PositionType,
+    PropagationPhase, SelectionMode, StateFlags,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+use url::Url;
+
+/// Time to wait for the user to stop typing before search suggestions are fetched
+const SUGGEST_DELAY: Duration = Duration::from_millis(200);
+
+/// State of the suggestions popover of the address bar
+#[derive(Default)]
+struct CompletionState {
+    /// Suggestions that are currently shown, in the same order as the rows in the list
+    suggestions: RefCell<Vec<Suggestion>>,
+    /// Set while the text of the entry is changed by us instead of by the user
+    updating: Cell<bool>,
+    /// Length of the text the user typed the previous time, used to detect deletions
+    typed_len: Cell<usize>,
+    /// Incremented on every change, so outdated search suggestions can be discarded
+    generation: Cell<u64>,
+}
+
+/// Adds a suggestions popover to the address bar of the window, with suggestions from the open tabs,
+/// the registered completion providers (history, bookmarks) and the default search engine.
+pub(crate) fn setup_address_completion(window: &BrowserWindow) {
+    let entry = window.imp().searchbar.get();
+    let state = Rc::new(CompletionState::default());
+
+    let list = ListBox::builder().selection_mode(SelectionMode::Single).build();
+    list.set_focusable(false);
+
+    let popover = Popover::builder()
+        .child(&list)
+        .position(PositionType::Bottom)
+        .has_arrow(false)
+        .autohide(false)
+        .can_focus(false)
+        .halign(Align::Start)
+        .build();
+    popover.set_parent(&entry);
+
+    entry.connect_changed({
+        let window = window.clone();
+        let state = state.clone();
+        let list = list.clone();
+        let popover = popover.clone();
+        move |entry| {
+            // Text that is set programmatically (like switching tabs) does not trigger suggestions
+            if state.updating.get() || window.imp().searchbar_updating.get() || !entry.state_flags().contains(StateFlags::FOCUS_WITHIN) {
+                return;
+            }
+            update_suggestions(&window, entry, &state, &list, &popover);
+        }
+    });
+
+    list.connect_row_activated({
+        let window = window.clone();
+        let state = state.clone();
+        let popover = popover.clone();
+        move |_, row| {
+            activate_suggestion(&window, &state, &popover, row.index());
+        }
+    });
+
+    let key_controller = EventControllerKey::new();
+    key_controller.set_propagation_phase(PropagationPhase::Capture);
+    key_controller.connect_key_pressed({
+        let window = window.clone();
+        let state = state.clone();
+        let list = list.clone();
+        let popover = popover.clone();
+        move |_, key, _, _| {
+            if !popover.is_visible() {
+                return Propagation::Proceed;
+            }
+
+            let count = state.suggestions.borrow().len() as i32;
+            let selected = list.selected_row().map(|row| row.index());
+            match key {
+                Key::Down => {
+                    let next = selected.map(|index| (index + 1).min(count - 1)).unwrap_or(0);
+                    list.select_row(list.row_at_index(next).as_ref());
+                    Propagation::Stop
+                }
+                Key::Up => {
+                    match selected {
+                        Some(index) if index > 0 => list.select_row(list.row_at_index(index - 1).as_ref()),
+                        _ => list.unselect_all(),
+                    }
+                    Propagation::Stop
+                }
+                Key::Escape => {
+                    popover.popdown();
+                    Propagation::Stop
+                }
+                Key::Return | Key::KP_Enter => match selected {
+                    Some(index) => {
+                        activate_suggestion(&window, &state, &popover, index);
+                        Propagation::Stop
+                    }
+                    None => {
+                        popover.popdown();
+                        Propagation::Proceed
+                    }
+                },
+                _ => Propagation::Proceed,
+            }
+        }
+    });
+    entry.add_controller(key_controller);
+
+    // Hide the suggestions when the address bar loses focus. This is delayed, so a click on a
+    // suggestion is handled before the popover disappears.
+    let focus_controller = EventControllerFocus::new();
+    focus_controller.connect_leave({
+        let entry = entry.clone();
+        let popover = popover.clone();
+        move |_| {
+            let entry = entry.clone();
+            let popover = popover.clone();
+            glib::timeout_add_local_once(Duration::from_millis(150), move || {
+                if !entry.state_flags().contains(StateFlags::FOCUS_WITHIN) {
+                    popover.popdown();
+                }
+            });
+        }
+    });
+    entry.add_controller(focus_controller);
+}
+
+/// Recalculates the suggestions for the text in the entry
+fn update_suggestions(window: &BrowserWindow, entry: &Entry, state: &Rc<CompletionState>, list: &ListBox, popover: &Popover) {
+    let text = entry.text().to_string();
+    let generation = state.generation.get() + 1;
+    state.generation.set(generation);
+
+    // When the user removes text, we should not complete it again
+    let deleting = text.len() <= state.typed_len.get();
+    state.typed_len.set(text.len());
+
+    if text.trim().is_empty() {
+        state.suggestions.borrow_mut().clear();
+        popover.popdown();
+        return;
+    }
+
+    let mut candidates = open_tab_candidates(window);
+    candidates.extend(provider_candidates(&text, MAX_SUGGESTIONS));
+    let suggestions = rank(&text, candidates, Utc::now(), MAX_SUGGESTIONS);
+
+    let at_end = entry.position() == text.chars().count() as i32;
+    if !deleting && at_end {
+        if let Some(completed) = inline_completion(&text, &suggestions) {
+            state.updating.set(true);
+            entry.set_text(&completed);
+            entry.select_region(text.chars().count() as i32, -1);
+            state.updating.set(false);
+        }
+    }
+
+    *state.suggestions.borrow_mut() = suggestions;
+    show_suggestions(entry, state, list, popover);
+
+    // Text typed in a private tab is not sent to the search engine before the user searches for it
+    let imp = window.imp();
+    let private = imp
+        .current_tab_id()
+        .and_then(|tab_id| imp.tab_manager.lock().unwrap().get_tab(tab_id))
+        .is_some_and(|tab| tab.is_private());
+    if !private {
+        fetch_search_suggestions(entry, state, list, popover, text, generation);
+    }
+}
+
+/// Returns all open tabs of the window as candidates
+fn open_tab_candidates(window: &BrowserWindow) -> Vec<CompletionCandidate> {
+    let manager = window.imp().tab_manager.lock().unwrap();
+    manager
+        .order()
+        .into_iter()
+        .filter_map(|tab_id| manager.get_tab(tab_id))
+        .map(|tab| CompletionCandidate {
+            url: tab.url().to_string(),
+            title: tab.title().to_string(),
+            kind: CompletionKind::OpenTab(tab.id()),
+            visit_count: 0,
+            last_visit: None,
+        })
+        .collect()
+}
+
+/// Asks the default search engine for suggestions, and adds them to the list when they arrive. The
+/// search engine is only asked once the user stops typing, and without cookies, so the search engine
+/// cannot link the typed text to the user.
+fn fetch_search_suggestions(entry: &Entry, state: &Rc<CompletionState>, list: &ListBox, popover: &Popover, query: String, generation: u64) {
+    let engines = search_engines();
+    let Some(engine) = engines.default_engine().cloned() else {
+        return;
+    };
+    let Some(suggest_url) = engine.suggest_url(&query) else {
+        return;
+    };
+
+    let entry = entry.clone();
+    let state = state.clone();
+    let list = list.clone();
+    let popover = popover.clone();
+    glib::spawn_future_local(async move {
+        // Nothing is fetched when the user has typed something else in the meantime, and responses to
+        // an older query are dropped
+        glib::timeout_future(SUGGEST_DELAY).await;
+        if state.generation.get() != generation {
+            return;
+        }
+
+        let Ok(Ok(body)) = runtime().spawn(fetch_background(suggest_url)).await else {
+            return;
+        };
+        if state.generation.get() != generation {
+            return;
+        }
+
+        let mut suggestions = state.suggestions.borrow_mut();
+        for text in parse_search_suggestions(String::from_utf8_lossy(&body).as_ref()) {
+            if suggestions.len() >= MAX_SUGGESTIONS {
+                break;
+            }
+            let Ok(url) = engine.search_url(&text) else {
+                continue;
+            };
+            suggestions.push(Suggestion {
+                candidate: CompletionCandidate {
+                    url: url.to_string(),
+                    title: text,
+                    kind: CompletionKind::SearchSuggestion,
+                    visit_count: 0,
+                    last_visit: None,
+                },
+                score: 0.0,
+            });
+        }
+        drop(suggestions);
+
+        show_suggestions(&entry, &state, &list, &popover);
+    });
+}
+
+/// Fills the list with the current suggestions, and shows or hides the popover
+fn show_suggestions(entry: &Entry, state: &CompletionState, list: &ListBox, popover: &Popover) {
+    list.remove_all();
+
+    let suggestions = state.suggestions.borrow();
+    if suggestions.is_empty() {
+        popover.popdown();
+        return;
+    }
+
+    for suggestion in suggestions.iter() {
+        let candidate = &suggestion.candidate;
+        let hbox = gtk4::Box::new(Orientation::Horizontal, 10);
+
+        let icon = match candidate.kind {
+            CompletionKind::History => "document-open-recent-symbolic",
+            CompletionKind::Bookmark => "starred-symbolic",
+            CompletionKind::OpenTab(_) => "view-paged-symbolic",
+            CompletionKind::SearchSuggestion => "edit-find-symbolic",
+        };
+        hbox.append(&Image::from_icon_name(icon));
+
+        let title = Label::new(Some(candidate.title.as_str()));
+        title.set_halign(Align::Start);
+        title.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        hbox.append(&title);
+
+        if candidate.kind != CompletionKind::SearchSuggestion {
+            let url = Label::new(Some(candidate.url.as_str()));
+            url.set_halign(Align::Start);
+            url.set_hexpand(true);
+            url.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+            url.add_css_class("dim-label");
+            hbox.append(&url);
+        }
+
+        if let CompletionKind::OpenTab(_) = candidate.kind {
+            let switch = Label::new(Some("Switch to tab"));
+            switch.add_css_class("dim-label");
+            hbox.append(&switch);
+        }
+
+        list.append(&hbox);
+    }
+
+    popover.set_size_request(entry.width(), -1);
+    if !popover.is_visible() {
+        popover.popup();
+    }
+}
+
+/// Opens the suggestion at the given index: switches to the tab for open tabs, and loads the URL in
+/// the active tab for everything else.
+fn activate_suggestion(window: &BrowserWindow, state: &CompletionState, popover: &Popover, index: i32) {
+    popover.popdown();
+
+    let Some(suggestion) = state.suggestions.borrow().get(index as usize).cloned() else {
+        return;
+    };
+    let candidate = suggestion.candidate;
+
+    if let CompletionKind::OpenTab(tab_id) = candidate.kind {
+        if let Some(page_num) = window.imp().get_page_num_for_tab(tab_id) {
+            window.imp().tab_bar.set_current_page(Some(page_num));
+            return;
+        }
+    }
+
+    let Some(tab_id) = window.imp().current_tab_id() else {
+        return;
+    };
+
+    if let Ok(url) = Url::parse(&candidate.url) {
+        window.imp().set_searchbar_url(&url);
+    }
+
+    let sender = window.imp().get_sender();
+    runtime().spawn(async move {
+        sender.send(Message::LoadUrl(tab_id, candidate.url)).await.unwrap();
+    });
+}
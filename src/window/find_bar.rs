@@ -0,0 +1,354 @@
+use crate::engine::text_runs;
+use crate::find::{find_in_runs, find_regex, FindOptions, PageMatches};
+use crate::tab::TabId;
+use crate::window::imp::page_drawer;
+use crate::window::BrowserWindow;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::ObjectSubclassIsExt;
+use gtk4::{cairo, DrawingArea, ScrolledWindow, Viewport, Widget};
+use sourceview5::prelude::*;
+use sourceview5::{SearchContext, SearchSettings, View};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Matches on the rendered page of a tab, shared with the drawing area that highlights them
+pub(crate) type SharedPageMatches = Rc<RefCell<Option<(TabId, PageMatches)>>>;
+
+/// Space that is kept above a match on a rendered page when scrolling to it
+const SCROLL_MARGIN: f64 = 50.0;
+
+/// The content of the current tab that the find bar searches in
+pub(crate) enum FindTarget {
+    /// The source view of a tab in source, XML or JSON mode
+    Source { view: View, context: SearchContext },
+    /// The rendered page of a tab
+    Rendered { area: DrawingArea, scroller: ScrolledWindow },
+}
+
+impl FindTarget {
+    fn widget(&self) -> Widget {
+        match self {
+            FindTarget::Source { view, .. } => view.clone().upcast(),
+            FindTarget::Rendered { area, .. } => area.clone().upcast(),
+        }
+    }
+}
+
+/// Connects the find bar, which searches the content of the current tab
+pub(crate) fn setup_find_bar(window: &BrowserWindow) {
+    let imp = window.imp();
+    imp.find_bar.connect_entry(&imp.find_entry.get());
+
+    imp.find_entry.connect_search_changed({
+        let window = window.clone();
+        move |_| search(&window)
+    });
+    for option in [&imp.find_match_case, &imp.find_whole_word, &imp.find_regex] {
+        option.connect_toggled({
+            let window = window.clone();
+            move |_| search(&window)
+        });
+    }
+
+    // Enter and Ctrl+G go to the next match, Ctrl+Shift+G goes to the previous match
+    imp.find_entry.connect_activate({
+        let window = window.clone();
+        move |_| find_next(&window, true)
+    });
+    imp.find_entry.connect_next_match({
+        let window = window.clone();
+        move |_| find_next(&window, true)
+    });
+    imp.find_entry.connect_previous_match({
+        let window = window.clone();
+        move |_| find_next(&window, false)
+    });
+    imp.find_next.connect_clicked({
+        let window = window.clone();
+        move |_| find_next(&window, true)
+    });
+    imp.find_prev.connect_clicked({
+        let window = window.clone();
+        move |_| find_next(&window, false)
+    });
+
+    // Closing the find bar removes the highlights
+    imp.find_bar.connect_search_mode_enabled_notify({
+        let window = window.clone();
+        move |bar| {
+            if !bar.is_search_mode() {
+                clear(&window);
+            }
+        }
+    });
+}
+
+/// Shows the find bar and selects the text in it, so a new search can be typed right away
+pub(crate) fn show_find_bar(window: &BrowserWindow) {
+    let imp = window.imp();
+    imp.find_bar.set_search_mode(true);
+    imp.find_entry.grab_focus();
+    imp.find_entry.select_region(0, -1);
+
+    search(window);
+}
+
+/// Searches again when the content of the current tab has been replaced, like after switching tabs
+/// or loading another page
+pub(crate) fn update_find(window: &BrowserWindow) {
+    let imp = window.imp();
+    if !imp.find_bar.is_search_mode() {
+        return;
+    }
+
+    let searched = imp.find_target.borrow().as_ref().map(FindTarget::widget);
+    if content_widget(window) != searched {
+        search(window);
+    }
+}
+
+/// Selects the next or previous match, and scrolls to it
+pub(crate) fn find_next(window: &BrowserWindow, forward: bool) {
+    let imp = window.imp();
+    let target = imp.find_target.borrow();
+
+    match target.as_ref() {
+        Some(FindTarget::Source { view, context }) => {
+            let buffer = context.buffer();
+            let (start, end) = buffer.selection_bounds().unwrap_or_else(|| {
+                let iter = buffer.iter_at_mark(&buffer.get_insert());
+                (iter, iter)
+            });
+            select_source_match(view, context, if forward { &end } else { &start }, forward);
+
+            drop(target);
+            update_source_status(window);
+        }
+        Some(FindTarget::Rendered { area, scroller }) => {
+            if let Some((_, matches)) = imp.page_matches.borrow_mut().as_mut() {
+                matches.step(forward);
+            }
+            scroll_to_current_match(window, scroller);
+            area.queue_draw();
+
+            drop(target);
+            update_rendered_status(window);
+        }
+        None => {}
+    }
+}
+
+/// Draws the matches of the find bar on top of a rendered page. The selected match stands out.
+pub(crate) fn draw_page_matches(cr: &cairo::Context, matches: &PageMatches) {
+    for (index, rect) in matches.matches.iter().enumerate() {
+        if matches.current == Some(index) {
+            cr.set_source_rgba(1.0, 0.55, 0.0, 0.6);
+        } else {
+            cr.set_source_rgba(1.0, 0.9, 0.0, 0.4);
+        }
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        _ = cr.fill();
+    }
+}
+
+/// Searches the content of the current tab with the text and the options of the find bar
+fn search(window: &BrowserWindow) {
+    clear(window);
+
+    let imp = window.imp();
+    let query = imp.find_entry.text().to_string();
+    if query.is_empty() {
+        return;
+    }
+    let options = FindOptions {
+        match_case: imp.find_match_case.is_active(),
+        whole_word: imp.find_whole_word.is_active(),
+        regex: imp.find_regex.is_active(),
+    };
+
+    let Some(tab_id) = imp.current_tab_id() else {
+        return;
+    };
+    let Some(widget) = content_widget(window) else {
+        return;
+    };
+
+    match widget.downcast::<View>() {
+        Ok(view) => search_source(window, view, &query, options),
+        Err(widget) => match widget.downcast::<DrawingArea>() {
+            Ok(area) => search_rendered(window, tab_id, area, &query, options),
+            // The start page has nothing to search
+            Err(_) => set_status(window, "No matches", true),
+        },
+    }
+}
+
+/// Searches a source view with the search context of sourceview, which highlights the matches itself
+fn search_source(window: &BrowserWindow, view: View, query: &str, options: FindOptions) {
+    let Ok(buffer) = view.buffer().downcast::<sourceview5::Buffer>() else {
+        return;
+    };
+
+    let settings = SearchSettings::new();
+    settings.set_search_text(Some(query));
+    settings.set_case_sensitive(options.match_case);
+    settings.set_at_word_boundaries(options.whole_word);
+    settings.set_regex_enabled(options.regex);
+    settings.set_wrap_around(true);
+
+    let context = SearchContext::new(&buffer, Some(&settings));
+    context.set_highlight(true);
+
+    // The matches are counted in the background, so the status is updated once they are known
+    context.connect_occurrences_count_notify({
+        let window = window.clone();
+        move |_| update_source_status(&window)
+    });
+
+    // Searching starts at the current match, so it stays selected while more text is typed
+    let from = buffer
+        .selection_bounds()
+        .map_or_else(|| buffer.iter_at_mark(&buffer.get_insert()), |(start, _)| start);
+    select_source_match(&view, &context, &from, true);
+
+    window.imp().find_target.replace(Some(FindTarget::Source { view, context }));
+    update_source_status(window);
+}
+
+/// Selects the first match from the given position in the given direction, and scrolls to it
+fn select_source_match(view: &View, context: &SearchContext, from: &gtk4::TextIter, forward: bool) {
+    let found = match forward {
+        true => context.forward(from),
+        false => context.backward(from),
+    };
+
+    if let Some((mut start, end, _)) = found {
+        context.buffer().select_range(&start, &end);
+        view.scroll_to_iter(&mut start, 0.1, false, 0.0, 0.0);
+    }
+}
+
+fn update_source_status(window: &BrowserWindow) {
+    let imp = window.imp();
+    let target = imp.find_target.borrow();
+    let Some(FindTarget::Source { context, .. }) = target.as_ref() else {
+        return;
+    };
+
+    if context.regex_error().is_some() {
+        set_status(window, "Invalid regular expression", true);
+        return;
+    }
+
+    let count = context.occurrences_count();
+    let position = context
+        .buffer()
+        .selection_bounds()
+        .map_or(-1, |(start, end)| context.occurrence_position(&start, &end));
+
+    match count {
+        // Still counting
+        -1 => set_status(window, "", false),
+        0 => set_status(window, "No matches", true),
+        _ if position > 0 => set_status(window, &format!("{} of {}", position, count), false),
+        _ => set_status(window, &format!("{} matches", count), false),
+    }
+}
+
+/// Searches the text of the render tree of the tab, and highlights the matches in the drawing area
+fn search_rendered(window: &BrowserWindow, tab_id: TabId, area: DrawingArea, query: &str, options: FindOptions) {
+    let imp = window.imp();
+
+    let regex = match find_regex(query, options) {
+        Ok(regex) => regex,
+        Err(_) => {
+            set_status(window, "Invalid regular expression", true);
+            return;
+        }
+    };
+    let Some(drawer) = page_drawer(&area) else {
+        return;
+    };
+    let Some(scroller) = imp.content_scroller(tab_id) else {
+        return;
+    };
+
+    let runs = drawer.lock().unwrap().as_mut().map(text_runs).unwrap_or_default();
+
+    // The page is laid out when it is drawn, so this is the first moment the height of the page is
+    // known. The drawing area is made as high as the page, so the matches can be scrolled to.
+    let page_height = runs.iter().map(|run| run.y + run.height).fold(0.0, f64::max).ceil() as i32;
+    if page_height > area.height() {
+        area.set_content_height(page_height);
+    }
+
+    imp.page_matches
+        .replace(Some((tab_id, PageMatches::new(find_in_runs(&runs, &regex)))));
+    scroll_to_current_match(window, &scroller);
+    area.queue_draw();
+
+    imp.find_target.replace(Some(FindTarget::Rendered { area, scroller }));
+    update_rendered_status(window);
+}
+
+fn scroll_to_current_match(window: &BrowserWindow, scroller: &ScrolledWindow) {
+    let page_matches = window.imp().page_matches.borrow();
+    let Some(rect) = page_matches.as_ref().and_then(|(_, matches)| matches.current_match()) else {
+        return;
+    };
+
+    let adjustment = scroller.vadjustment();
+    let visible = adjustment.value()..adjustment.value() + adjustment.page_size() - rect.height;
+    if !visible.contains(&rect.y) {
+        adjustment.set_value(rect.y - SCROLL_MARGIN);
+    }
+}
+
+fn update_rendered_status(window: &BrowserWindow) {
+    let page_matches = window.imp().page_matches.borrow();
+    match page_matches.as_ref() {
+        Some((_, matches)) if !matches.matches.is_empty() => {
+            let position = matches.current.map_or(0, |index| index + 1);
+            set_status(window, &format!("{} of {}", position, matches.matches.len()), false);
+        }
+        _ => set_status(window, "No matches", true),
+    }
+}
+
+/// Removes the highlights of the previous search
+fn clear(window: &BrowserWindow) {
+    let imp = window.imp();
+
+    match imp.find_target.take() {
+        Some(FindTarget::Source { context, .. }) => context.set_highlight(false),
+        Some(FindTarget::Rendered { area, .. }) => {
+            imp.page_matches.replace(None);
+            area.queue_draw();
+        }
+        None => {}
+    }
+    set_status(window, "", false);
+}
+
+fn set_status(window: &BrowserWindow, status: &str, error: bool) {
+    let imp = window.imp();
+    imp.find_status.set_text(status);
+
+    if error {
+        imp.find_entry.add_css_class("error");
+    } else {
+        imp.find_entry.remove_css_class("error");
+    }
+}
+
+/// Returns the widget that shows the content of the current tab
+fn content_widget(window: &BrowserWindow) -> Option<Widget> {
+    let imp = window.imp();
+    let scroller = imp.content_scroller(imp.current_tab_id()?)?;
+
+    // Widgets that cannot scroll by themselves, like the drawing area, are wrapped in a viewport
+    match scroller.child()?.downcast::<Viewport>() {
+        Ok(viewport) => viewport.child(),
+        Err(child) => Some(child),
+    }
+}
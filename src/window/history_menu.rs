@@ -0,0 +1,100 @@
+use crate::window::BrowserWindow;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::ObjectSubclassIsExt;
+use gtk4::{glib, Align, Button, GestureClick, GestureLongPress, Label, ListBox, Popover, PositionType, SelectionMode};
+
+/// Maximum number of entries shown in the history dropdown
+const MAX_MENU_ENTRIES: usize = 15;
+
+/// Direction of the history dropdown
+#[derive(Clone, Copy)]
+enum Direction {
+    Back,
+    Forward,
+}
+
+/// Adds a dropdown with the session history of the current tab to the back and forward buttons. The
+/// dropdown is shown on a long press or a right click.
+pub(crate) fn setup_history_menu(window: &BrowserWindow) {
+    attach_menu(window, &window.imp().btn_prev.get(), Direction::Back);
+    attach_menu(window, &window.imp().btn_next.get(), Direction::Forward);
+}
+
+fn attach_menu(window: &BrowserWindow, button: &Button, direction: Direction) {
+    let long_press = GestureLongPress::new();
+    long_press.connect_pressed({
+        let window = window.clone();
+        let button = button.clone();
+        move |gesture, _, _| {
+            // Prevent the click that ends the long press from navigating
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+            show_menu(&window, &button, direction);
+        }
+    });
+    button.add_controller(long_press);
+
+    let right_click = GestureClick::builder().button(gtk4::gdk::BUTTON_SECONDARY).build();
+    right_click.connect_pressed({
+        let window = window.clone();
+        let button = button.clone();
+        move |_, _, _, _| {
+            show_menu(&window, &button, direction);
+        }
+    });
+    button.add_controller(right_click);
+}
+
+/// Shows a popover below the button that lists the entries that can be reached in the given direction
+fn show_menu(window: &BrowserWindow, button: &Button, direction: Direction) {
+    let Some(tab_id) = window.imp().current_tab_id() else {
+        return;
+    };
+    let Some(tab) = window.imp().tab_manager.lock().unwrap().get_tab(tab_id) else {
+        return;
+    };
+
+    let entries = match direction {
+        Direction::Back => tab.history().back_entries(),
+        Direction::Forward => tab.history().forward_entries(),
+    };
+    if entries.is_empty() {
+        return;
+    }
+
+    let list = ListBox::builder().selection_mode(SelectionMode::None).build();
+    let mut indices = Vec::new();
+    for (index, entry) in entries.into_iter().take(MAX_MENU_ENTRIES) {
+        let title = if entry.title.is_empty() {
+            entry.url.as_str()
+        } else {
+            entry.title.as_str()
+        };
+        let label = Label::new(Some(title));
+        label.set_halign(Align::Start);
+        label.set_max_width_chars(50);
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        label.set_tooltip_text(Some(entry.url.as_str()));
+        list.append(&label);
+        indices.push(index);
+    }
+
+    let popover = Popover::builder().child(&list).position(PositionType::Bottom).build();
+    popover.set_parent(button);
+    popover.connect_closed(|popover| {
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+
+    list.connect_row_activated({
+        let window = window.clone();
+        let popover = popover.clone();
+        move |_, row| {
+            popover.popdown();
+            if let Some(index) = indices.get(row.index() as usize) {
+                window.imp().navigate_history(tab_id, *index);
+            }
+        }
+    });
+
+    popover.popup();
+}
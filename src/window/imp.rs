@@ -1,29 +1,45 @@
-use crate::engine::GosubEngineConfig;
-use crate::eventloop::WindowEventLoopDummy;
-use crate::fetcher::address_parser::{GosubAddressParser, GosubRenderMode};
+use crate::about::{render_about, AboutContext, AboutTab};
+use crate::application::Application;
+use crate::bookmarks::bookmark_store;
+use crate::containers::container_store;
+use crate::downloads::download_manager;
+use crate::engine::{draw_tree, GosubEngineConfig};
+use crate::fetcher::address_parser::{CustomRenderOutput, GosubAddressParser, GosubRenderMode};
+use crate::fetcher::network_profile::{network_profiles, NetworkKind};
+use crate::fetcher::PageResponse;
+use crate::history::{history_store, Transition};
+use crate::session::WindowSession;
+use crate::settings::settings;
 use crate::tab::{GosubTab, GosubTabManager, TabCommand, TabId};
+use crate::window::find_bar::{draw_page_matches, FindTarget, SharedPageMatches};
 use crate::window::message::Message;
+use crate::window::page_links::setup_page_links;
 use crate::window::tab_context_menu::{build_context_menu, setup_context_menu_actions, TabInfo};
 use crate::{fetcher, runtime};
 use async_channel::{Receiver, Sender};
 use glib::subclass::InitializingObject;
 use gosub_engine::prelude::*;
-use gtk4::gio::SimpleActionGroup;
+use gtk4::gio::{MenuItem, SimpleActionGroup};
 use gtk4::glib::subclass::Signal;
 use gtk4::glib::Quark;
 use gtk4::graphene::Point;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{
-    gdk, glib, Button, CompositeTemplate, DrawingArea, Entry, GestureClick, Image, Notebook, PopoverMenu, PopoverMenuFlags, ScrolledWindow,
-    Settings, TemplateChild, TextView, ToggleButton, Widget,
+    gdk, glib, Button, CheckButton, CompositeTemplate, DrawingArea, Entry, GestureClick, Image, Label, Notebook, PopoverMenu,
+    PopoverMenuFlags, ScrolledWindow, SearchBar, SearchEntry, Settings, TemplateChild, TextView, ToggleButton, Widget,
 };
 use log::info;
 use once_cell::sync::Lazy;
 use sourceview5::prelude::*;
 use sourceview5::{LanguageManager, View};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
+use url::Url;
 
 // Create a static Quark as a unique key
 static TAB_ID_QUARK: Lazy<Quark> = Lazy::new(|| Quark::from_str("tab_id"));
@@ -47,36 +63,106 @@ impl<T: IsA<Widget>> WidgetExtTabId for T {
     }
 }
 
+static PAGE_DRAWER_QUARK: Lazy<Quark> = Lazy::new(|| Quark::from_str("page_drawer"));
+
+/// Drawer of a rendered page, shared with the drawing area that shows it
+pub(crate) type PageDrawer = Arc<Mutex<Option<<GosubEngineConfig as HasTreeDrawer>::TreeDrawer>>>;
+
+/// Returns the drawer of the page that is shown in the drawing area. This is not always the drawer of
+/// the tab, as custom render modes render their own page.
+pub(crate) fn page_drawer(area: &DrawingArea) -> Option<PageDrawer> {
+    // The data is only set by `create_drawing_area`, with the same type
+    unsafe { area.qdata::<PageDrawer>(*PAGE_DRAWER_QUARK).map(|ptr| ptr.as_ref().clone()) }
+}
+
 #[derive(CompositeTemplate)]
 #[template(resource = "/io/gosub/browser-gtk/ui/window.ui")]
 pub struct BrowserWindow {
+    #[template_child]
+    pub btn_prev: TemplateChild<Button>,
+    #[template_child]
+    pub btn_next: TemplateChild<Button>,
     #[template_child]
     pub searchbar: TemplateChild<Entry>,
     #[template_child]
+    pub btn_bookmark: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub img_bookmark: TemplateChild<Image>,
+    #[template_child]
+    pub find_bar: TemplateChild<SearchBar>,
+    #[template_child]
+    pub find_entry: TemplateChild<SearchEntry>,
+    #[template_child]
+    pub find_prev: TemplateChild<Button>,
+    #[template_child]
+    pub find_next: TemplateChild<Button>,
+    #[template_child]
+    pub find_match_case: TemplateChild<CheckButton>,
+    #[template_child]
+    pub find_whole_word: TemplateChild<CheckButton>,
+    #[template_child]
+    pub find_regex: TemplateChild<CheckButton>,
+    #[template_child]
+    pub find_status: TemplateChild<Label>,
+    #[template_child]
     pub tab_bar: TemplateChild<Notebook>,
     #[template_child]
     pub log_scroller: TemplateChild<ScrolledWindow>,
     #[template_child]
     pub log: TemplateChild<TextView>,
+    #[template_child]
+    pub statusbar: TemplateChild<Label>,
 
     // Other stuff that are non-widgets
     pub tab_manager: Arc<Mutex<GosubTabManager>>,
     pub sender: Arc<Sender<Message>>,
     pub receiver: Arc<Receiver<Message>>,
+    /// Set while the searchbar text is changed by the browser instead of by the user
+    pub searchbar_updating: Cell<bool>,
+    /// A session save has been scheduled
+    session_save_pending: Cell<bool>,
+    /// Set while a session is restored, so restored tabs are not loaded when they are added
+    restoring_session: Cell<bool>,
+    /// How the user got to the pages that are loading. The visit is added to the history once the
+    /// page has been loaded.
+    pending_visits: RefCell<HashMap<TabId, Transition>>,
+    /// The content that is searched by the find bar
+    pub(crate) find_target: RefCell<Option<FindTarget>>,
+    /// The matches of the find bar on the rendered page of the current tab
+    pub(crate) page_matches: SharedPageMatches,
 }
 
 impl Default for BrowserWindow {
     fn default() -> Self {
         let (tx, rx) = async_channel::unbounded::<Message>();
         Self {
+            btn_prev: TemplateChild::default(),
+            btn_next: TemplateChild::default(),
             searchbar: TemplateChild::default(),
+            btn_bookmark: TemplateChild::default(),
+            img_bookmark: TemplateChild::default(),
+            find_bar: TemplateChild::default(),
+            find_entry: TemplateChild::default(),
+            find_prev: TemplateChild::default(),
+            find_next: TemplateChild::default(),
+            find_match_case: TemplateChild::default(),
+            find_whole_word: TemplateChild::default(),
+            find_regex: TemplateChild::default(),
+            find_status: TemplateChild::default(),
             tab_bar: TemplateChild::default(),
             log_scroller: TemplateChild::default(),
             log: TemplateChild::default(),
+            statusbar: TemplateChild::default(),
 
             tab_manager: Arc::new(Mutex::new(GosubTabManager::new())),
             sender: Arc::new(tx),
             receiver: Arc::new(rx),
+            searchbar_updating: Cell::new(false),
+            session_save_pending: Cell::new(false),
+            restoring_session: Cell::new(false),
+            pending_visits: RefCell::new(HashMap::new()),
+            find_target: RefCell::new(None),
+            page_matches: Rc::new(RefCell::new(None)),
         }
     }
 }
@@ -138,7 +224,12 @@ impl BrowserWindow {
 
     #[template_callback]
     fn handle_prev_clicked(&self, _btn: &Button) {
-        todo!("not yet implemented");
+        self.go_back();
+    }
+
+    #[template_callback]
+    fn handle_next_clicked(&self, _btn: &Button) {
+        self.go_forward();
     }
 
     #[template_callback]
@@ -150,9 +241,14 @@ impl BrowserWindow {
         settings.set_property("gtk-application-prefer-dark-theme", btn.is_active());
     }
 
+    #[template_callback]
+    fn handle_bookmark_clicked(&self, _btn: &ToggleButton) {
+        self.toggle_bookmark();
+    }
+
     #[template_callback]
     fn handle_refresh_clicked(&self, _btn: &Button) {
-        self.log("Refreshing the current page");
+        self.reload_current_tab();
     }
 
     #[template_callback]
@@ -191,6 +287,183 @@ impl BrowserWindow {
         self.log.scroll_to_mark(&mark, 0.0, true, 0.0, 1.0);
     }
 
+    /// Shows the given URL in the searchbar. Internationalized domain names that could be used for
+    /// spoofing are shown as punycode, together with a warning icon. Otherwise the icon shows whether
+    /// the connection is encrypted. Pages that are not fetched from the network, like about: pages,
+    /// have no icon.
+    pub(crate) fn set_searchbar_url(&self, url: &Url) {
+        let display = GosubAddressParser::display(url);
+
+        self.searchbar_updating.set(true);
+        self.searchbar.set_text(display.text.as_str());
+        self.searchbar_updating.set(false);
+
+        if display.spoof_warning {
+            self.searchbar.set_primary_icon_name(Some("dialog-warning-symbolic"));
+            self.searchbar.set_primary_icon_tooltip_text(Some(
+                "This address contains characters that look like other characters, and is shown as punycode",
+            ));
+        } else {
+            let (icon, tooltip) = match url.scheme() {
+                "https" | "wss" => (Some("channel-secure"), Some("The connection to this site is encrypted")),
+                "http" | "ws" | "ftp" | "gopher" => (Some("channel-insecure"), Some("The connection to this site is not encrypted")),
+                _ => (None, None),
+            };
+            self.searchbar.set_primary_icon_name(icon);
+            self.searchbar.set_primary_icon_tooltip_text(tooltip);
+        }
+
+        self.update_bookmark_button(url);
+    }
+
+    /// Shows whether the given URL is bookmarked in the bookmark button
+    fn update_bookmark_button(&self, url: &Url) {
+        let bookmarked = !bookmark_store().find_by_url(url).is_empty();
+
+        // Changing the state does not emit "clicked", so this does not toggle the bookmark
+        self.btn_bookmark.set_active(bookmarked);
+        if bookmarked {
+            self.img_bookmark.set_icon_name(Some("starred-symbolic"));
+            self.btn_bookmark.set_tooltip_text(Some("Remove the bookmark of this page"));
+        } else {
+            self.img_bookmark.set_icon_name(Some("non-starred-symbolic"));
+            self.btn_bookmark.set_tooltip_text(Some("Bookmark this page (Ctrl+D)"));
+        }
+    }
+
+    /// Bookmarks the page of the current tab, unless it has been bookmarked already
+    pub(crate) fn bookmark_current_page(&self) {
+        let Some(tab) = self
+            .current_tab_id()
+            .and_then(|tab_id| self.tab_manager.lock().unwrap().get_tab(tab_id))
+        else {
+            return;
+        };
+
+        let store = bookmark_store();
+        if !store.find_by_url(tab.url()).is_empty() {
+            self.log(format!("{} is already bookmarked", tab.url()).as_str());
+            return;
+        }
+
+        let favicon = match tab.url().scheme() {
+            "http" | "https" => tab.url().join("/favicon.ico").ok(),
+            _ => None,
+        };
+        store.add_bookmark(None, tab.title(), tab.url(), favicon.as_ref());
+        self.log(format!("Bookmarked {}", tab.url()).as_str());
+
+        self.notify_bookmarks_changed();
+    }
+
+    /// Bookmarks the page of the current tab, or removes its bookmarks when it has been bookmarked
+    fn toggle_bookmark(&self) {
+        let Some(tab) = self
+            .current_tab_id()
+            .and_then(|tab_id| self.tab_manager.lock().unwrap().get_tab(tab_id))
+        else {
+            return;
+        };
+
+        let store = bookmark_store();
+        let bookmarks = store.find_by_url(tab.url());
+        if bookmarks.is_empty() {
+            self.bookmark_current_page();
+            return;
+        }
+
+        for bookmark in bookmarks {
+            store.remove(bookmark.id);
+        }
+        self.log(format!("Removed the bookmark of {}", tab.url()).as_str());
+
+        self.notify_bookmarks_changed();
+    }
+
+    /// Updates the bookmarks menu and the bookmark button of every window
+    fn notify_bookmarks_changed(&self) {
+        match self.obj().application().and_downcast::<Application>() {
+            Some(app) => app.bookmarks_changed(),
+            None => self.update_bookmarks(),
+        }
+    }
+
+    /// Updates the bookmark button after the bookmarks have changed
+    pub(crate) fn update_bookmarks(&self) {
+        if let Some(tab) = self
+            .current_tab_id()
+            .and_then(|tab_id| self.tab_manager.lock().unwrap().get_tab(tab_id))
+        {
+            self.update_bookmark_button(tab.url());
+        }
+    }
+
+    /// Navigates the current tab one entry back in its session history
+    pub(crate) fn go_back(&self) {
+        let Some(tab_id) = self.current_tab_id() else {
+            return;
+        };
+        let Some(tab) = self.tab_manager.lock().unwrap().get_tab(tab_id) else {
+            return;
+        };
+
+        if tab.history().can_go_back() {
+            self.navigate_history(tab_id, tab.history().current_index() - 1);
+        }
+    }
+
+    /// Navigates the current tab one entry forward in its session history
+    pub(crate) fn go_forward(&self) {
+        let Some(tab_id) = self.current_tab_id() else {
+            return;
+        };
+        let Some(tab) = self.tab_manager.lock().unwrap().get_tab(tab_id) else {
+            return;
+        };
+
+        if tab.history().can_go_forward() {
+            self.navigate_history(tab_id, tab.history().current_index() + 1);
+        }
+    }
+
+    /// Loads the page of the current tab again
+    pub(crate) fn reload_current_tab(&self) {
+        let Some(tab_id) = self.current_tab_id() else {
+            return;
+        };
+
+        self.log("Refreshing the current page");
+        let sender = self.get_sender();
+        runtime().spawn(async move {
+            sender.send(Message::ReloadTab(tab_id)).await.unwrap();
+        });
+    }
+
+    /// Sends a message to navigate the tab to the given session history entry
+    pub(crate) fn navigate_history(&self, tab_id: TabId, index: usize) {
+        let sender = self.get_sender();
+        runtime().spawn(async move {
+            sender.send(Message::NavigateHistory(tab_id, index)).await.unwrap();
+        });
+    }
+
+    /// Enables or disables the back and forward buttons based on the history of the given tab
+    pub(crate) fn update_navigation_buttons(&self, tab_id: TabId) {
+        let tab = self.tab_manager.lock().unwrap().get_tab(tab_id);
+
+        self.btn_prev
+            .set_sensitive(tab.as_ref().is_some_and(|tab| tab.history().can_go_back()));
+        self.btn_next
+            .set_sensitive(tab.as_ref().is_some_and(|tab| tab.history().can_go_forward()));
+    }
+
+    /// Returns the scrolled window that shows the content of the given tab
+    pub(crate) fn content_scroller(&self, tab_id: TabId) -> Option<ScrolledWindow> {
+        let page_num = self.get_page_num_for_tab(tab_id)?;
+        let page = self.tab_bar.nth_page(Some(page_num))?;
+        page.first_child()?.downcast::<ScrolledWindow>().ok()
+    }
+
     pub(crate) fn close_tab(&self, tab_id: TabId) {
         let mut manager = self.tab_manager.lock().unwrap();
         if manager.tab_count() == 1 {
@@ -198,12 +471,323 @@ impl BrowserWindow {
             return;
         }
         manager.remove_tab(tab_id);
+        drop(manager);
+
+        self.tabs_closed();
+    }
+
+    /// Closes the tabs that are closed by the given tab manager operation (like closing all tabs
+    /// to the right)
+    pub(crate) fn close_tabs(&self, operation: impl FnOnce(&mut GosubTabManager) -> Vec<TabId>) {
+        let closed = operation(&mut self.tab_manager.lock().unwrap());
+        self.log(format!("Closed {} tabs", closed.len()).as_str());
+
+        self.tabs_closed();
+    }
+
+    /// Cleans up after one or more tabs have been closed
+    fn tabs_closed(&self) {
+        let manager = self.tab_manager.lock().unwrap();
+
+        // All state of private tabs is thrown away when the last private tab of all windows is closed
+        if !manager.has_private_tabs() && !self.other_windows_have_private_tabs() {
+            network_profiles().lock().unwrap().discard_private();
+        }
+        let no_tabs_left = manager.tab_count() == 0;
+        // Pages that were still loading in the closed tabs are not visited
+        self.pending_visits
+            .borrow_mut()
+            .retain(|tab_id, _| manager.get_tab(*tab_id).is_some());
+        drop(manager);
+
+        self.update_recently_closed_menu();
+
+        // There is always at least one tab open
+        if no_tabs_left {
+            self.open_tab(None, "about:blank", "New Tab", NetworkKind::Default);
+        }
+    }
+
+    /// Returns true when another browser window has private tabs open
+    fn other_windows_have_private_tabs(&self) -> bool {
+        let Some(app) = self.obj().application().and_downcast::<Application>() else {
+            return false;
+        };
+
+        app.browser_windows()
+            .iter()
+            .filter(|window| *window != &*self.obj())
+            .any(|window| window.imp().tab_manager.lock().unwrap().has_private_tabs())
+    }
+
+    /// Removes the tab from this window without closing it, so it can be moved to another window. The
+    /// window is not closed when it has no tabs left, see `close_if_empty`.
+    pub(crate) fn detach_tab(&self, tab_id: TabId) -> Option<GosubTab> {
+        let tab = self.tab_manager.lock().unwrap().detach_tab(tab_id)?;
+        self.pending_visits.borrow_mut().remove(&tab_id);
+        self.refresh_tabs();
+
+        Some(tab)
+    }
+
+    /// Closes the window when all of its tabs have been moved to other windows. This is done after the
+    /// tabs have been added to the other window, as closing the window throws away the state of private
+    /// tabs when no other window has any.
+    fn close_if_empty(&self) {
+        if self.tab_manager.lock().unwrap().tab_count() == 0 {
+            self.obj().close();
+        }
+    }
+
+    /// Adds a tab that has been moved from another window at the given position, and shows it
+    pub(crate) fn attach_tab(&self, tab: GosubTab, position: Option<usize>) {
+        let tab_id = tab.id();
+        let loading = tab.is_loading();
+
+        let mut manager = self.tab_manager.lock().unwrap();
+        manager.add_tab(tab, position);
+        manager.notify_tab_changed(tab_id);
+        manager.set_active(tab_id);
+        drop(manager);
+        self.refresh_tabs();
+
+        self.reload_moved_tab(tab_id, loading);
+    }
+
+    /// Takes over a tab whose page has been dragged from the tab bar of another window to the given
+    /// position in this tab bar. Pages that are added by this window itself are ignored.
+    pub(crate) fn adopt_dropped_tab(&self, tab_id: TabId, page_num: usize) {
+        if self.tab_manager.lock().unwrap().get_tab(tab_id).is_some() {
+            return;
+        }
+        let Some(app) = self.obj().application().and_downcast::<Application>() else {
+            return;
+        };
+        let Some((source, tab)) = app
+            .browser_windows()
+            .into_iter()
+            .filter(|window| window != &*self.obj())
+            .find_map(|window| window.imp().detach_tab(tab_id).map(|tab| (window, tab)))
+        else {
+            return;
+        };
+        let loading = tab.is_loading();
+
+        // The label and the content are created again, as they belong to the other window
+        let mut manager = self.tab_manager.lock().unwrap();
+        manager.adopt_tab(tab, page_num);
+        manager.notify_tab_changed(tab_id);
+        manager.set_active(tab_id);
+        drop(manager);
+        self.refresh_tabs();
+
+        if let Some(page) = self
+            .get_page_num_for_tab(tab_id)
+            .and_then(|page_num| self.tab_bar.nth_page(Some(page_num)))
+        {
+            let pinned = self.tab_manager.lock().unwrap().get_tab(tab_id).is_some_and(|tab| tab.is_pinned());
+            self.tab_bar.set_tab_reorderable(&page, !pinned);
+            self.tab_bar.set_tab_detachable(&page, !pinned);
+        }
+
+        source.imp().close_if_empty();
+        self.reload_moved_tab(tab_id, loading);
+    }
+
+    /// The page of a tab that was still loading when it was moved is loaded by the other window, so it
+    /// is loaded again in this window
+    fn reload_moved_tab(&self, tab_id: TabId, loading: bool) {
+        if loading {
+            self.pending_visits.borrow_mut().insert(tab_id, Transition::Link);
+            self.load_favicon_async(tab_id);
+            self.load_url_async(tab_id);
+        }
+    }
+
+    /// Moves the tab to a new window
+    pub(crate) fn move_tab_to_new_window(&self, tab_id: TabId) {
+        let Some(app) = self.obj().application().and_downcast::<Application>() else {
+            return;
+        };
+        if self.tab_manager.lock().unwrap().tab_count() < 2 {
+            self.log("Cannot move the only tab to a new window");
+            return;
+        }
+
+        if let Some(tab) = self.detach_tab(tab_id) {
+            super::BrowserWindow::with_tab(&app, tab).present();
+        }
+    }
+
+    /// Duplicates the tab, and loads the copy
+    pub(crate) fn duplicate_tab(&self, tab_id: TabId) {
+        let Some(new_tab_id) = self.tab_manager.lock().unwrap().duplicate_tab(tab_id) else {
+            return;
+        };
+
+        self.refresh_tabs();
+
+        self.load_favicon_async(new_tab_id);
+        self.load_url_async(new_tab_id);
+    }
+
+    /// Reopens a recently closed tab. Index 0 is the most recently closed tab.
+    pub(crate) fn reopen_closed_tab(&self, index: usize) {
+        let Some(tab_id) = self.tab_manager.lock().unwrap().reopen_closed_tab(index) else {
+            self.log("No closed tab to reopen");
+            return;
+        };
+
+        self.update_recently_closed_menu();
+        self.refresh_tabs();
+
+        self.load_favicon_async(tab_id);
+        self.load_url_async(tab_id);
+    }
+
+    /// Fills the "Recently Closed" menu with the closed tabs. The menu bar is shared by all windows,
+    /// so only the most recently used window fills it.
+    pub(crate) fn update_recently_closed_menu(&self) {
+        let Some(app) = self.obj().application().and_downcast::<Application>() else {
+            return;
+        };
+        if app.browser_window().as_ref() != Some(&*self.obj()) {
+            return;
+        }
+        let Some(menu) = app.recently_closed_menu() else {
+            return;
+        };
+
+        menu.remove_all();
+        let manager = self.tab_manager.lock().unwrap();
+        for (index, tab) in manager.closed_tabs().enumerate() {
+            let item = MenuItem::new(Some(tab.title()), None);
+            item.set_action_and_target_value(Some("win.reopen-closed-tab"), Some(&(index as i32).to_variant()));
+            menu.append_item(&item);
+        }
     }
 
     pub(crate) fn refresh_tabs(&self) {
         let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
 
-        rt.block_on(self.refresh_tabs_async())
+        rt.block_on(self.refresh_tabs_async());
+
+        // The page that the find bar searches might have been replaced
+        super::find_bar::update_find(&self.obj());
+
+        // Every change to the tabs ends with a refresh, so this is where the session is kept up to date
+        self.schedule_session_save();
+    }
+
+    /// Restores the tabs of a previous session. Only the active tab is loaded, the other tabs are
+    /// loaded when they are shown for the first time. Returns false when there was nothing to restore.
+    pub(crate) fn restore_session(&self, session: &WindowSession) -> bool {
+        let (tabs, active_tab) = session.restore_tabs();
+        if tabs.is_empty() {
+            return false;
+        }
+
+        let mut manager = self.tab_manager.lock().unwrap();
+        let mut tab_ids = Vec::new();
+        for mut tab in tabs {
+            tab.set_needs_load(true);
+            let tab_id = manager.add_tab(tab, None);
+            manager.notify_tab_changed(tab_id);
+            tab_ids.push(tab_id);
+        }
+
+        let active_tab_id = active_tab.and_then(|index| tab_ids.get(index).copied()).unwrap_or(tab_ids[0]);
+        manager.set_active(active_tab_id);
+        drop(manager);
+
+        self.restoring_session.set(true);
+        self.refresh_tabs();
+        self.restoring_session.set(false);
+
+        self.load_tab(active_tab_id);
+        true
+    }
+
+    /// Returns true when the tab has been restored from a session, and should be loaded as soon as
+    /// it is shown
+    pub(crate) fn needs_load(&self, tab_id: TabId) -> bool {
+        !self.restoring_session.get() && self.tab_manager.lock().unwrap().get_tab(tab_id).is_some_and(|tab| tab.needs_load())
+    }
+
+    /// Loads a tab that has been restored from a session, when it is not loaded yet
+    pub(crate) fn load_tab(&self, tab_id: TabId) {
+        let mut manager = self.tab_manager.lock().unwrap();
+        let Some(mut tab) = manager.get_tab(tab_id) else {
+            return;
+        };
+        if !tab.needs_load() {
+            return;
+        }
+
+        tab.set_needs_load(false);
+        tab.set_loading(true);
+        manager.update_tab(tab_id, &tab);
+        drop(manager);
+
+        self.refresh_tabs();
+
+        self.load_favicon_async(tab_id);
+        self.load_url_async(tab_id);
+    }
+
+    /// Saves the session after a short delay, so a burst of changes results in a single write
+    fn schedule_session_save(&self) {
+        if self.session_save_pending.replace(true) {
+            return;
+        }
+
+        let window = self.obj().clone();
+        glib::timeout_add_local_once(Duration::from_secs(1), move || {
+            window.imp().session_save_pending.set(false);
+            if let Some(app) = window.application().and_downcast::<Application>() {
+                app.save_session();
+            }
+        });
+    }
+
+    /// Loads the URL in the tab, as a new entry in its session history
+    fn navigate(&self, tab_id: TabId, url: Url, view_mode: GosubRenderMode, transition: Transition) {
+        let scroll_position = self.content_scroller(tab_id).map_or(0.0, |s| s.vadjustment().value());
+
+        // Update information in the given tab with the new url
+        let mut manager = self.tab_manager.lock().unwrap();
+        let mut tab = manager.get_tab(tab_id).unwrap().clone();
+
+        tab.set_scroll_position(scroll_position);
+        tab.navigate(url.clone(), view_mode);
+        tab.set_favicon(None);
+        tab.set_title(url.as_str());
+        tab.set_loading(true);
+
+        manager.update_tab(tab_id, &tab);
+        drop(manager);
+
+        if self.current_tab_id() == Some(tab_id) {
+            self.set_searchbar_url(&url);
+            self.update_navigation_buttons(tab_id);
+        }
+
+        self.refresh_tabs();
+
+        // Now, load favicon and url content
+        self.pending_visits.borrow_mut().insert(tab_id, transition);
+        self.load_favicon_async(tab_id);
+        self.load_url_async(tab_id);
+    }
+
+    /// Returns the tabs of the window that are stored in the session. Returns None when the window has
+    /// no tabs yet, as nothing has been restored then.
+    pub(crate) fn window_session(&self) -> Option<WindowSession> {
+        let manager = self.tab_manager.lock().unwrap();
+        if manager.tab_count() == 0 {
+            return None;
+        }
+        Some(WindowSession::from_manager(&manager, self.current_tab_id()))
     }
 
     /// Refresh tabs will asynchronously update the tab bar based on the current state of the tab
@@ -233,14 +817,18 @@ impl BrowserWindow {
                     notebook_box.set_tab_id(tab.id());
                     self.tab_bar.insert_page(&notebook_box, Some(&label), Some(position));
 
-                    // We can reorder tab, unless it's pinned/pinned
+                    // We can reorder tab, unless it's pinned/pinned. The same goes for dragging it to
+                    // another window.
                     if let Some(page) = self.tab_bar.nth_page(Some(position)) {
                         self.tab_bar.set_tab_reorderable(&page, !tab.is_pinned());
+                        self.tab_bar.set_tab_detachable(&page, !tab.is_pinned());
                     }
                 }
                 TabCommand::Close(tab_id) => {
-                    let page_num = self.get_page_num_for_tab(tab_id);
-                    self.tab_bar.remove_page(page_num);
+                    // The page is gone already when the tab has been dragged to another window
+                    if let Some(page_num) = self.get_page_num_for_tab(tab_id) {
+                        self.tab_bar.remove_page(Some(page_num));
+                    }
                 }
                 TabCommand::CloseAll => {
                     for _ in 0..self.tab_bar.pages().n_items() {
@@ -273,34 +861,42 @@ impl BrowserWindow {
                     } else if tab.render_mode() == GosubRenderMode::Json {
                         let view = create_view_mode("json", tab.content());
                         scrolled_window.set_child(Some(&view));
-                    } else if tab.has_drawer() {
-                        let tab_clone = tab.clone();
-
-                        let area = DrawingArea::default();
-                        area.set_draw_func(move |_area, cr, width, height| {
-                            let binding = tab_clone.drawer();
-                            let mut drawer_lock = binding.lock().unwrap();
-
-                            if let Some(drawer) = drawer_lock.as_mut() {
-                                let mut render_backend = <GosubEngineConfig as HasRenderBackend>::RenderBackend::new();
-                                let size = SizeU32::new(width as u32, height as u32);
-
-                                // Drawer.draw will populate the scene with elements from the tree
-                                let mut win_data = WindowData {
-                                    scene: Scene::new(),
-                                    cr: Some(cr.clone()),
-                                };
-                                drawer.draw(&mut render_backend, &mut win_data, size, &WindowEventLoopDummy);
-
-                                let mut active_win_data = ActiveWindowData { cr: cr.clone() };
-                                _ = render_backend.render(&mut win_data, &mut active_win_data);
-
-                                return;
+                    } else if tab.render_mode() == GosubRenderMode::PrettyJson {
+                        let view = create_view_mode("json", pretty_print_json(tab.content()).as_str());
+                        scrolled_window.set_child(Some(&view));
+                    } else if let GosubRenderMode::Custom(name) = tab.render_mode() {
+                        match GosubAddressParser::render_custom(&name, tab.url(), tab.content()) {
+                            Some(CustomRenderOutput::Html(html)) => {
+                                match <GosubEngineConfig as HasTreeDrawer>::TreeDrawer::from_source(
+                                    tab.url().clone(),
+                                    &html,
+                                    TaffyLayouter,
+                                    false,
+                                ) {
+                                    Ok(drawer) => {
+                                        let drawer = Arc::new(Mutex::new(Some(drawer)));
+                                        let area = create_drawing_area(drawer.clone(), tab.id(), self.page_matches.clone());
+                                        setup_page_links(&self.obj(), &area, drawer, tab.id(), tab.url().clone());
+                                        scrolled_window.set_child(Some(&area));
+                                    }
+                                    Err(e) => {
+                                        let view = create_view_mode("text", format!("Cannot render output of {}: {:?}", name, e).as_str());
+                                        scrolled_window.set_child(Some(&view));
+                                    }
+                                }
                             }
-
-                            drop(drawer_lock);
-                        });
-
+                            Some(CustomRenderOutput::Text(text)) => {
+                                let view = create_view_mode("text", &text);
+                                scrolled_window.set_child(Some(&view));
+                            }
+                            None => {
+                                let view = create_view_mode("text", format!("No handler registered for render mode {}", name).as_str());
+                                scrolled_window.set_child(Some(&view));
+                            }
+                        }
+                    } else if tab.has_drawer() {
+                        let area = create_drawing_area(tab.drawer(), tab.id(), self.page_matches.clone());
+                        setup_page_links(&self.obj(), &area, tab.drawer(), tab.id(), tab.url().clone());
                         scrolled_window.set_child(Some(&area));
                     } else {
                         // No drawer is (yet) created, so we display a default page (with the gosub logo)
@@ -312,6 +908,16 @@ impl BrowserWindow {
                     // scrolled_window.set_child(Some(&content));
                     scrolled_window.set_tab_id(tab.id());
 
+                    // Restore the scroll position when navigating back to a page. This is done once
+                    // the content has been laid out, as the adjustment is not yet known here.
+                    let scroll_position = tab.history().current().map_or(0.0, |entry| entry.scroll_position);
+                    if !tab.is_loading() && scroll_position > 0.0 {
+                        let adjustment = scrolled_window.vadjustment();
+                        glib::idle_add_local_once(move || {
+                            adjustment.set_value(scroll_position);
+                        });
+                    }
+
                     // Since a tab contains a box, we just update the child inside the box. This way
                     // we do not need to remove the actual page from the notebook, which results in all
                     // kind of issues.
@@ -348,6 +954,13 @@ impl BrowserWindow {
     fn create_normal_tab_label(&self, tab: &GosubTab) -> Widget {
         let label_vbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 5);
 
+        // Tabs inside a container are marked with the color of the container
+        if let Some(container) = tab.container().and_then(|id| container_store().get(id)) {
+            let marker = gtk4::Label::new(None);
+            marker.set_markup(format!("<span foreground=\"{}\">●</span>", container.color.hex()).as_str());
+            label_vbox.append(&marker);
+        }
+
         // When the tab is loading, we show a spinner
         if tab.is_loading() {
             let spinner = gtk4::Spinner::new();
@@ -355,6 +968,8 @@ impl BrowserWindow {
             label_vbox.append(&spinner);
         } else if let Some(favicon) = &tab.favicon() {
             label_vbox.append(&Image::from_paintable(Some(&favicon.clone())));
+        } else if tab.is_private() {
+            label_vbox.append(&Image::from_icon_name("security-high-symbolic"));
         }
 
         let mut title = tab.title().to_string();
@@ -392,6 +1007,13 @@ impl BrowserWindow {
             false => self.create_normal_tab_label(tab),
         };
 
+        if tab.is_private() {
+            tab_label.add_css_class("private-tab");
+            tab_label.set_tooltip_text(Some("Private tab"));
+        } else if let Some(container) = tab.container().and_then(|id| container_store().get(id)) {
+            tab_label.set_tooltip_text(Some(format!("Container: {}", container.name).as_str()));
+        }
+
         let gesture = GestureClick::builder()
             .button(0) // 0 means all buttons
             .build();
@@ -411,6 +1033,7 @@ impl BrowserWindow {
                     is_left: tab_manager.is_most_left_unpinned_tab(tab_id),
                     is_right: tab_manager.is_most_right_tab(tab_id),
                     tab_count,
+                    has_closed_tabs: tab_manager.closed_tabs().next().is_some(),
                 };
                 drop(tab_manager);
 
@@ -469,6 +1092,7 @@ impl BrowserWindow {
         let manager = self.tab_manager.lock().unwrap();
         let tab = manager.get_tab(tab_id).unwrap();
         let url = tab.url().to_string();
+        let network = network_profiles().lock().unwrap().get(tab.network_kind());
         drop(manager);
 
         let sender_clone = self.get_sender().clone();
@@ -477,7 +1101,7 @@ impl BrowserWindow {
                 // about: pages do not have a favicon (or maybe a default one?)
                 Vec::new()
             } else {
-                fetcher::fetch_favicon(url.as_str()).await
+                fetcher::fetch_favicon(url.as_str(), &network).await
             };
             sender_clone.send(Message::FaviconLoaded(tab_id, favicon)).await.unwrap();
         });
@@ -487,18 +1111,33 @@ impl BrowserWindow {
         let manager = self.tab_manager.lock().unwrap();
         let tab = manager.get_tab(tab_id).unwrap();
         let url = tab.url().clone();
+        let tab_network_kind = tab.network_kind();
+        let render_mode = tab.render_mode();
+        let network = network_profiles().lock().unwrap().get(tab_network_kind);
         drop(manager);
 
         let sender_clone = self.get_sender().clone();
-        runtime().spawn(async move {
-            if url.scheme() == "about:" {
-                let html_content = load_about_url(url.to_string());
+
+        // About pages are rendered by the browser itself, as they need the state of the window
+        if url.scheme() == "about" {
+            let html_content = render_about(&url, &self.about_context());
+            runtime().spawn(async move {
                 sender_clone.send(Message::UrlLoaded(tab_id, html_content)).await.unwrap();
-                return;
-            }
+            });
+            return;
+        }
 
-            match fetcher::fetch_url_body(url).await {
-                Ok(content) => {
+        runtime().spawn(async move {
+            match fetcher::fetch_page(url, &network, &render_mode).await {
+                // Responses that cannot be rendered are streamed to disk instead
+                Ok(PageResponse::Download(response)) => {
+                    let download = download_manager().start(response, tab_network_kind);
+                    sender_clone
+                        .send(Message::DownloadStarted(tab_id, download.filename()))
+                        .await
+                        .unwrap();
+                }
+                Ok(PageResponse::Page(content)) => {
                     let html_content = String::from_utf8_lossy(content.as_slice());
                     // we get a Cow.. and we clone it into the url?
                     sender_clone
@@ -517,6 +1156,40 @@ impl BrowserWindow {
         });
     }
 
+    /// Adds the page that has been loaded in the tab to the history. Pages in private tabs and about:
+    /// pages are not added, and neither are restored tabs that are loaded when they are shown.
+    fn add_visit(&self, tab: &GosubTab) {
+        let Some(transition) = self.pending_visits.borrow_mut().remove(&tab.id()) else {
+            return;
+        };
+        if tab.is_private() || tab.url().scheme() == "about" || !settings().privacy.remember_history {
+            return;
+        }
+
+        history_store().add_visit(tab.url(), tab.title(), transition, chrono::Utc::now());
+    }
+
+    /// Collects the state of the window that is shown on the about: pages
+    fn about_context(&self) -> AboutContext {
+        let active_tab_id = self.current_tab_id();
+        let manager = self.tab_manager.lock().unwrap();
+
+        let tabs = manager
+            .order()
+            .into_iter()
+            .filter_map(|tab_id| manager.get_tab(tab_id))
+            .map(|tab| AboutTab {
+                title: tab.title().to_string(),
+                url: tab.url().to_string(),
+                pinned: tab.is_pinned(),
+                private: tab.is_private(),
+                active: Some(tab.id()) == active_tab_id,
+            })
+            .collect();
+
+        AboutContext { tabs }
+    }
+
     /// Handles all message coming from the async (tokio) tasks
     pub async fn handle_message(&self, message: Message) {
         info!(target: "gtk", "Received a message: {:?}", message);
@@ -526,19 +1199,52 @@ impl BrowserWindow {
                 self.refresh_tabs();
             }
             Message::OpenTab(url, title) => {
-                self.open_tab(None, &url, &title);
+                self.open_tab(None, &url, &title, NetworkKind::Default);
+            }
+            Message::OpenPrivateTab(url, title) => {
+                self.open_tab(None, &url, &title, NetworkKind::Private);
             }
             Message::OpenTabRight(target_tab_id, url, title) => {
+                // Tabs opened from a private or container tab stay within the same profile
+                let manager = self.tab_manager.lock().unwrap();
+                let network = manager
+                    .get_tab(target_tab_id)
+                    .map_or(NetworkKind::Default, |tab| tab.network_kind());
+                drop(manager);
+
                 for page_num in 0..self.tab_bar.pages().n_items() {
                     let page = self.tab_bar.nth_page(Some(page_num)).unwrap();
                     if page.get_tab_id().unwrap() == target_tab_id {
-                        self.open_tab(Some(page_num as usize + 1), &url, &title);
+                        self.open_tab(Some(page_num as usize + 1), &url, &title, network);
                         return;
                     }
                 }
             }
+            Message::ReopenInContainer(tab_id, container) => {
+                let network = match container {
+                    Some(container) => NetworkKind::Container(container),
+                    None => NetworkKind::Default,
+                };
+
+                // The tab is replaced by a copy with the same history, so it does not show up as a
+                // closed tab
+                let Some(new_tab_id) = self.tab_manager.lock().unwrap().reopen_in_network(tab_id, network) else {
+                    return;
+                };
+                self.tabs_closed();
+                self.refresh_tabs();
+
+                self.load_favicon_async(new_tab_id);
+                self.load_url_async(new_tab_id);
+            }
 
             Message::LoadUrl(tab_id, url_str) => {
+                // Typing the keyword of a bookmark opens the bookmark
+                let url_str = bookmark_store()
+                    .find_by_keyword(url_str.trim())
+                    .and_then(|bookmark| bookmark.url)
+                    .map_or(url_str, |url| url.to_string());
+
                 self.log(format!("Loading URL: {}", url_str).as_str());
 
                 let Ok((view_mode, url)) = GosubAddressParser::parse(url_str.as_str()) else {
@@ -546,33 +1252,72 @@ impl BrowserWindow {
                     return;
                 };
 
-                // Update information in the given tab with the new url
+                self.navigate(tab_id, url, view_mode, Transition::Typed);
+            }
+            Message::FollowLink(tab_id, url) => {
+                // Links are not looked up as bookmark keywords, and always show the page itself
+                self.log(format!("Following link: {}", url).as_str());
+                self.navigate(tab_id, url, GosubRenderMode::Rendered, Transition::Link);
+            }
+            Message::NavigateHistory(tab_id, index) => {
+                let scroll_position = self.content_scroller(tab_id).map_or(0.0, |s| s.vadjustment().value());
+
                 let mut manager = self.tab_manager.lock().unwrap();
-                let mut tab = manager.get_tab(tab_id).unwrap().clone();
+                let Some(mut tab) = manager.get_tab(tab_id) else {
+                    return;
+                };
 
+                tab.set_scroll_position(scroll_position);
+                if !tab.navigate_history(index) {
+                    return;
+                }
                 tab.set_favicon(None);
-                tab.set_title(url.as_str());
-                tab.set_url(url);
                 tab.set_loading(true);
-                tab.set_render_mode(view_mode);
 
+                let url = tab.url().clone();
                 manager.update_tab(tab_id, &tab);
                 drop(manager);
 
+                self.log(format!("Navigating to history entry {}: {}", index, url).as_str());
+                if self.current_tab_id() == Some(tab_id) {
+                    self.set_searchbar_url(&url);
+                    self.update_navigation_buttons(tab_id);
+                }
+
                 self.refresh_tabs();
 
-                // Now, load favicon and url content
+                self.pending_visits.borrow_mut().insert(tab_id, Transition::Link);
                 self.load_favicon_async(tab_id);
                 self.load_url_async(tab_id);
             }
+            Message::LoadTab(tab_id) => {
+                self.load_tab(tab_id);
+            }
+            Message::ReloadTab(tab_id) => {
+                let mut manager = self.tab_manager.lock().unwrap();
+                let Some(mut tab) = manager.get_tab(tab_id) else {
+                    return;
+                };
+                tab.set_loading(true);
+                manager.update_tab(tab_id, &tab);
+                drop(manager);
+
+                self.refresh_tabs();
+
+                self.pending_visits.borrow_mut().insert(tab_id, Transition::Reload);
+                self.load_url_async(tab_id);
+            }
             Message::FaviconLoaded(tab_id, buf) => {
                 if buf.is_empty() {
                     self.log(format!("no favicon found for tab {}", tab_id).as_str());
                     return;
                 }
 
+                // The tab may have been closed or moved to another window in the meantime
                 let manager = self.tab_manager.lock().unwrap();
-                let mut tab = manager.get_tab(tab_id).unwrap().clone();
+                let Some(mut tab) = manager.get_tab(tab_id) else {
+                    return;
+                };
                 drop(manager);
 
                 let bytes = glib::Bytes::from(buf.as_slice());
@@ -595,7 +1340,9 @@ impl BrowserWindow {
             }
             Message::UrlLoaded(tab_id, html_content) => {
                 let mut manager = self.tab_manager.lock().unwrap();
-                let mut tab = manager.get_tab(tab_id).unwrap().clone();
+                let Some(mut tab) = manager.get_tab(tab_id) else {
+                    return;
+                };
                 tab.set_content(&html_content);
 
                 let d =
@@ -615,7 +1362,34 @@ impl BrowserWindow {
                 manager.update_tab(tab_id, &tab);
                 drop(manager);
 
+                self.add_visit(&tab);
+                self.refresh_tabs();
+            }
+            Message::DownloadStarted(tab_id, filename) => {
+                // A download does not replace the page, so the tab goes back to the page it showed.
+                // Tabs that were opened for the download have no previous page, and keep its URL.
+                self.pending_visits.borrow_mut().remove(&tab_id);
+
+                let mut manager = self.tab_manager.lock().unwrap();
+                let Some(mut tab) = manager.get_tab(tab_id) else {
+                    return;
+                };
+                tab.cancel_navigation();
+                tab.set_loading(false);
+                let url = tab.url().clone();
+                manager.update_tab(tab_id, &tab);
+                drop(manager);
+
+                if self.current_tab_id() == Some(tab_id) {
+                    self.set_searchbar_url(&url);
+                    self.update_navigation_buttons(tab_id);
+                }
                 self.refresh_tabs();
+
+                self.log(format!("Downloading {}", filename).as_str());
+                if let Some(app) = self.obj().application().and_downcast::<Application>() {
+                    app.show_downloads();
+                }
             }
             Message::Log(msg) => {
                 self.log(msg.as_str());
@@ -639,8 +1413,14 @@ impl BrowserWindow {
         }
     }
 
+    /// Returns the id of the tab that is currently shown
+    pub(crate) fn current_tab_id(&self) -> Option<TabId> {
+        let page_num = self.tab_bar.current_page()?;
+        self.tab_bar.nth_page(Some(page_num))?.get_tab_id()
+    }
+
     /// Retrieves the page number for the given TabID
-    fn get_page_num_for_tab(&self, tab_id: TabId) -> Option<u32> {
+    pub(crate) fn get_page_num_for_tab(&self, tab_id: TabId) -> Option<u32> {
         for i in 0..self.tab_bar.pages().n_items() {
             let page = self.tab_bar.nth_page(Some(i)).unwrap();
             if page.get_tab_id().unwrap() == tab_id {
@@ -652,14 +1432,16 @@ impl BrowserWindow {
     }
 
     /// Opens a new tab at the given position, with the given URL and title. If the position is None,
-    /// the tab will be added at the end of the tab-bar.
-    fn open_tab(&self, position: Option<usize>, url_str: &str, title: &str) {
+    /// the tab will be added at the end of the tab-bar. The tab fetches its resources within the
+    /// given network profile. Returns the id of the new tab.
+    fn open_tab(&self, position: Option<usize>, url_str: &str, title: &str, network: NetworkKind) -> Option<TabId> {
         let Ok((render_mode, url)) = GosubAddressParser::parse(url_str) else {
             self.log("Cannot parse URL");
-            return;
+            return None;
         };
 
         let mut tab = GosubTab::new(url, title);
+        tab.set_network_kind(network);
         let tab_id = tab.id();
 
         // add tab to manager, and notify the tab has changed. This will update the
@@ -673,11 +1455,38 @@ impl BrowserWindow {
         self.refresh_tabs();
 
         // Async load the favicon and the url contents
+        self.pending_visits.borrow_mut().insert(tab_id, Transition::Link);
         self.load_favicon_async(tab_id);
         self.load_url_async(tab_id);
+
+        Some(tab_id)
     }
 }
 
+/// Creates a drawing area that renders the tree of the given drawer. The matches of the find bar are
+/// highlighted on top of the page of the given tab.
+fn create_drawing_area(drawer: PageDrawer, tab_id: TabId, page_matches: SharedPageMatches) -> DrawingArea {
+    let area = DrawingArea::default();
+    unsafe {
+        // The find bar searches the drawer of the page that is shown, see `page_drawer`
+        area.set_qdata(*PAGE_DRAWER_QUARK, drawer.clone());
+    }
+    area.set_draw_func(move |_area, cr, width, height| {
+        let mut drawer_lock = drawer.lock().unwrap();
+
+        if let Some(drawer) = drawer_lock.as_mut() {
+            draw_tree(drawer, cr, width as u32, height as u32);
+        }
+        drop(drawer_lock);
+
+        if let Some((_, matches)) = page_matches.borrow().as_ref().filter(|(id, _)| *id == tab_id) {
+            draw_page_matches(cr, matches);
+        }
+    });
+
+    area
+}
+
 fn create_view_mode(lang: &str, content: &str) -> View {
     let lang = LanguageManager::default().language(lang);
     let buf = sourceview5::Buffer::builder()
@@ -707,33 +1516,11 @@ fn create_view_mode(lang: &str, content: &str) -> View {
     view
 }
 
-fn load_about_url(url: String) -> String {
-    match url.as_str() {
-        "blank" => r#"
-            <html>
-                <head>
-                    <title>Blank page</title>
-                </head>
-                <body>
-                    <h1>Blank page</h1>
-                    <p>This is a blank page</p>
-                </body>
-            </html>
-            "#
-        .to_string(),
-        _ => r#"
-            <html>
-                <head>
-                    <title>Unknown about: page</title>
-                </head>
-                <body>
-                    <h1>Unknown about: page</h1>
-                    <p>This is an unknown about: page</p>
-                </body>
-            </html>
-            "#
-        .to_string(),
-    }
+/// Pretty-prints the given JSON. Content that is not valid JSON is returned as-is.
+fn pretty_print_json(content: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(content)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| content.to_string())
 }
 
 /// Fetches the title from a HTML code snippet, or returns None if no title is found
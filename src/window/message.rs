@@ -1,19 +1,35 @@
+use crate::containers::ContainerId;
 use crate::tab::TabId;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use url::Url;
 
 pub enum Message {
     /// Open a new tab, and load a URL
     OpenTab(String, String),
+    /// Open a new private tab, and load a URL
+    OpenPrivateTab(String, String),
     /// Opens a new tab on the right side of the given TabID
     OpenTabRight(TabId, String, String),
+    /// Reopens the given tab in a container (or outside any container when None)
+    ReopenInContainer(TabId, Option<ContainerId>),
     /// Sent when we need to load a new url into a tab
     LoadUrl(TabId, String),
+    /// Sent when a link on the page of a tab has been clicked, with the resolved URL of the link
+    FollowLink(TabId, Url),
+    /// Navigates the tab to the given entry of its session history
+    NavigateHistory(TabId, usize),
+    /// Loads a restored tab that has not been loaded yet
+    LoadTab(TabId),
+    /// Loads the current URL of the tab again
+    ReloadTab(TabId),
 
     /// Sent when a favicon has been loaded for tab X
     FaviconLoaded(TabId, Vec<u8>),
     /// Sent when a URL has been loaded for tab X
     UrlLoaded(TabId, String),
+    /// Sent when the URL of tab X turned out to be a download, with the name of the file
+    DownloadStarted(TabId, String),
     /// Refresh tabs
     RefreshTabs(),
 
@@ -30,10 +46,17 @@ impl Debug for Message {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Message::OpenTab(url, title) => write!(f, "OpenTab({} {})", url, title),
+            Message::OpenPrivateTab(url, title) => write!(f, "OpenPrivateTab({} {})", url, title),
             Message::OpenTabRight(tab_id, url, title) => write!(f, "OpenTabRight({:?}, {} {})", tab_id, url, title),
+            Message::ReopenInContainer(tab_id, container) => write!(f, "ReopenInContainer({:?}, {:?})", tab_id, container),
             Message::LoadUrl(tab_id, url) => write!(f, "LoadUrl({:?}, {})", tab_id, url),
+            Message::FollowLink(tab_id, url) => write!(f, "FollowLink({:?}, {})", tab_id, url),
+            Message::NavigateHistory(tab_id, index) => write!(f, "NavigateHistory({:?}, {})", tab_id, index),
+            Message::LoadTab(tab_id) => write!(f, "LoadTab({:?})", tab_id),
+            Message::ReloadTab(tab_id) => write!(f, "ReloadTab({:?})", tab_id),
             Message::FaviconLoaded(tab_id, favicon) => write!(f, "FaviconLoaded({:?}, {} bytes)", tab_id, favicon.len()),
             Message::UrlLoaded(tab_id, content) => write!(f, "UrlLoaded({:?}, {} bytes)", tab_id, content.len()),
+            Message::DownloadStarted(tab_id, filename) => write!(f, "DownloadStarted({:?}, {})", tab_id, filename),
             Message::RefreshTabs() => write!(f, "RefreshTabs()"),
             Message::Log(msg) => write!(f, "Log({})", msg),
             Message::PinTab(tab_id) => write!(f, "PinTab({:?})", tab_id),
@@ -0,0 +1,102 @@
+use crate::engine::{link_areas, GosubEngineConfig};
+use crate::links::{link_at, resolve_link, LinkArea};
+use crate::runtime;
+use crate::tab::TabId;
+use crate::window::message::Message;
+use crate::window::BrowserWindow;
+use gosub_engine::prelude::*;
+use gtk4::glib::clone;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::ObjectSubclassIsExt;
+use gtk4::{gdk, DrawingArea, EventControllerMotion, GestureClick};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Makes the links on a rendered page clickable. Clicking a link opens it in the tab, middle-clicking
+/// or Ctrl+clicking opens it in a new tab next to it. Hovering a link shows where it goes in the
+/// statusbar.
+pub(crate) fn setup_page_links(
+    window: &BrowserWindow,
+    area: &DrawingArea,
+    drawer: Arc<Mutex<Option<<GosubEngineConfig as HasTreeDrawer>::TreeDrawer>>>,
+    tab_id: TabId,
+    base: Url,
+) {
+    // The links are collected from the render tree when they are needed. The page is laid out again
+    // when the drawing area is resized, so they are collected again after that.
+    let areas: Rc<RefCell<Option<Vec<LinkArea>>>> = Rc::new(RefCell::new(None));
+    area.connect_resize({
+        let areas = areas.clone();
+        move |_, _, _| {
+            areas.replace(None);
+        }
+    });
+
+    let find_link = Rc::new(move |x: f64, y: f64| {
+        let mut areas = areas.borrow_mut();
+        let areas = areas.get_or_insert_with(|| drawer.lock().unwrap().as_mut().map(link_areas).unwrap_or_default());
+        link_at(areas, x, y).and_then(|area| resolve_link(&base, &area.href))
+    });
+
+    let motion = EventControllerMotion::new();
+    motion.connect_motion(clone!(
+        #[weak]
+        window,
+        #[weak]
+        area,
+        #[strong]
+        find_link,
+        move |_, x, y| {
+            show_link(&window, &area, find_link(x, y).as_ref());
+        }
+    ));
+    motion.connect_leave(clone!(
+        #[weak]
+        window,
+        #[weak]
+        area,
+        move |_| {
+            show_link(&window, &area, None);
+        }
+    ));
+    area.add_controller(motion);
+
+    let click = GestureClick::new();
+    // Listen to all buttons, so middle clicks are handled as well
+    click.set_button(0);
+    click.connect_released(clone!(
+        #[weak]
+        window,
+        #[weak]
+        area,
+        move |gesture, _, x, y| {
+            let Some(url) = find_link(x, y) else {
+                return;
+            };
+
+            let ctrl = gesture.current_event_state().contains(gdk::ModifierType::CONTROL_MASK);
+            let message = match gesture.current_button() {
+                gdk::BUTTON_PRIMARY if !ctrl => Message::FollowLink(tab_id, url),
+                gdk::BUTTON_PRIMARY | gdk::BUTTON_MIDDLE => Message::OpenTabRight(tab_id, url.to_string(), "New Tab".into()),
+                _ => return,
+            };
+
+            // The page is replaced when the link is opened in this tab, so the link is no longer hovered
+            show_link(&window, &area, None);
+
+            let sender = window.imp().get_sender();
+            runtime().spawn(async move {
+                sender.send(message).await.unwrap();
+            });
+        }
+    ));
+    area.add_controller(click);
+}
+
+/// Shows the pointer cursor and the URL of the link in the statusbar while a link is hovered
+fn show_link(window: &BrowserWindow, area: &DrawingArea, link: Option<&Url>) {
+    area.set_cursor_from_name(link.map(|_| "pointer"));
+    window.imp().statusbar.set_text(link.map_or("", Url::as_str));
+}
@@ -1,8 +1,9 @@
+use crate::containers::{container_store, ContainerId};
 use crate::runtime;
 use crate::tab::TabId;
 use crate::window::message::Message;
-use gtk4::gio::{Menu, SimpleAction, SimpleActionGroup};
-use gtk4::glib::clone;
+use gtk4::gio::{Menu, MenuItem, SimpleAction, SimpleActionGroup};
+use gtk4::glib::{clone, VariantTy};
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 
@@ -20,6 +21,8 @@ pub(crate) struct TabInfo {
     pub(crate) is_right: bool,
     /// Number of total tabs
     pub(crate) tab_count: usize,
+    /// There are closed tabs that can be reopened
+    pub(crate) has_closed_tabs: bool,
 }
 
 pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, window: &super::BrowserWindow, info: TabInfo) {
@@ -46,35 +49,16 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
     let window_clone = window.clone();
     let reload_tab = SimpleAction::new("reload", None);
     reload_tab.connect_activate(move |_, _| {
-        let manager = window_clone.imp().tab_manager.lock().unwrap();
-        if let Some(tab) = manager.get_tab(info.id) {
-            let sender = window_clone.imp().sender.clone();
-            runtime().spawn(clone!(
-                #[strong]
-                sender,
-                async move {
-                    sender.send(Message::LoadUrl(info.id, tab.url().to_string())).await.unwrap();
-                }
-            ));
-        }
-    });
-    action_group.add_action(&reload_tab);
-
-    // Mute Tab
-    let window_clone = window.clone();
-    let mute_tab = SimpleAction::new("mute", None);
-    mute_tab.connect_activate(move |_, _| {
-        // @todo: implement mute tab
         let sender = window_clone.imp().sender.clone();
         runtime().spawn(clone!(
             #[strong]
             sender,
             async move {
-                sender.send(Message::Log("Tab should be muted".into())).await.unwrap();
+                sender.send(Message::ReloadTab(info.id)).await.unwrap();
             }
         ));
     });
-    action_group.add_action(&mute_tab);
+    action_group.add_action(&reload_tab);
 
     // Pin Tab
     let pin_tab = SimpleAction::new("pin", None);
@@ -116,17 +100,40 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
     let window_clone = window.clone();
     let duplicate_tab = SimpleAction::new("duplicate", None);
     duplicate_tab.connect_activate(move |_, _| {
-        // @todo: implement duplicate tab
+        window_clone.imp().duplicate_tab(info.id);
+    });
+    action_group.add_action(&duplicate_tab);
+
+    // Move to New Window
+    let window_clone = window.clone();
+    let move_to_new_window = SimpleAction::new("move-to-new-window", None);
+    if info.tab_count == 1 {
+        move_to_new_window.set_enabled(false);
+    }
+    move_to_new_window.connect_activate(move |_, _| {
+        window_clone.imp().move_tab_to_new_window(info.id);
+    });
+    action_group.add_action(&move_to_new_window);
+
+    // Reopen in Container. The parameter is the container id, or an empty string for no container.
+    let window_clone = window.clone();
+    let reopen_in_container = SimpleAction::new("reopen-in-container", Some(VariantTy::STRING));
+    reopen_in_container.connect_activate(move |_, param| {
+        let Some(container) = param.and_then(|p| p.get::<String>()) else {
+            return;
+        };
+        let container = container.parse::<ContainerId>().ok();
+
         let sender = window_clone.imp().sender.clone();
         runtime().spawn(clone!(
             #[strong]
             sender,
             async move {
-                sender.send(Message::Log("Tab should be duplicated".into())).await.unwrap();
+                sender.send(Message::ReopenInContainer(info.id, container)).await.unwrap();
             }
         ));
     });
-    action_group.add_action(&duplicate_tab);
+    action_group.add_action(&reopen_in_container);
 
     // Close Tab
     let close_tab = SimpleAction::new("close", None);
@@ -138,9 +145,13 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
     action_group.add_action(&close_tab);
 
     // Reopen Closed Tab
+    let window_clone = window.clone();
     let reopen_closed_tab = SimpleAction::new("reopen", None);
+    if !info.has_closed_tabs {
+        reopen_closed_tab.set_enabled(false);
+    }
     reopen_closed_tab.connect_activate(move |_, _| {
-        // @todo: implement reopen closed tab
+        window_clone.imp().reopen_closed_tab(0);
     });
     action_group.add_action(&reopen_closed_tab);
 
@@ -151,29 +162,7 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
         close_tabs_left.set_enabled(false);
     }
     close_tabs_left.connect_activate(move |_, _| {
-        let mut tabs_to_close = vec![];
-
-        let manager = window_clone.imp().tab_manager.lock().unwrap();
-        for tab_id in manager.order() {
-            if let Some(tab) = manager.get_tab(tab_id) {
-                // pinned tab, we cannot close
-                if !tab.is_pinned() {
-                    continue;
-                }
-                // our tab is found, so break the loop
-                if tab_id == info.id {
-                    break;
-                }
-                // Just add this tab to the list
-                tabs_to_close.push(tab_id);
-            }
-        }
-        drop(manager);
-
-        // close all the tabs we need to close
-        for tab_id in tabs_to_close {
-            window_clone.imp().close_tab(tab_id);
-        }
+        window_clone.imp().close_tabs(|manager| manager.close_tabs_left(info.id));
         _ = window_clone.imp().get_sender().send_blocking(Message::RefreshTabs());
     });
     action_group.add_action(&close_tabs_left);
@@ -185,26 +174,7 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
         close_tabs_right.set_enabled(false);
     }
     close_tabs_right.connect_activate(move |_, _| {
-        let mut own_tab_found = false;
-        let mut tabs_to_close = vec![];
-
-        let manager = window_clone.imp().tab_manager.lock().unwrap();
-        for tab_id in manager.order() {
-            if tab_id == info.id {
-                own_tab_found = true;
-                continue;
-            }
-
-            if own_tab_found {
-                tabs_to_close.push(tab_id);
-            }
-        }
-        drop(manager);
-
-        // close all the tabs we need to close
-        for tab_id in tabs_to_close {
-            window_clone.imp().close_tab(tab_id);
-        }
+        window_clone.imp().close_tabs(|manager| manager.close_tabs_right(info.id));
         _ = window_clone.imp().get_sender().send_blocking(Message::RefreshTabs());
     });
     action_group.add_action(&close_tabs_right);
@@ -216,16 +186,19 @@ pub(crate) fn setup_context_menu_actions(action_group: &SimpleActionGroup, windo
         close_other_tabs.set_enabled(false);
     }
     close_other_tabs.connect_activate(move |_, _| {
-        let manager = window_clone.imp().tab_manager.lock().unwrap();
-        let tabs = manager.order();
-        for tab_id in tabs {
-            if tab_id != info.id {
-                window_clone.imp().close_tab(tab_id);
-            }
-        }
+        window_clone.imp().close_tabs(|manager| manager.close_other_tabs(info.id));
         _ = window_clone.imp().get_sender().send_blocking(Message::RefreshTabs());
     });
     action_group.add_action(&close_other_tabs);
+
+    // Close All Tabs (pinned tabs stay open)
+    let window_clone = window.clone();
+    let close_all_tabs = SimpleAction::new("close-all", None);
+    close_all_tabs.connect_activate(move |_, _| {
+        window_clone.imp().close_tabs(|manager| manager.close_all_tabs());
+        _ = window_clone.imp().get_sender().send_blocking(Message::RefreshTabs());
+    });
+    action_group.add_action(&close_all_tabs);
 }
 
 pub(crate) fn build_context_menu(tab_info: TabInfo) -> Menu {
@@ -237,13 +210,24 @@ pub(crate) fn build_context_menu(tab_info: TabInfo) -> Menu {
 
     let section = Menu::new();
     section.append(Some("Reload Tab"), Some("tab.reload"));
-    section.append(Some("Mute Tab"), Some("tab.mute"));
     if tab_info.is_pinned {
         section.append(Some("Unpin Tab"), Some("tab.unpin"));
     } else {
         section.append(Some("Pin Tab"), Some("tab.pin"));
     }
     section.append(Some("Duplicate Tab"), Some("tab.duplicate"));
+    section.append(Some("Move to New Window"), Some("tab.move-to-new-window"));
+
+    let submenu = Menu::new();
+    let item = MenuItem::new(Some("No Container"), None);
+    item.set_action_and_target_value(Some("tab.reopen-in-container"), Some(&"".to_variant()));
+    submenu.append_item(&item);
+    for container in container_store().all() {
+        let item = MenuItem::new(Some(container.name.as_str()), None);
+        item.set_action_and_target_value(Some("tab.reopen-in-container"), Some(&container.id.to_string().to_variant()));
+        submenu.append_item(&item);
+    }
+    section.append_submenu(Some("Reopen in Container"), &submenu);
     menu.append_section(None, &section);
 
     let section = Menu::new();
@@ -253,10 +237,9 @@ pub(crate) fn build_context_menu(tab_info: TabInfo) -> Menu {
     submenu.append(Some("Close Tabs to Left"), Some("tab.close-left"));
     submenu.append(Some("Close Tabs to Right"), Some("tab.close-right"));
     submenu.append(Some("Close Other Tabs"), Some("tab.close-others"));
+    submenu.append(Some("Close All Tabs"), Some("tab.close-all"));
     section.append_submenu(Some("Close Other Tabs"), &submenu);
 
-    // @todo: we should only be allowed to reopen closed tab, after we have closed one..
-    // this functionality is not yet implemented
     section.append(Some("Reopen Closed Tab"), Some("tab.reopen"));
     menu.append_section(None, &section);
 
@@ -0,0 +1,57 @@
+-- A trimmed down Firefox places.sqlite, with only the tables and columns the importer reads
+CREATE TABLE moz_places (
+    id INTEGER PRIMARY KEY,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    visit_count INTEGER DEFAULT 0
+);
+
+CREATE TABLE moz_bookmarks (
+    id INTEGER PRIMARY KEY,
+    type INTEGER,
+    fk INTEGER DEFAULT NULL,
+    parent INTEGER,
+    position INTEGER,
+    title LONGVARCHAR,
+    keyword_id INTEGER,
+    folder_type TEXT,
+    dateAdded INTEGER,
+    lastModified INTEGER,
+    guid TEXT
+);
+
+CREATE TABLE moz_keywords (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    keyword TEXT UNIQUE,
+    place_id INTEGER,
+    post_data TEXT
+);
+
+INSERT INTO moz_places (id, url, title) VALUES
+    (1, 'https://gosub.io/', 'Gosub'),
+    (2, 'https://docs.rs/', 'Docs.rs'),
+    (3, 'https://example.com/', 'Example'),
+    (4, 'place:sort=8&maxResults=10', 'Recent Tags');
+
+INSERT INTO moz_bookmarks (id, type, fk, parent, position, title, dateAdded, lastModified, guid) VALUES
+    (1, 2, NULL, 0, 0, '', 1699999000000000, 1699999000000000, 'root________'),
+    (2, 2, NULL, 1, 0, 'menu', 1699999000000000, 1699999000000000, 'menu________'),
+    (3, 2, NULL, 1, 1, 'toolbar', 1699999000000000, 1699999000000000, 'toolbar_____'),
+    (4, 2, NULL, 1, 2, 'tags', 1699999000000000, 1699999000000000, 'tags________'),
+    (5, 2, NULL, 1, 3, 'unfiled', 1699999000000000, 1699999000000000, 'unfiled_____'),
+    (6, 2, NULL, 1, 4, 'mobile', 1699999000000000, 1699999000000000, 'mobile______'),
+
+    (10, 1, 1, 3, 0, 'Gosub', 1700000000000000, 1700000000000000, 'gosubBookmrk'),
+    (11, 2, NULL, 3, 1, 'Rust', 1700000100000000, 1700000200000000, 'rustFolder__'),
+    (12, 1, 2, 11, 0, 'Docs.rs', 1700000200000000, 1700000200000000, 'docsBookmrk_'),
+
+    (13, 3, NULL, 2, 0, '', 1700000300000000, 1700000300000000, 'separator___'),
+    (14, 1, 4, 2, 1, 'Recent Tags', 1700000300000000, 1700000300000000, 'recentTags__'),
+    (15, 1, 3, 2, 2, 'Example', 1700000400000000, 1700000400000000, 'exampleBkmrk'),
+
+    (20, 2, NULL, 4, 0, 'rust', 1700000000000000, 1700000000000000, 'tagRust_____'),
+    (21, 2, NULL, 4, 1, 'browser', 1700000000000000, 1700000000000000, 'tagBrowser__'),
+    (22, 1, 1, 20, 0, NULL, 1700000000000000, 1700000000000000, 'tagRustGosub'),
+    (23, 1, 1, 21, 0, NULL, 1700000000000000, 1700000000000000, 'tagBrowserGs');
+
+INSERT INTO moz_keywords (keyword, place_id) VALUES ('gs', 1);